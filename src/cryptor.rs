@@ -1,9 +1,6 @@
 // TODO: Note that Apple's implementation will malloc/copy keys to an aligned
 // buffer if necessary.
 
-// TODO: enum of all algorithms so that we can force iv, padding, etc. to be
-// provided if required.
-
 use std::{ffi::c_void, fmt::Display};
 
 #[repr(C)]
@@ -15,7 +12,7 @@ enum Operation {
 
 #[repr(u32)]
 #[non_exhaustive]
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Mode {
     ECB = 1,
     CBC = 2,
@@ -24,14 +21,18 @@ pub enum Mode {
     // F8 = 5,
     // LRW = 6,
     OFB = 7,
+    /// Not reachable through the [`Config`] builders, as the cryptor doesn't
+    /// accept the tweak key, and rejected by
+    /// [`CipherSpec`](crate::spec::CipherSpec). Kept for compatibility.
     XTS = 8,
-    /// Must be specified for RC4, and must not be specified for others.
-    // RC4 = 9,
     CFB8 = 10,
 }
 
+/// Must be specified for RC4, and must not be specified for others.
+const RC4_MODE: u32 = 9;
+
 #[repr(u32)]
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum Padding {
     None = 0,
@@ -90,6 +91,10 @@ pub enum CryptorError {
     CallSequence,
     KeySize,
     Key,
+    Overflow,
+    /// Only returned by [`CipherSpec`](crate::spec::CipherSpec), when given an
+    /// initialization vector for ECB mode, which the [`Config`] builders
+    /// prevent at compile time.
     InitializationVectorPresent,
     Unexpected(i32),
 }
@@ -109,6 +114,7 @@ impl Display for CryptorError {
             Self::CallSequence => "call sequence failure",
             Self::KeySize => "key size is invalid",
             Self::Key => "key is invalid",
+            Self::Overflow => "operation will result in an overflow",
             Self::InitializationVectorPresent => "ECB mode does not support initialization vectors",
             Self::Unexpected(code) => {
                 let s = format!("unexpected error {}", code);
@@ -123,7 +129,7 @@ impl Display for CryptorError {
 #[allow(dead_code)]
 #[derive(Copy, Clone, Debug, PartialEq)]
 #[repr(i32)]
-enum Status {
+pub(crate) enum Status {
     Success = 0,
     ParamError = -4300,
     BufferTooSmall = -4301,
@@ -152,96 +158,92 @@ impl From<Status> for CryptorError {
             Status::CallSequenceError => Self::CallSequence,
             Status::KeySizeError => Self::KeySize,
             Status::InvalidKey => Self::Key,
+            Status::Overflow => Self::Overflow,
             _ => Self::Unexpected(status as i32),
         }
     }
 }
 
+#[repr(u32)]
 #[non_exhaustive]
-#[derive(Clone, Copy, Debug, PartialEq)]
-/// The configuration for a [`Cryptor`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    AES = 0,
+    DES = 1,
+    TDES = 2,
+    CAST = 3,
+    RC4 = 4,
+    RC2 = 5,
+    Blowfish = 6,
+}
+
+/// The configuration for a [`Cryptor`], created by choosing an algorithm and
+/// then a mode.
 ///
 /// ```
-/// # use common_crypto::cryptor::{Config, Mode};
-/// let config = Config::AES256 {
-///     mode: Mode::CTR,
-///     iv: Some(b"use random iv :)"),
-///     key: b"aes128 key must be 32 bytes long",
-/// };
+/// # use common_crypto::cryptor::{Config, Padding};
+/// let config = Config::aes256(b"aes256 key must be 32 bytes long")
+///     .cbc(b"use random iv :)", Padding::PKCS7);
 /// ```
-// TODO: padding, rounds
-pub enum Config<'a> {
-    AES128 {
-        mode: Mode,
-        iv: Option<&'a [u8; 16]>,
-        key: &'a [u8; 16],
-    },
-    AES192 {
-        mode: Mode,
-        iv: Option<&'a [u8; 16]>,
-        key: &'a [u8; 24],
-    },
-    AES256 {
-        mode: Mode,
-        iv: Option<&'a [u8; 16]>,
-        key: &'a [u8; 32],
-    },
-    DES {
-        mode: Mode,
-        iv: Option<&'a [u8; 8]>,
-        key: &'a [u8; 8],
-    },
-    TDES {
-        mode: Mode,
-        iv: Option<&'a [u8; 8]>,
-        key: &'a [u8; 24],
-    },
-    CAST {
-        mode: Mode,
-        iv: Option<&'a [u8; 8]>,
-        /// Valid key sizes are between 5 and 24.
-        key: &'a [u8],
-        padding: Padding,
-    },
-    RC4 {
-        /// Valid key sizes are between 1 and 512.
-        key: &'a [u8],
-    },
-    RC2 {
-        mode: Mode,
-        iv: Option<&'a [u8; 8]>,
-        /// Valid key sizes are between 1 and 128.
-        key: &'a [u8],
-    },
-    Blowfish {
-        mode: Mode,
-        iv: Option<&'a [u8; 8]>,
-        /// Valid key sizes are between 8 and 56.
-        key: &'a [u8],
-    },
+///
+/// Only valid combinations can be expressed: RC4 has no mode, ECB doesn't
+/// accept an initialization vector, the other modes require one of the block
+/// size, and padding can only be chosen for ECB and CBC.
+// TODO: rounds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config<'a> {
+    algorithm: Algorithm,
+    /// `None` for stream ciphers.
+    mode: Option<Mode>,
+    key: &'a [u8],
+    iv: Option<&'a [u8]>,
+    padding: Padding,
 }
 
-impl<'a> From<&Config<'a>> for u32 {
-    fn from(config: &Config) -> Self {
-        match config {
-            Config::AES128 { .. } => 0,
-            Config::AES192 { .. } => 0,
-            Config::AES256 { .. } => 0,
-            Config::DES { .. } => 1,
-            Config::TDES { .. } => 2,
-            Config::CAST { .. } => 3,
-            Config::RC4 { .. } => 4,
-            Config::RC2 { .. } => 5,
-            Config::Blowfish { .. } => 6,
-        }
+impl<'a> Config<'a> {
+    pub fn aes128(key: &'a [u8; 16]) -> BlockCipher<'a, 16> {
+        BlockCipher::new(Algorithm::AES, key)
     }
-}
 
-impl<'a> Config<'a> {
-    fn padding(&self) -> Padding {
-        match self {
-            Config::CAST { padding, .. } => *padding,
-            _ => Padding::None,
+    pub fn aes192(key: &'a [u8; 24]) -> BlockCipher<'a, 16> {
+        BlockCipher::new(Algorithm::AES, key)
+    }
+
+    pub fn aes256(key: &'a [u8; 32]) -> BlockCipher<'a, 16> {
+        BlockCipher::new(Algorithm::AES, key)
+    }
+
+    pub fn des(key: &'a [u8; 8]) -> BlockCipher<'a, 8> {
+        BlockCipher::new(Algorithm::DES, key)
+    }
+
+    pub fn tdes(key: &'a [u8; 24]) -> BlockCipher<'a, 8> {
+        BlockCipher::new(Algorithm::TDES, key)
+    }
+
+    /// Valid key sizes are between 5 and 16.
+    pub fn cast(key: &'a [u8]) -> BlockCipher<'a, 8> {
+        BlockCipher::new(Algorithm::CAST, key)
+    }
+
+    /// Valid key sizes are between 1 and 128.
+    pub fn rc2(key: &'a [u8]) -> BlockCipher<'a, 8> {
+        BlockCipher::new(Algorithm::RC2, key)
+    }
+
+    /// Valid key sizes are between 8 and 56.
+    pub fn blowfish(key: &'a [u8]) -> BlockCipher<'a, 8> {
+        BlockCipher::new(Algorithm::Blowfish, key)
+    }
+
+    /// Valid key sizes are between 1 and 512.
+    pub fn rc4(key: &'a [u8]) -> Self {
+        Self {
+            algorithm: Algorithm::RC4,
+            mode: None,
+            key,
+            iv: None,
+            padding: Padding::None,
         }
     }
 
@@ -249,52 +251,85 @@ impl<'a> Config<'a> {
         0
     }
 
-    const fn mode(&self) -> u32 {
-        match self {
-            Config::AES128 { mode, .. } => *mode as u32,
-            Config::AES192 { mode, .. } => *mode as u32,
-            Config::AES256 { mode, .. } => *mode as u32,
-            Config::DES { mode, .. } => *mode as u32,
-            Config::TDES { mode, .. } => *mode as u32,
-            Config::CAST { mode, .. } => *mode as u32,
-            Config::RC4 { .. } => 9,
-            Config::RC2 { mode, .. } => *mode as u32,
-            Config::Blowfish { mode, .. } => *mode as u32,
+    pub(crate) const fn mode(&self) -> Option<Mode> {
+        self.mode
+    }
+
+    fn iv_ptr(&self) -> *const c_void {
+        self.iv
+            .map_or(std::ptr::null(), |iv| iv.as_ptr() as *const c_void)
+    }
+
+    /// The block size in bytes, or `None` for stream ciphers.
+    pub(crate) const fn block_size(&self) -> Option<usize> {
+        match self.algorithm {
+            Algorithm::AES => Some(16),
+            Algorithm::RC4 => None,
+            _ => Some(8),
         }
     }
 
-    fn iv_ptr(&self) -> Result<*const u8, CryptorError> {
-        let (mode, ptr) = match self {
-            Config::AES128 { mode, iv, .. } if iv.is_some() => (mode, iv.unwrap().as_ptr()),
-            Config::AES192 { mode, iv, .. } if iv.is_some() => (mode, iv.unwrap().as_ptr()),
-            Config::AES256 { mode, iv, .. } if iv.is_some() => (mode, iv.unwrap().as_ptr()),
-            Config::DES { mode, iv, .. } if iv.is_some() => (mode, iv.unwrap().as_ptr()),
-            Config::CAST { mode, iv, .. } if iv.is_some() => (mode, iv.unwrap().as_ptr()),
-            Config::RC2 { mode, iv, .. } if iv.is_some() => (mode, iv.unwrap().as_ptr()),
-            Config::Blowfish { mode, iv, .. } if iv.is_some() => (mode, iv.unwrap().as_ptr()),
-            _ => return Ok(std::ptr::null()),
-        };
+    pub(crate) const fn iv(&self) -> Option<&'a [u8]> {
+        self.iv
+    }
 
-        if mode == &Mode::ECB {
-            Err(CryptorError::InitializationVectorPresent)
-        } else {
-            Ok(ptr)
+    /// A copy of the config using ECB mode without padding.
+    pub(crate) fn to_ecb(self) -> Self {
+        Self {
+            mode: self.mode.map(|_| Mode::ECB),
+            iv: None,
+            padding: Padding::None,
+            ..self
         }
     }
+}
+
+/// A block cipher for which a mode hasn't been chosen yet. Initialization
+/// vectors must be the size of a block.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockCipher<'a, const BLOCK_SIZE: usize> {
+    algorithm: Algorithm,
+    key: &'a [u8],
+}
 
-    fn key(&self) -> &[u8] {
-        match self {
-            Config::AES128 { key, .. } => *key,
-            Config::AES192 { key, .. } => *key,
-            Config::AES256 { key, .. } => *key,
-            Config::DES { key, .. } => *key,
-            Config::TDES { key, .. } => *key,
-            Config::CAST { key, .. } => *key,
-            Config::RC4 { key, .. } => *key,
-            Config::RC2 { key, .. } => *key,
-            Config::Blowfish { key, .. } => *key,
+impl<'a, const BLOCK_SIZE: usize> BlockCipher<'a, BLOCK_SIZE> {
+    const fn new(algorithm: Algorithm, key: &'a [u8]) -> Self {
+        Self { algorithm, key }
+    }
+
+    const fn with_mode(self, mode: Mode, iv: Option<&'a [u8]>, padding: Padding) -> Config<'a> {
+        Config {
+            algorithm: self.algorithm,
+            mode: Some(mode),
+            key: self.key,
+            iv,
+            padding,
         }
     }
+
+    pub const fn ecb(self, padding: Padding) -> Config<'a> {
+        self.with_mode(Mode::ECB, None, padding)
+    }
+
+    pub const fn cbc(self, iv: &'a [u8; BLOCK_SIZE], padding: Padding) -> Config<'a> {
+        self.with_mode(Mode::CBC, Some(iv), padding)
+    }
+
+    pub const fn cfb(self, iv: &'a [u8; BLOCK_SIZE]) -> Config<'a> {
+        self.with_mode(Mode::CFB, Some(iv), Padding::None)
+    }
+
+    pub const fn cfb8(self, iv: &'a [u8; BLOCK_SIZE]) -> Config<'a> {
+        self.with_mode(Mode::CFB8, Some(iv), Padding::None)
+    }
+
+    pub const fn ofb(self, iv: &'a [u8; BLOCK_SIZE]) -> Config<'a> {
+        self.with_mode(Mode::OFB, Some(iv), Padding::None)
+    }
+
+    pub const fn ctr(self, iv: &'a [u8; BLOCK_SIZE]) -> Config<'a> {
+        self.with_mode(Mode::CTR, Some(iv), Padding::None)
+    }
 }
 
 /// A cryptor supporting all of the block and stream ciphers provided by the
@@ -302,19 +337,26 @@ impl<'a> Config<'a> {
 ///
 /// ```
 /// # use common_crypto::cryptor::{Config, Cryptor};
-/// let config = Config::RC4 { key: b"Key" };
+/// let config = Config::rc4(b"Key");
 /// assert_eq!(
 ///     Cryptor::encrypt(&config, b"Plaintext").unwrap(),
 ///     &[0xbb, 0xf3, 0x16, 0xe8, 0xd9, 0x40, 0xaf, 0x0a, 0xd3]
 /// );
 /// ```
-
+///
+/// Cryptors can be moved between threads, but aren't `Sync` because
+/// [`Cryptor::update`] modifies the underlying cryptor through a shared
+/// reference.
 #[derive(Debug)]
 pub struct Cryptor {
     handle: CCCryptorRef,
 }
 
-impl<'a> Drop for Cryptor {
+// The handle is owned exclusively by the cryptor, and Common Crypto doesn't
+// tie cryptors to the thread which created them.
+unsafe impl Send for Cryptor {}
+
+impl Drop for Cryptor {
     fn drop(&mut self) {
         unsafe {
             CCCryptorRelease(self.handle);
@@ -329,12 +371,12 @@ impl Cryptor {
         let status = unsafe {
             CCCryptorCreateWithMode(
                 operation,
-                config.mode(),
-                config.into(),
-                config.padding(),
-                config.iv_ptr()? as *const c_void,
-                config.key().as_ptr() as *const c_void,
-                config.key().len(),
+                config.mode.map_or(RC4_MODE, |mode| mode as u32),
+                config.algorithm as u32,
+                config.padding,
+                config.iv_ptr(),
+                config.key.as_ptr() as *const c_void,
+                config.key.len(),
                 // Tweak is unsued
                 std::ptr::null(),
                 0,
@@ -400,11 +442,13 @@ impl Cryptor {
     pub fn finish(self, output: &mut Vec<u8>) -> Result<(), CryptorError> {
         let mut written = 0usize;
 
+        output.resize(unsafe { CCCryptorGetOutputLength(self.handle, 0, true) }, 0);
+
         let status = unsafe {
             CCCryptorFinal(
                 self.handle,
                 output.as_mut_ptr() as *mut c_void,
-                output.capacity(),
+                output.len(),
                 &mut written as *mut usize,
             )
         };
@@ -418,18 +462,26 @@ impl Cryptor {
 
         Ok(())
     }
+
+    /// Processes all of the input and finishes.
+    fn update_and_finish(self, input: impl AsRef<[u8]>) -> Result<Vec<u8>, CryptorError> {
+        let mut output = Vec::new();
+        self.update(input, &mut output)?;
+
+        let mut remaining = Vec::new();
+        self.finish(&mut remaining)?;
+        output.append(&mut remaining);
+
+        Ok(output)
+    }
 }
 
 impl Cryptor {
     pub fn encrypt(config: &Config<'_>, input: impl AsRef<[u8]>) -> Result<Vec<u8>, CryptorError> {
-        let mut output = Vec::new();
-        Cryptor::new_encryptor(config)?.update(input, &mut output)?;
-        Ok(output)
+        Cryptor::new_encryptor(config)?.update_and_finish(input)
     }
 
     pub fn decrypt(config: &Config<'_>, input: impl AsRef<[u8]>) -> Result<Vec<u8>, CryptorError> {
-        let mut output = Vec::new();
-        Cryptor::new_decryptor(config)?.update(input, &mut output)?;
-        Ok(output)
+        Cryptor::new_decryptor(config)?.update_and_finish(input)
     }
 }
@@ -0,0 +1,231 @@
+//! Counter mode with random access to the keystream.
+//!
+//! Common Crypto's CTR cryptors can only be advanced sequentially, and the
+//! mode options for choosing the counter endianness are deprecated with only
+//! big-endian counters spanning the whole block being supported. Instead the
+//! keystream is produced here by encrypting the counter blocks with an ECB
+//! cryptor, which allows decryption to start at any byte offset and the
+//! counter to be laid out as other implementations expect.
+
+#[cfg(doc)]
+use crate::cryptor::BlockCipher;
+use crate::cryptor::{Config, Cryptor, CryptorError, Mode};
+use std::io::{self, Read, Seek, SeekFrom};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Endianness {
+    /// The counter occupies the end of the block, most significant byte
+    /// first.
+    Big,
+    /// The counter occupies the start of the block, least significant byte
+    /// first.
+    Little,
+}
+
+/// The layout of the counter within the counter block. The rest of the block
+/// is a nonce which is never modified.
+///
+/// ```
+/// # use common_crypto::ctr::{Counter, Endianness};
+/// // A 96-bit nonce followed by a 32-bit counter, as used by GCM.
+/// let counter = Counter {
+///     endianness: Endianness::Big,
+///     bits: 32,
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Counter {
+    pub endianness: Endianness,
+    /// The width of the counter, which must be a multiple of 8 and no larger
+    /// than the block size.
+    pub bits: u32,
+}
+
+/// A CTR mode cipher which can be positioned anywhere in the keystream.
+///
+/// The initialization vector of the [`Config`] is used as the initial counter
+/// block. Encrypting or decrypting past the point where the counter would wrap
+/// fails with [`CryptorError::Overflow`].
+///
+/// ```
+/// # use common_crypto::cryptor::{Config, Cryptor};
+/// # use common_crypto::ctr::CTR;
+/// let config = Config::aes128(b"0123456789abcdef").ctr(b"use random iv :)");
+///
+/// let encrypted = Cryptor::encrypt(&config, b"Hello, world!").unwrap();
+///
+/// let mut ctr = CTR::new(&config).unwrap();
+/// ctr.seek(7);
+/// let mut data = encrypted[7..].to_vec();
+/// ctr.apply_keystream(&mut data).unwrap();
+/// assert_eq!(data, b"world!");
+/// ```
+#[derive(Debug)]
+pub struct CTR {
+    cryptor: Cryptor,
+    block: Vec<u8>,
+    counter: Counter,
+    position: u64,
+}
+
+impl CTR {
+    /// Creates a cipher positioned at the start of the keystream, using a
+    /// big-endian counter spanning the whole block as with [`Mode::CTR`]. The
+    /// config must be created with [`BlockCipher::ctr`].
+    pub fn new(config: &Config<'_>) -> Result<Self, CryptorError> {
+        let bits = config.block_size().unwrap_or_default() as u32 * 8;
+
+        Self::with_counter(
+            config,
+            Counter {
+                endianness: Endianness::Big,
+                bits,
+            },
+        )
+    }
+
+    /// Creates a cipher positioned at the start of the keystream, using the
+    /// given counter layout.
+    pub fn with_counter(config: &Config<'_>, counter: Counter) -> Result<Self, CryptorError> {
+        let block = match (config.mode(), config.iv()) {
+            (Some(Mode::CTR), Some(iv)) => iv.to_vec(),
+            _ => return Err(CryptorError::Param),
+        };
+
+        if counter.bits == 0
+            || !counter.bits.is_multiple_of(8)
+            || counter.bits as usize > block.len() * 8
+        {
+            return Err(CryptorError::Param);
+        }
+
+        Ok(Self {
+            cryptor: Cryptor::new_encryptor(&config.to_ecb())?,
+            block,
+            counter,
+            position: 0,
+        })
+    }
+
+    /// The current offset in the keystream, in bytes.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Moves to the given offset in the keystream, in bytes.
+    pub fn seek(&mut self, offset: u64) {
+        self.position = offset;
+    }
+
+    /// Encrypts or decrypts the data in place, advancing the position by its
+    /// length.
+    pub fn apply_keystream(&mut self, data: &mut [u8]) -> Result<(), CryptorError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let block_size = self.block.len() as u64;
+        let first_block = self.position / block_size;
+        let skip = (self.position % block_size) as usize;
+        let blocks = (skip + data.len()).div_ceil(block_size as usize);
+
+        let initial = self.read_counter();
+        let max = u128::MAX >> (128 - self.counter.bits);
+        let first = initial
+            .checked_add(first_block as u128)
+            .ok_or(CryptorError::Overflow)?;
+        let last = first
+            .checked_add(blocks as u128 - 1)
+            .filter(|last| *last <= max)
+            .ok_or(CryptorError::Overflow)?;
+
+        let mut counters = Vec::with_capacity(blocks * block_size as usize);
+        for value in first..=last {
+            counters.extend_from_slice(&self.counter_block(value));
+        }
+
+        let mut keystream = Vec::new();
+        self.cryptor.update(&counters, &mut keystream)?;
+
+        for (byte, key) in data.iter_mut().zip(&keystream[skip..]) {
+            *byte ^= key;
+        }
+
+        self.position += data.len() as u64;
+
+        Ok(())
+    }
+
+    /// The byte range of the counter within the block.
+    fn counter_range(&self) -> std::ops::Range<usize> {
+        let len = self.counter.bits as usize / 8;
+
+        match self.counter.endianness {
+            Endianness::Big => self.block.len() - len..self.block.len(),
+            Endianness::Little => 0..len,
+        }
+    }
+
+    fn read_counter(&self) -> u128 {
+        let bytes = &self.block[self.counter_range()];
+
+        match self.counter.endianness {
+            Endianness::Big => bytes.iter().fold(0, |n, b| n << 8 | *b as u128),
+            Endianness::Little => bytes.iter().rev().fold(0, |n, b| n << 8 | *b as u128),
+        }
+    }
+
+    /// The initial block with its counter replaced by the given value.
+    fn counter_block(&self, value: u128) -> Vec<u8> {
+        let mut block = self.block.clone();
+        let range = self.counter_range();
+        let len = range.len();
+
+        match self.counter.endianness {
+            Endianness::Big => block[range].copy_from_slice(&value.to_be_bytes()[16 - len..]),
+            Endianness::Little => block[range].copy_from_slice(&value.to_le_bytes()[..len]),
+        }
+
+        block
+    }
+}
+
+/// Decrypts a CTR mode ciphertext as it's read, with support for seeking.
+///
+/// The ciphertext is expected to start at offset 0 of the inner reader.
+#[derive(Debug)]
+pub struct CTRReader<R> {
+    inner: R,
+    ctr: CTR,
+}
+
+impl<R> CTRReader<R> {
+    /// The cipher must be positioned to match the inner reader, which is the
+    /// case for a newly created [`CTR`] and a reader at the start of the
+    /// ciphertext.
+    pub fn new(inner: R, ctr: CTR) -> Self {
+        Self { inner, ctr }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CTRReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.ctr
+            .apply_keystream(&mut buf[..read])
+            .map_err(io::Error::other)?;
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for CTRReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let offset = self.inner.seek(pos)?;
+        self.ctr.seek(offset);
+        Ok(offset)
+    }
+}
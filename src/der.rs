@@ -0,0 +1,179 @@
+//! Just enough of a DER reader for the key and container formats.
+//!
+//! Only definite lengths are supported, as required by DER. Elements are
+//! borrowed from the input, and malformed input results in `None`.
+
+pub(crate) const INTEGER: u8 = 0x02;
+pub(crate) const OCTET_STRING: u8 = 0x04;
+pub(crate) const NULL: u8 = 0x05;
+pub(crate) const OID: u8 = 0x06;
+pub(crate) const SEQUENCE: u8 = 0x30;
+pub(crate) const SET: u8 = 0x31;
+
+/// A context-specific, constructed tag such as `[0] EXPLICIT`.
+pub(crate) const fn explicit(number: u8) -> u8 {
+    0xa0 | number
+}
+
+/// A context-specific, primitive tag such as `[0] IMPLICIT OCTET STRING`.
+pub(crate) const fn implicit(number: u8) -> u8 {
+    0x80 | number
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub(crate) fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Reads the next element, returning its tag and contents.
+    pub(crate) fn read_any(&mut self) -> Option<(u8, &'a [u8])> {
+        let (&tag, rest) = self.data.split_first()?;
+        let (&first, rest) = rest.split_first()?;
+
+        // The high tag number form isn't used by any of the formats.
+        if tag & 0x1f == 0x1f {
+            return None;
+        }
+
+        let (len, rest) = match first {
+            0..=0x7f => (first as usize, rest),
+            0x81..=0x84 => {
+                let count = (first & 0x7f) as usize;
+                if rest.len() < count {
+                    return None;
+                }
+                let (bytes, rest) = rest.split_at(count);
+                let len = bytes.iter().fold(0usize, |n, b| n << 8 | *b as usize);
+                (len, rest)
+            }
+            // Indefinite lengths and lengths over 4 GiB.
+            _ => return None,
+        };
+
+        if rest.len() < len {
+            return None;
+        }
+
+        let (contents, rest) = rest.split_at(len);
+        self.data = rest;
+        Some((tag, contents))
+    }
+
+    /// Reads the contents of the next element, which must have the tag.
+    pub(crate) fn read(&mut self, tag: u8) -> Option<&'a [u8]> {
+        match self.read_any()? {
+            (t, contents) if t == tag => Some(contents),
+            _ => None,
+        }
+    }
+
+    /// Reads the next element if it has the tag.
+    pub(crate) fn read_optional(&mut self, tag: u8) -> Option<&'a [u8]> {
+        match self.peek_tag() {
+            Some(t) if t == tag => self.read(tag),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn sequence(&mut self) -> Option<Reader<'a>> {
+        self.read(SEQUENCE).map(Reader::new)
+    }
+
+    pub(crate) fn oid(&mut self) -> Option<&'a [u8]> {
+        self.read(OID)
+    }
+
+    pub(crate) fn octet_string(&mut self) -> Option<&'a [u8]> {
+        self.read(OCTET_STRING)
+    }
+
+    /// Reads a non-negative integer which fits in a `u32`.
+    pub(crate) fn u32(&mut self) -> Option<u32> {
+        let bytes = self.read(INTEGER)?;
+
+        match bytes {
+            [] => None,
+            [first, ..] if first & 0x80 != 0 => None,
+            [0, rest @ ..] if rest.len() <= 4 => Some(fold(rest)),
+            _ if bytes.len() <= 4 => Some(fold(bytes)),
+            _ => None,
+        }
+    }
+
+    /// Reads an `AlgorithmIdentifier`, returning the algorithm's OID and a
+    /// reader over its parameters.
+    pub(crate) fn algorithm(&mut self) -> Option<(&'a [u8], Reader<'a>)> {
+        let mut sequence = self.sequence()?;
+        let oid = sequence.oid()?;
+        Some((oid, sequence))
+    }
+
+    /// Checks that everything has been read.
+    pub(crate) fn finish(self) -> Option<()> {
+        self.is_empty().then_some(())
+    }
+}
+
+/// Checks that the data is a single DER sequence, with nothing after it, as
+/// a sanity check on decrypted keys. Garbage is a lot less likely to be a
+/// single DER sequence than to have valid padding.
+pub(crate) fn is_single_sequence(data: &[u8]) -> bool {
+    let mut reader = Reader::new(data);
+    reader.sequence().and(reader.finish()).is_some()
+}
+
+/// Formats the contents of an OID in dotted form, such as `1.2.840.113549`.
+pub(crate) fn oid_to_string(oid: &[u8]) -> Option<String> {
+    // The last byte of each base 128 arc has the high bit clear.
+    if oid.last()? & 0x80 != 0 {
+        return None;
+    }
+
+    let mut arcs = Vec::new();
+    let mut arc = 0u64;
+
+    for byte in oid {
+        if arc == 0 && *byte == 0x80 || arc >> 57 != 0 {
+            return None;
+        }
+
+        arc = arc << 7 | (byte & 0x7f) as u64;
+
+        if byte & 0x80 == 0 {
+            arcs.push(arc);
+            arc = 0;
+        }
+    }
+
+    // The first two arcs are combined into one.
+    let (first, rest) = arcs.split_first()?;
+    let (a, b) = match first {
+        0..=39 => (0, *first),
+        40..=79 => (1, first - 40),
+        _ => (2, first - 80),
+    };
+
+    let mut s = format!("{a}.{b}");
+    for arc in rest {
+        s.push_str(&format!(".{arc}"));
+    }
+
+    Some(s)
+}
+
+fn fold(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |n, b| n << 8 | *b as u32)
+}
@@ -0,0 +1,530 @@
+//! A self-describing, authenticated format for encrypting data at rest.
+//!
+//! Data is encrypted with AES-256 in CTR mode and split into chunks, each of
+//! which is authenticated with HMAC-SHA256 before it's decrypted
+//! (encrypt-then-MAC), so that streams can be processed without holding them
+//! in memory. Modifying, reordering, truncating or extending the chunks is
+//! detected.
+//!
+//! # Format
+//!
+//! All integers are big-endian.
+//!
+//! | Offset | Size | Field                                                   |
+//! |--------|------|---------------------------------------------------------|
+//! | 0      | 4    | Magic, `CCEV`                                           |
+//! | 4      | 1    | Version, 1                                              |
+//! | 5      | 1    | Cipher, 1 for AES-256-CTR                               |
+//! | 6      | 1    | MAC, 1 for HMAC-SHA256                                  |
+//! | 7      | 1    | Key derivation, 0 for a key or 1 for PBKDF2-HMAC-SHA256 |
+//! | 8      | 4    | Chunk size, a multiple of 16 from 16 bytes to 16 MiB    |
+//! | 12     | 4    | PBKDF2 iterations, 0 for a key, at most 6 000 000       |
+//! | 16     | 16   | Salt                                                    |
+//! | 32     | 16   | Initial counter block                                   |
+//! | 48     | 32   | Header tag                                              |
+//!
+//! The master key is either given, or derived from the password and salt
+//! with PBKDF2 to 32 bytes. The encryption key is then
+//! `HMAC-SHA256(master, "encryption" || salt)` and the MAC key
+//! `HMAC-SHA256(master, "authentication" || salt)`.
+//!
+//! The header tag is the HMAC of the first 48 bytes, so the header can't be
+//! modified and a wrong key is detected before any data is read.
+//!
+//! The header is followed by chunks, each consisting of the ciphertext and a
+//! 32-byte tag. Every chunk holds exactly the chunk size of ciphertext apart
+//! from the last, which holds less and may be empty. The ciphertext of all
+//! chunks forms one CTR keystream starting at the initial counter block, and
+//! chunk `i`'s tag is
+//! `HMAC-SHA256(mac key, header tag || u64 i || u8 last || ciphertext)`,
+//! where `last` is 1 for the last chunk and 0 otherwise.
+//!
+//! ```
+//! # use common_crypto::envelope::{self, Secret};
+//! let key = [7; 32];
+//! let sealed = envelope::seal(Secret::Key(&key), b"data at rest").unwrap();
+//! assert_eq!(envelope::open(Secret::Key(&key), sealed).unwrap(), b"data at rest");
+//! ```
+
+use crate::cryptor::{Config, Cryptor, CryptorError};
+use crate::hmac::{self, HMAC};
+use crate::kdf::{self, PRF};
+use crate::random;
+use std::fmt::{self, Display};
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+const MAGIC: &[u8; 4] = b"CCEV";
+const VERSION: u8 = 1;
+const CIPHER_AES_256_CTR: u8 = 1;
+const MAC_HMAC_SHA256: u8 = 1;
+const KDF_NONE: u8 = 0;
+const KDF_PBKDF2_SHA256: u8 = 1;
+
+const HEADER_LEN: usize = 48;
+const TAG_LEN: usize = 32;
+const MAX_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+
+/// The most PBKDF2 iterations accepted, ten times the default. The count is
+/// read from the header before it can be authenticated, so without a limit a
+/// forged envelope could take hours to reject.
+pub const MAX_ITERATIONS: u32 = 6_000_000;
+
+/// The key or password to encrypt with.
+#[derive(Clone, Copy, Debug)]
+pub enum Secret<'a> {
+    Key(&'a [u8; 32]),
+    Password(&'a [u8]),
+}
+
+/// Options which only affect encryption, as they're recorded in the header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// The amount of plaintext per authenticated chunk.
+    pub chunk_size: u32,
+    /// The PBKDF2 iterations when encrypting with a password, at most
+    /// [`MAX_ITERATIONS`].
+    pub iterations: u32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            chunk_size: 64 * 1024,
+            iterations: 600_000,
+        }
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum EnvelopeError {
+    /// The input isn't an envelope, or its header is malformed.
+    Format,
+    /// The envelope was written by a newer version.
+    Version(u8),
+    /// The envelope uses an algorithm which isn't supported.
+    Unsupported,
+    /// A tag didn't match, due to the wrong key or password or the data
+    /// having been modified.
+    Authentication,
+    /// The envelope ended before its last chunk.
+    Truncated,
+    /// The options aren't valid, or the secret doesn't match how the
+    /// envelope's key was derived.
+    Param,
+    Cryptor(CryptorError),
+    Io(io::Error),
+}
+
+impl std::error::Error for EnvelopeError {}
+
+impl Display for EnvelopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Format => f.write_str("input is not a valid envelope"),
+            Self::Version(version) => write!(f, "unsupported envelope version {version}"),
+            Self::Unsupported => f.write_str("envelope uses an unsupported algorithm"),
+            Self::Authentication => f.write_str("authentication failed"),
+            Self::Truncated => f.write_str("envelope is truncated"),
+            Self::Param => f.write_str("invalid envelope parameters"),
+            Self::Cryptor(e) => e.fmt(f),
+            Self::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<CryptorError> for EnvelopeError {
+    fn from(e: CryptorError) -> Self {
+        Self::Cryptor(e)
+    }
+}
+
+/// Unwraps the errors returned by the reader and writer.
+impl From<io::Error> for EnvelopeError {
+    fn from(e: io::Error) -> Self {
+        match e.downcast::<EnvelopeError>() {
+            Ok(e) => e,
+            Err(e) => Self::Io(e),
+        }
+    }
+}
+
+impl From<EnvelopeError> for io::Error {
+    fn from(e: EnvelopeError) -> Self {
+        match e {
+            EnvelopeError::Io(e) => e,
+            e => io::Error::other(e),
+        }
+    }
+}
+
+/// The parsed header, along with the keys derived from it.
+struct Header {
+    bytes: [u8; HEADER_LEN],
+    chunk_size: usize,
+    cryptor: Cryptor,
+    /// Initialised with the MAC key, to be cloned for each tag.
+    mac: hmac::SHA256,
+}
+
+impl Header {
+    fn new(
+        bytes: [u8; HEADER_LEN],
+        secret: Secret<'_>,
+        encrypt: bool,
+    ) -> Result<Self, EnvelopeError> {
+        if &bytes[..4] != MAGIC {
+            return Err(EnvelopeError::Format);
+        }
+
+        if bytes[4] != VERSION {
+            return Err(EnvelopeError::Version(bytes[4]));
+        }
+
+        if bytes[5] != CIPHER_AES_256_CTR || bytes[6] != MAC_HMAC_SHA256 {
+            return Err(EnvelopeError::Unsupported);
+        }
+
+        let chunk_size = u32::from_be_bytes(bytes[8..12].try_into().unwrap());
+        let iterations = u32::from_be_bytes(bytes[12..16].try_into().unwrap());
+        let salt = &bytes[16..32];
+        let iv: &[u8; 16] = bytes[32..48].try_into().unwrap();
+
+        if chunk_size == 0 || !chunk_size.is_multiple_of(16) || chunk_size > MAX_CHUNK_SIZE {
+            return Err(EnvelopeError::Format);
+        }
+
+        if iterations > MAX_ITERATIONS {
+            return Err(EnvelopeError::Format);
+        }
+
+        let master = match (bytes[7], secret) {
+            (KDF_NONE, _) if iterations != 0 => return Err(EnvelopeError::Format),
+            (KDF_PBKDF2_SHA256, _) if iterations == 0 => return Err(EnvelopeError::Format),
+            (KDF_NONE, Secret::Key(key)) => key.to_vec(),
+            (KDF_PBKDF2_SHA256, Secret::Password(password)) => {
+                kdf::pbkdf2(password, salt, PRF::SHA256, iterations, 32)?
+            }
+            (KDF_NONE | KDF_PBKDF2_SHA256, _) => return Err(EnvelopeError::Param),
+            _ => return Err(EnvelopeError::Unsupported),
+        };
+
+        let encryption_key = HMAC::sha256(&master, [&b"encryption"[..], salt].concat());
+        let mac_key = HMAC::sha256(&master, [&b"authentication"[..], salt].concat());
+
+        let config = Config::aes256(&encryption_key).ctr(iv);
+        let cryptor = if encrypt {
+            Cryptor::new_encryptor(&config)?
+        } else {
+            Cryptor::new_decryptor(&config)?
+        };
+
+        Ok(Self {
+            bytes,
+            chunk_size: chunk_size as usize,
+            cryptor,
+            mac: hmac::SHA256::new(mac_key),
+        })
+    }
+
+    fn header_tag(&self) -> [u8; TAG_LEN] {
+        let mut mac = self.mac.clone();
+        mac.update(self.bytes);
+        mac.finish()
+    }
+
+    fn chunk_tag(&self, header_tag: &[u8], index: u64, last: bool, ciphertext: &[u8]) -> [u8; 32] {
+        let mut mac = self.mac.clone();
+        mac.update(header_tag);
+        mac.update(index.to_be_bytes());
+        mac.update([last as u8]);
+        mac.update(ciphertext);
+        mac.finish()
+    }
+}
+
+/// Encrypts the data into an envelope with the default options.
+pub fn seal(secret: Secret<'_>, data: impl AsRef<[u8]>) -> Result<Vec<u8>, EnvelopeError> {
+    seal_with_options(secret, &Options::default(), data)
+}
+
+pub fn seal_with_options(
+    secret: Secret<'_>,
+    options: &Options,
+    data: impl AsRef<[u8]>,
+) -> Result<Vec<u8>, EnvelopeError> {
+    let mut writer = EnvelopeWriter::new(Vec::new(), secret, options)?;
+    writer.write_all(data.as_ref())?;
+    writer.finish()
+}
+
+/// The cost of deriving the key from a password, which is recorded in the
+/// envelope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkFactor {
+    /// A fixed number of PBKDF2 iterations.
+    Iterations(u32),
+    /// As many iterations as take about this long on this machine, see
+    /// [`kdf::calibrate_pbkdf2`], up to [`MAX_ITERATIONS`].
+    Duration(Duration),
+}
+
+/// The OWASP recommendation for PBKDF2-HMAC-SHA256.
+impl Default for WorkFactor {
+    fn default() -> Self {
+        Self::Iterations(Options::default().iterations)
+    }
+}
+
+/// Encrypts the data with a key derived from the password, using a random
+/// salt and the default work factor.
+///
+/// ```
+/// # use common_crypto::envelope;
+/// let sealed = envelope::seal_with_password("hunter2", b"secret").unwrap();
+/// let opened = envelope::open_with_password("hunter2", sealed).unwrap();
+/// assert_eq!(opened, b"secret");
+/// ```
+pub fn seal_with_password(
+    password: impl AsRef<[u8]>,
+    data: impl AsRef<[u8]>,
+) -> Result<Vec<u8>, EnvelopeError> {
+    seal_with_password_and_work_factor(password, data, WorkFactor::default())
+}
+
+pub fn seal_with_password_and_work_factor(
+    password: impl AsRef<[u8]>,
+    data: impl AsRef<[u8]>,
+    work_factor: WorkFactor,
+) -> Result<Vec<u8>, EnvelopeError> {
+    let password = password.as_ref();
+    let iterations = match work_factor {
+        WorkFactor::Iterations(iterations) => iterations,
+        WorkFactor::Duration(duration) => {
+            kdf::calibrate_pbkdf2(password.len(), 16, PRF::SHA256, 32, duration)?
+                .min(MAX_ITERATIONS)
+        }
+    };
+
+    let options = Options {
+        iterations,
+        ..Options::default()
+    };
+
+    seal_with_options(Secret::Password(password), &options, data)
+}
+
+/// Decrypts data sealed with a password, using the parameters recorded in
+/// it.
+pub fn open_with_password(
+    password: impl AsRef<[u8]>,
+    envelope: impl AsRef<[u8]>,
+) -> Result<Vec<u8>, EnvelopeError> {
+    open(Secret::Password(password.as_ref()), envelope)
+}
+
+/// Authenticates and decrypts an envelope.
+pub fn open(secret: Secret<'_>, envelope: impl AsRef<[u8]>) -> Result<Vec<u8>, EnvelopeError> {
+    let mut reader = EnvelopeReader::new(envelope.as_ref(), secret)?;
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Encrypts everything written to it into an envelope.
+///
+/// [`EnvelopeWriter::finish`] must be called to write the last chunk, without
+/// which the envelope will be rejected as truncated.
+pub struct EnvelopeWriter<W: Write> {
+    inner: W,
+    header: Header,
+    header_tag: [u8; TAG_LEN],
+    index: u64,
+    plaintext: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+impl<W: Write> EnvelopeWriter<W> {
+    /// Writes the header, with a random salt and initial counter block.
+    pub fn new(mut inner: W, secret: Secret<'_>, options: &Options) -> Result<Self, EnvelopeError> {
+        let (kdf, iterations) = match secret {
+            Secret::Key(_) => (KDF_NONE, 0),
+            Secret::Password(_) if options.iterations > 0 => {
+                (KDF_PBKDF2_SHA256, options.iterations)
+            }
+            Secret::Password(_) => return Err(EnvelopeError::Param),
+        };
+
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[..4].copy_from_slice(MAGIC);
+        bytes[4..8].copy_from_slice(&[VERSION, CIPHER_AES_256_CTR, MAC_HMAC_SHA256, kdf]);
+        bytes[8..12].copy_from_slice(&options.chunk_size.to_be_bytes());
+        bytes[12..16].copy_from_slice(&iterations.to_be_bytes());
+        random::fill(&mut bytes[16..48])?;
+
+        let header = Header::new(bytes, secret, true).map_err(|e| match e {
+            EnvelopeError::Format => EnvelopeError::Param,
+            e => e,
+        })?;
+        let header_tag = header.header_tag();
+
+        inner.write_all(&header.bytes)?;
+        inner.write_all(&header_tag)?;
+
+        Ok(Self {
+            inner,
+            plaintext: Vec::with_capacity(header.chunk_size),
+            header,
+            header_tag,
+            index: 0,
+            ciphertext: Vec::new(),
+        })
+    }
+
+    fn write_chunk(&mut self, len: usize, last: bool) -> Result<(), EnvelopeError> {
+        self.header
+            .cryptor
+            .update(&self.plaintext[..len], &mut self.ciphertext)?;
+        self.plaintext.drain(..len);
+
+        let tag = self
+            .header
+            .chunk_tag(&self.header_tag, self.index, last, &self.ciphertext);
+        self.inner.write_all(&self.ciphertext)?;
+        self.inner.write_all(&tag)?;
+        self.index += 1;
+
+        Ok(())
+    }
+
+    /// Writes the last chunk, returning the inner writer.
+    pub fn finish(mut self) -> Result<W, EnvelopeError> {
+        self.write_chunk(self.plaintext.len(), true)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EnvelopeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let chunk_size = self.header.chunk_size;
+        let len = buf.len().min(chunk_size - self.plaintext.len());
+        self.plaintext.extend_from_slice(&buf[..len]);
+
+        // A full chunk is never the last, as the last is written by finish.
+        if self.plaintext.len() == chunk_size {
+            self.write_chunk(chunk_size, false)?;
+        }
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Authenticates and decrypts an envelope as it's read.
+///
+/// Only authenticated data is returned, a chunk at a time. Errors are
+/// returned as [`io::Error`]s wrapping an [`EnvelopeError`], which can be
+/// recovered with [`EnvelopeError::from`].
+pub struct EnvelopeReader<R: Read> {
+    inner: R,
+    header: Header,
+    header_tag: [u8; TAG_LEN],
+    index: u64,
+    done: bool,
+    chunk: Vec<u8>,
+    plaintext: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> EnvelopeReader<R> {
+    /// Reads and authenticates the header.
+    pub fn new(mut inner: R, secret: Secret<'_>) -> Result<Self, EnvelopeError> {
+        let mut bytes = [0u8; HEADER_LEN + TAG_LEN];
+        if read_full(&mut inner, &mut bytes)? < bytes.len() {
+            return Err(EnvelopeError::Format);
+        }
+
+        let header = Header::new(bytes[..HEADER_LEN].try_into().unwrap(), secret, false)?;
+        let header_tag = header.header_tag();
+        if !hmac::constant_time_eq(&header_tag, &bytes[HEADER_LEN..]) {
+            return Err(EnvelopeError::Authentication);
+        }
+
+        Ok(Self {
+            inner,
+            chunk: vec![0u8; header.chunk_size + TAG_LEN],
+            header,
+            header_tag,
+            index: 0,
+            done: false,
+            plaintext: Vec::new(),
+            position: 0,
+        })
+    }
+
+    fn read_chunk(&mut self) -> Result<(), EnvelopeError> {
+        let read = read_full(&mut self.inner, &mut self.chunk)?;
+        if read < TAG_LEN {
+            return Err(EnvelopeError::Truncated);
+        }
+
+        let last = read < self.chunk.len();
+        let (ciphertext, tag) = self.chunk[..read].split_at(read - TAG_LEN);
+        let expected = self
+            .header
+            .chunk_tag(&self.header_tag, self.index, last, ciphertext);
+
+        if !hmac::constant_time_eq(&expected, tag) {
+            return Err(EnvelopeError::Authentication);
+        }
+
+        self.header
+            .cryptor
+            .update(ciphertext, &mut self.plaintext)?;
+        self.position = 0;
+        self.index += 1;
+        self.done = last;
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for EnvelopeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.plaintext.len() {
+            if self.done {
+                return Ok(0);
+            }
+
+            self.read_chunk()?;
+        }
+
+        let len = buf.len().min(self.plaintext.len() - self.position);
+        buf[..len].copy_from_slice(&self.plaintext[self.position..self.position + len]);
+        self.position += len;
+
+        Ok(len)
+    }
+}
+
+/// Reads until the buffer is full or the end of the input, returning the
+/// number of bytes read.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(read)
+}
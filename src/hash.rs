@@ -1,33 +1,272 @@
 use std::ffi::c_void;
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+pub mod checksums;
+pub mod multi;
+
+/// The largest input passed to Common Crypto in a single call, as lengths are
+/// 32-bit. This is a multiple of every block size so no data is buffered
+/// between calls.
+const MAX_CHUNK: usize = 1 << 30;
+
+/// The size of the buffer used when hashing readers, large enough that the
+/// cost of each read and call into Common Crypto is amortised.
+const BUFFER_SIZE: usize = 256 * 1024;
+
+/// Files at least this large are memory mapped rather than read by
+/// [`hash_file_mapped`], below it mapping costs more than it saves.
+#[cfg(feature = "mmap")]
+const MMAP_THRESHOLD: u64 = 4 * 1024 * 1024;
 
 pub struct Hash;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Algorithm {
+    MD2,
+    MD4,
+    MD5,
+    SHA1,
+    SHA224,
+    SHA256,
+    SHA384,
+    SHA512,
+}
+
+impl Algorithm {
+    /// The length of the digest in bytes.
+    pub fn digest_len(self) -> usize {
+        match self {
+            Self::MD2 | Self::MD4 | Self::MD5 => 16,
+            Self::SHA1 => 20,
+            Self::SHA224 => 28,
+            Self::SHA256 => 32,
+            Self::SHA384 => 48,
+            Self::SHA512 => 64,
+        }
+    }
+
+    fn all() -> [Self; 8] {
+        [
+            Self::MD2,
+            Self::MD4,
+            Self::MD5,
+            Self::SHA1,
+            Self::SHA224,
+            Self::SHA256,
+            Self::SHA384,
+            Self::SHA512,
+        ]
+    }
+}
+
+/// Writes the name used by BSD style checksum files, such as `SHA256`.
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::MD2 => "MD2",
+            Self::MD4 => "MD4",
+            Self::MD5 => "MD5",
+            Self::SHA1 => "SHA1",
+            Self::SHA224 => "SHA224",
+            Self::SHA256 => "SHA256",
+            Self::SHA384 => "SHA384",
+            Self::SHA512 => "SHA512",
+        };
+
+        f.write_str(s)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseAlgorithmError;
+
+impl std::error::Error for ParseAlgorithmError {}
+
+impl Display for ParseAlgorithmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unknown hash algorithm")
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = ParseAlgorithmError;
+
+    /// Parses the names written by [`Display`], ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::all()
+            .into_iter()
+            .find(|a| a.to_string().eq_ignore_ascii_case(s))
+            .ok_or(ParseAlgorithmError)
+    }
+}
+
+/// Hashes a file with the given algorithm.
+pub fn hash_file(path: impl AsRef<Path>, algorithm: Algorithm) -> io::Result<Vec<u8>> {
+    let mut hasher = Hasher::new(algorithm);
+    read_file(path.as_ref(), &mut hasher)?;
+    Ok(hasher.finish())
+}
+
+/// Hashes a file like [`hash_file`], memory mapping it instead of reading it
+/// if it's large.
+///
+/// # Safety
+///
+/// The file mustn't be modified while it's being hashed, by this process or
+/// any other. Truncating a mapped file is undefined behaviour, and usually
+/// crashes the process with `SIGBUS`.
+#[cfg(feature = "mmap")]
+pub unsafe fn hash_file_mapped(
+    path: impl AsRef<Path>,
+    algorithm: Algorithm,
+) -> io::Result<Vec<u8>> {
+    let mut hasher = Hasher::new(algorithm);
+    map_file(path.as_ref(), &mut hasher)?;
+    Ok(hasher.finish())
+}
+
+/// Writes the contents of the file to the hasher.
+fn read_file(path: &Path, hasher: &mut impl Write) -> io::Result<()> {
+    copy(File::open(path)?, hasher).map(|_| ())
+}
+
+/// Writes the contents of the file to the hasher, see [`hash_file_mapped`].
+#[cfg(feature = "mmap")]
+unsafe fn map_file(path: &Path, hasher: &mut impl Write) -> io::Result<()> {
+    let file = File::open(path)?;
+
+    if file.metadata()?.len() >= MMAP_THRESHOLD {
+        // Safety: upheld by the caller, the map is only read and never
+        // outlives the file.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        return hasher.write_all(&map);
+    }
+
+    copy(file, hasher).map(|_| ())
+}
+
+/// Like [`io::copy`], but with a buffer suited to hashing.
+fn copy(mut reader: impl Read, writer: &mut impl Write) -> io::Result<u64> {
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut total = 0;
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(total),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        writer.write_all(&buffer[..read])?;
+        total += read as u64;
+    }
+}
+
+/// A hasher for an algorithm chosen at runtime.
+#[derive(Clone)]
+pub struct Hasher {
+    state: State,
+}
+
+macro_rules! implement_hasher {
+    ($($algorithm:ident),*) => {
+        #[derive(Clone)]
+        enum State {
+            $($algorithm($algorithm),)*
+        }
+
+        impl Hasher {
+            pub fn new(algorithm: Algorithm) -> Self {
+                let state = match algorithm {
+                    $(Algorithm::$algorithm => State::$algorithm($algorithm::new()),)*
+                };
+
+                Self { state }
+            }
+
+            pub fn algorithm(&self) -> Algorithm {
+                match self.state {
+                    $(State::$algorithm(_) => Algorithm::$algorithm,)*
+                }
+            }
+
+            pub fn update(&mut self, data: impl AsRef<[u8]>) {
+                match &mut self.state {
+                    $(State::$algorithm(hasher) => hasher.update(data),)*
+                }
+            }
+
+            pub fn finish(self) -> Vec<u8> {
+                match self.state {
+                    $(State::$algorithm(hasher) => hasher.finish().to_vec(),)*
+                }
+            }
+        }
+    };
+}
+
+implement_hasher!(MD2, MD4, MD5, SHA1, SHA224, SHA256, SHA384, SHA512);
+
+impl Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 macro_rules! implement_hash {
-    ($func:ident, $struct:ident, $ctx:ident, $one_shot:ident, $init:ident, $update:ident, $final:ident, $len:expr) => {
+    ($func:ident, $reader:ident, $struct:ident, $ctx:ident, $one_shot:ident, $init:ident, $update:ident, $final:ident, $len:expr) => {
         extern "C" {
-            fn $one_shot(data: *const c_void, len: i32, output: *mut u8) -> *mut u8;
+            fn $one_shot(data: *const c_void, len: u32, output: *mut u8) -> *mut u8;
             fn $init(ctx: *mut $ctx) -> i32;
-            fn $update(ctx: *mut $ctx, data: *const c_void, len: i32) -> i32;
+            fn $update(ctx: *mut $ctx, data: *const c_void, len: u32) -> i32;
             fn $final(output: *mut c_void, ctx: *mut $ctx) -> i32;
         }
 
         impl Hash {
             pub fn $func(data: impl AsRef<[u8]>) -> [u8; $len] {
+                let data = data.as_ref();
+
+                if data.len() > MAX_CHUNK {
+                    let mut hasher = $struct::new();
+                    hasher.update(data);
+                    return hasher.finish();
+                }
+
                 let mut output = [0u8; $len];
 
                 unsafe {
                     $one_shot(
-                        data.as_ref().as_ptr() as *const c_void,
-                        data.as_ref().len() as i32,
+                        data.as_ptr() as *const c_void,
+                        data.len() as u32,
                         output.as_mut_ptr(),
                     );
                 }
 
                 output
             }
+
+            /// Hashes everything read from the reader.
+            pub fn $reader(reader: impl Read) -> io::Result<[u8; $len]> {
+                let mut hasher = $struct::new();
+                copy(reader, &mut hasher)?;
+                Ok(hasher.finish())
+            }
         }
 
-        #[derive(Default)]
+        /// Cloning the hasher snapshots its state, so that inputs sharing a
+        /// prefix only need to hash it once.
+        #[derive(Clone, Default)]
         pub struct $struct {
             ctx: $ctx,
         }
@@ -44,12 +283,14 @@ macro_rules! implement_hash {
             }
 
             pub fn update(&mut self, data: impl AsRef<[u8]>) {
-                unsafe {
-                    $update(
-                        &mut self.ctx,
-                        data.as_ref().as_ptr() as *const c_void,
-                        data.as_ref().len() as i32,
-                    );
+                for chunk in data.as_ref().chunks(MAX_CHUNK) {
+                    unsafe {
+                        $update(
+                            &mut self.ctx,
+                            chunk.as_ptr() as *const c_void,
+                            chunk.len() as u32,
+                        );
+                    }
                 }
             }
 
@@ -59,11 +300,22 @@ macro_rules! implement_hash {
                 output
             }
         }
+
+        impl Write for $struct {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.update(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
     };
 }
 
 #[repr(C)]
-#[derive(Default)]
+#[derive(Clone, Default)]
 struct SHA1Context {
     h0: u32,
     h1: u32,
@@ -77,6 +329,7 @@ struct SHA1Context {
 }
 implement_hash!(
     sha1,
+    sha1_reader,
     SHA1,
     SHA1Context,
     CC_SHA1,
@@ -87,7 +340,7 @@ implement_hash!(
 );
 
 #[repr(C)]
-#[derive(Default)]
+#[derive(Clone, Default)]
 struct SHA256Context {
     count: [u32; 2],
     hash: [u32; 8],
@@ -96,6 +349,7 @@ struct SHA256Context {
 
 implement_hash!(
     sha224,
+    sha224_reader,
     SHA224,
     SHA256Context,
     CC_SHA224,
@@ -107,6 +361,7 @@ implement_hash!(
 
 implement_hash!(
     sha256,
+    sha256_reader,
     SHA256,
     SHA256Context,
     CC_SHA256,
@@ -117,7 +372,7 @@ implement_hash!(
 );
 
 #[repr(C)]
-#[derive(Default)]
+#[derive(Clone, Default)]
 struct SHA512Context {
     count: [u64; 2],
     hash: [u64; 8],
@@ -126,6 +381,7 @@ struct SHA512Context {
 
 implement_hash!(
     sha384,
+    sha384_reader,
     SHA384,
     SHA512Context,
     CC_SHA384,
@@ -137,6 +393,7 @@ implement_hash!(
 
 implement_hash!(
     sha512,
+    sha512_reader,
     SHA512,
     SHA512Context,
     CC_SHA512,
@@ -147,7 +404,7 @@ implement_hash!(
 );
 
 #[repr(C)]
-#[derive(Default)]
+#[derive(Clone, Default)]
 struct MD2Context {
     num: i32,
     data: [u8; 16],
@@ -157,6 +414,7 @@ struct MD2Context {
 
 implement_hash!(
     md2,
+    md2_reader,
     MD2,
     MD2Context,
     CC_MD2,
@@ -167,7 +425,7 @@ implement_hash!(
 );
 
 #[repr(C)]
-#[derive(Default)]
+#[derive(Clone, Default)]
 struct MD4Context {
     a: u32,
     b: u32,
@@ -181,6 +439,7 @@ struct MD4Context {
 
 implement_hash!(
     md4,
+    md4_reader,
     MD4,
     MD4Context,
     CC_MD4,
@@ -191,7 +450,7 @@ implement_hash!(
 );
 
 #[repr(C)]
-#[derive(Default)]
+#[derive(Clone, Default)]
 struct MD5Context {
     a: u32,
     b: u32,
@@ -205,6 +464,7 @@ struct MD5Context {
 
 implement_hash!(
     md5,
+    md5_reader,
     MD5,
     MD5Context,
     CC_MD5,
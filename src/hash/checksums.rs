@@ -0,0 +1,270 @@
+//! Checksum files in the formats written by GNU coreutils' `sha256sum` and
+//! friends, and by the BSD `sha256` family of tools.
+//!
+//! ```text
+//! 3a6eb0790f39ac87c94f3856b2dd2c5d110e6811602261a9a923d3bb23adc8b7  data.txt
+//! 8d777f385d3dfec8815d20f7496026dc *data.bin
+//! SHA256 (data.txt) = 3a6eb0790f39ac87c94f3856b2dd2c5d110e6811602261a9a923d3bb23adc8b7
+//! ```
+//!
+//! As with coreutils, file names containing a backslash or newline are
+//! escaped, which is marked by a backslash at the start of the line.
+//!
+//! ```no_run
+//! # use common_crypto::hash::checksums::{self, Status};
+//! let text = std::fs::read_to_string("SHA256SUMS")?;
+//! let entries = checksums::parse(&text, None)?;
+//!
+//! for (entry, status) in entries.iter().zip(checksums::verify(&entries, ".")) {
+//!     println!("{}: {}", entry.path.display(), status);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use super::multi;
+use super::Algorithm;
+use crate::encoding::{hex_decode, hex_encode};
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// `<digest>  <path>`, or `<digest> *<path>` for binary mode.
+    GNU,
+    /// `<ALGORITHM> (<path>) = <digest>`, also written by `sha256sum --tag`.
+    BSD,
+}
+
+/// A line of a checksum file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub algorithm: Algorithm,
+    pub digest: Vec<u8>,
+    pub path: PathBuf,
+    /// Whether the file was read in binary mode, which is only recorded by
+    /// the GNU format and makes no difference on Unix.
+    pub binary: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The line number, starting at 1.
+    pub line: usize,
+}
+
+impl std::error::Error for ParseError {}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "improperly formatted checksum line {}", self.line)
+    }
+}
+
+/// The result of verifying an entry.
+#[derive(Debug)]
+pub enum Status {
+    Ok,
+    Failed,
+    Missing,
+    /// The file exists but couldn't be read.
+    Unreadable(io::Error),
+}
+
+/// Writes the status as `sha256sum --check` does.
+impl Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok => f.write_str("OK"),
+            Self::Failed => f.write_str("FAILED"),
+            Self::Missing => f.write_str("FAILED open or read: missing"),
+            Self::Unreadable(e) => write!(f, "FAILED open or read: {e}"),
+        }
+    }
+}
+
+/// Hashes the files to create the entries of a checksum file. The paths are
+/// recorded as given.
+pub fn generate<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    algorithm: Algorithm,
+) -> io::Result<Vec<Entry>> {
+    multi::hash_files(paths, &[algorithm])
+        .entries
+        .into_iter()
+        .map(|entry| match entry.digests {
+            Ok(digests) => Ok(Entry {
+                algorithm,
+                digest: digests.get(algorithm).unwrap_or_default().to_vec(),
+                path: entry.path,
+                binary: false,
+            }),
+            Err(e) => Err(io::Error::new(
+                e.kind(),
+                format!("{}: {e}", entry.path.display()),
+            )),
+        })
+        .collect()
+}
+
+/// Writes the entries in the given format. Paths must be valid UTF-8.
+pub fn write(mut writer: impl Write, entries: &[Entry], format: Format) -> io::Result<()> {
+    for entry in entries {
+        let path = entry.path.to_str().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "path is not valid UTF-8")
+        })?;
+        let escaped = path.contains(['\\', '\n']);
+        let path = path.replace('\\', "\\\\").replace('\n', "\\n");
+        let digest = hex_encode(&entry.digest);
+
+        if escaped {
+            writer.write_all(b"\\")?;
+        }
+
+        match format {
+            Format::GNU => {
+                let mode = if entry.binary { '*' } else { ' ' };
+                writeln!(writer, "{digest} {mode}{path}")?;
+            }
+            Format::BSD => writeln!(writer, "{} ({path}) = {digest}", entry.algorithm)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a checksum file in either format, which may be mixed. Blank lines
+/// and lines starting with `#` are ignored.
+///
+/// The GNU format doesn't name the algorithm, so it's the given one or is
+/// inferred from the length of the digest, with MD5 assumed for 128 bits.
+pub fn parse(text: &str, algorithm: Option<Algorithm>) -> Result<Vec<Entry>, ParseError> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| parse_line(line, algorithm).ok_or(ParseError { line: i + 1 }))
+        .collect()
+}
+
+fn parse_line(line: &str, algorithm: Option<Algorithm>) -> Option<Entry> {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+
+    let (algorithm, digest, path, binary) = match parse_bsd(line) {
+        Some((algorithm, path, digest)) => (algorithm, digest, path, false),
+        None => {
+            let (digest, rest) = line.split_once(' ')?;
+            let binary = match rest.as_bytes().first()? {
+                b' ' => false,
+                b'*' => true,
+                _ => return None,
+            };
+            let algorithm = match algorithm {
+                Some(algorithm) => algorithm,
+                None => infer_algorithm(digest.len() / 2)?,
+            };
+
+            (algorithm, digest, &rest[1..], binary)
+        }
+    };
+
+    let digest = hex_decode(digest).filter(|d| d.len() == algorithm.digest_len())?;
+    let path = if escaped {
+        unescape(path)?
+    } else {
+        path.to_string()
+    };
+
+    if path.is_empty() {
+        return None;
+    }
+
+    Some(Entry {
+        algorithm,
+        digest,
+        path: PathBuf::from(path),
+        binary,
+    })
+}
+
+/// Splits `<ALGORITHM> (<path>) = <digest>`, where the path may contain
+/// parentheses.
+fn parse_bsd(line: &str) -> Option<(Algorithm, &str, &str)> {
+    let (algorithm, rest) = line.split_once(" (")?;
+    let (path, digest) = rest.rsplit_once(") = ")?;
+    Some((algorithm.parse().ok()?, path, digest))
+}
+
+fn infer_algorithm(digest_len: usize) -> Option<Algorithm> {
+    [
+        Algorithm::MD5,
+        Algorithm::SHA1,
+        Algorithm::SHA224,
+        Algorithm::SHA256,
+        Algorithm::SHA384,
+        Algorithm::SHA512,
+    ]
+    .into_iter()
+    .find(|a| a.digest_len() == digest_len)
+}
+
+fn unescape(path: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(path.len());
+    let mut chars = path.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                '\\' => unescaped.push('\\'),
+                'n' => unescaped.push('\n'),
+                _ => return None,
+            },
+            c => unescaped.push(c),
+        }
+    }
+
+    Some(unescaped)
+}
+
+/// Checks the files against the entries, with relative paths resolved against
+/// the base directory. The statuses are in the same order as the entries.
+pub fn verify(entries: &[Entry], base: impl AsRef<Path>) -> Vec<Status> {
+    let mut statuses: Vec<Option<Status>> = entries.iter().map(|_| None).collect();
+
+    // Each file is only hashed with the algorithm of its own entry.
+    let mut algorithms: Vec<Algorithm> = Vec::new();
+    for entry in entries {
+        if !algorithms.contains(&entry.algorithm) {
+            algorithms.push(entry.algorithm);
+        }
+    }
+
+    for algorithm in algorithms {
+        let indices: Vec<usize> = (0..entries.len())
+            .filter(|&i| entries[i].algorithm == algorithm)
+            .collect();
+        let paths: Vec<PathBuf> = indices
+            .iter()
+            .map(|&i| base.as_ref().join(&entries[i].path))
+            .collect();
+
+        let manifest = multi::hash_files(&paths, &[algorithm]);
+
+        for (i, result) in indices.into_iter().zip(manifest.entries) {
+            let entry = &entries[i];
+            statuses[i] = Some(match result.digests {
+                Ok(digests) if digests.get(entry.algorithm) == Some(&entry.digest[..]) => {
+                    Status::Ok
+                }
+                Ok(_) => Status::Failed,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Status::Missing,
+                Err(e) => Status::Unreadable(e),
+            });
+        }
+    }
+
+    statuses.into_iter().map(Option::unwrap).collect()
+}
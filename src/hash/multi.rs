@@ -0,0 +1,187 @@
+//! Computing several digests of many files at once.
+//!
+//! Each input is only read once however many algorithms are requested, and
+//! files are spread across a pool of threads.
+//!
+//! ```no_run
+//! # use common_crypto::hash::Algorithm;
+//! # use common_crypto::hash::multi;
+//! let algorithms = [Algorithm::SHA256, Algorithm::MD5];
+//! let manifest = multi::hash_files(&["a.tar", "b.tar"], &algorithms);
+//!
+//! for entry in &manifest.entries {
+//!     match &entry.digests {
+//!         Ok(digests) => println!("{:?}: {:?}", entry.path, digests.get(Algorithm::SHA256)),
+//!         Err(e) => eprintln!("{:?}: {}", entry.path, e),
+//!     }
+//! }
+//! ```
+
+use super::{copy, read_file, Algorithm, Hasher};
+use std::io::{self, Read, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Feeds the same input to a hasher per algorithm.
+#[derive(Clone)]
+pub struct MultiHasher {
+    hashers: Vec<Hasher>,
+}
+
+impl MultiHasher {
+    /// Duplicate algorithms are only computed once.
+    pub fn new(algorithms: &[Algorithm]) -> Self {
+        let mut hashers: Vec<Hasher> = Vec::with_capacity(algorithms.len());
+
+        for algorithm in algorithms {
+            if !hashers.iter().any(|h| h.algorithm() == *algorithm) {
+                hashers.push(Hasher::new(*algorithm));
+            }
+        }
+
+        Self { hashers }
+    }
+
+    pub fn update(&mut self, data: impl AsRef<[u8]>) {
+        for hasher in &mut self.hashers {
+            hasher.update(data.as_ref());
+        }
+    }
+
+    pub fn finish(self) -> Digests {
+        Digests(
+            self.hashers
+                .into_iter()
+                .map(|h| (h.algorithm(), h.finish()))
+                .collect(),
+        )
+    }
+}
+
+impl Write for MultiHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The digests of a single input, in the order the algorithms were given.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Digests(Vec<(Algorithm, Vec<u8>)>);
+
+impl Digests {
+    pub fn get(&self, algorithm: Algorithm) -> Option<&[u8]> {
+        self.iter()
+            .find(|(a, _)| *a == algorithm)
+            .map(|(_, digest)| digest)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Algorithm, &[u8])> {
+        self.0.iter().map(|(a, digest)| (*a, &digest[..]))
+    }
+}
+
+/// The outcome of hashing a file.
+#[derive(Debug)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub digests: io::Result<Digests>,
+}
+
+/// The results of [`hash_files`], with an entry per path in the order they
+/// were given.
+#[derive(Debug, Default)]
+pub struct Manifest {
+    pub entries: Vec<Entry>,
+}
+
+impl Manifest {
+    /// The entries which couldn't be read.
+    pub fn errors(&self) -> impl Iterator<Item = (&Path, &io::Error)> {
+        self.entries
+            .iter()
+            .filter_map(|e| e.digests.as_ref().err().map(|err| (e.path.as_path(), err)))
+    }
+}
+
+/// Computes the digests of everything read from the reader in a single pass.
+pub fn hash_reader(reader: impl Read, algorithms: &[Algorithm]) -> io::Result<Digests> {
+    let mut hasher = MultiHasher::new(algorithms);
+    copy(reader, &mut hasher)?;
+    Ok(hasher.finish())
+}
+
+/// Computes the digests of a file in a single pass.
+pub fn hash_file(path: impl AsRef<Path>, algorithms: &[Algorithm]) -> io::Result<Digests> {
+    let mut hasher = MultiHasher::new(algorithms);
+    read_file(path.as_ref(), &mut hasher)?;
+    Ok(hasher.finish())
+}
+
+/// Computes the digests of a file in a single pass, memory mapping it if it's
+/// large.
+///
+/// # Safety
+///
+/// As for [`super::hash_file_mapped`], the file mustn't be modified while
+/// it's being hashed.
+#[cfg(feature = "mmap")]
+pub unsafe fn hash_file_mapped(
+    path: impl AsRef<Path>,
+    algorithms: &[Algorithm],
+) -> io::Result<Digests> {
+    let mut hasher = MultiHasher::new(algorithms);
+    unsafe { super::map_file(path.as_ref(), &mut hasher)? };
+    Ok(hasher.finish())
+}
+
+/// Hashes the files using a thread per available CPU.
+pub fn hash_files<P: AsRef<Path> + Sync>(paths: &[P], algorithms: &[Algorithm]) -> Manifest {
+    let threads = thread::available_parallelism().unwrap_or(NonZeroUsize::MIN);
+    hash_files_with_threads(paths, algorithms, threads)
+}
+
+/// Hashes the files using at most the given number of threads. Failing to
+/// read a file doesn't prevent the others from being hashed.
+pub fn hash_files_with_threads<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    algorithms: &[Algorithm],
+    threads: NonZeroUsize,
+) -> Manifest {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(paths.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..threads.get().min(paths.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(index) else {
+                    break;
+                };
+
+                let digests = hash_file(path, algorithms);
+                results.lock().unwrap().push((index, digests));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_unstable_by_key(|(index, _)| *index);
+
+    Manifest {
+        entries: results
+            .into_iter()
+            .map(|(index, digests)| Entry {
+                path: paths[index].as_ref().to_path_buf(),
+                digests,
+            })
+            .collect(),
+    }
+}
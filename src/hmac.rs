@@ -1,3 +1,4 @@
+use crate::hash;
 use std::ffi::c_void;
 
 #[repr(u32)]
@@ -11,6 +12,7 @@ enum Algorithm {
 }
 
 #[repr(C)]
+#[derive(Clone)]
 struct Context {
     ctx: [u32; 96],
 }
@@ -53,6 +55,40 @@ impl HMAC {
             )
         }
     }
+
+    /// Authenticates using a hash chosen at runtime, returning `None` for
+    /// those which aren't supported.
+    pub(crate) fn with_algorithm(
+        algorithm: hash::Algorithm,
+        key: &[u8],
+        data: &[u8],
+    ) -> Option<Vec<u8>> {
+        let hmac_algorithm = match algorithm {
+            hash::Algorithm::MD5 => Algorithm::MD5,
+            hash::Algorithm::SHA1 => Algorithm::SHA1,
+            hash::Algorithm::SHA224 => Algorithm::SHA224,
+            hash::Algorithm::SHA256 => Algorithm::SHA256,
+            hash::Algorithm::SHA384 => Algorithm::SHA384,
+            hash::Algorithm::SHA512 => Algorithm::SHA512,
+            _ => return None,
+        };
+
+        let mut hash = vec![0u8; algorithm.digest_len()];
+        Self::generate(hmac_algorithm, key, data, &mut hash);
+        Some(hash)
+    }
+}
+
+/// Compares authentication codes in constant time, so that an attacker can't
+/// learn how much of a forged code was correct from how long it took to
+/// reject.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let difference = a.iter().zip(b).fold(0, |d, (a, b)| d | (a ^ b));
+    std::hint::black_box(difference) == 0
 }
 
 macro_rules! implement_digest {
@@ -72,6 +108,10 @@ macro_rules! implement_digest {
             }
         }
 
+        /// Cloning the context snapshots its state, so that messages
+        /// authenticated with the same key or sharing a prefix only need to
+        /// process it once.
+        #[derive(Clone)]
         pub struct $algorithm {
             context: Context,
         }
@@ -0,0 +1,291 @@
+//! Just enough of a JSON reader and writer for JOSE headers.
+//!
+//! Only the members of the top level object are returned, with strings
+//! unescaped and other values borrowed as they appear in the input. Malformed
+//! input, and objects with duplicate member names, result in `None`.
+
+use crate::encoding::base64url_decode;
+use std::fmt::Write;
+
+/// Nesting deeper than this is rejected rather than risking the stack.
+const MAX_DEPTH: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Value<'a> {
+    String(String),
+    /// Any other value, such as a number or nested object.
+    Other(&'a str),
+}
+
+/// Reads a JSON object, returning its members in order.
+pub(crate) fn object(text: &str) -> Option<Vec<(String, Value<'_>)>> {
+    let mut parser = Parser { text, pos: 0 };
+    let mut members: Vec<(String, Value<'_>)> = Vec::new();
+
+    parser.expect(b'{')?;
+
+    if !parser.consume(b'}') {
+        loop {
+            let name = parser.string()?;
+            parser.expect(b':')?;
+
+            let value = if parser.peek() == Some(b'"') {
+                Value::String(parser.string()?)
+            } else {
+                let start = parser.skip_whitespace();
+                parser.value(0)?;
+                Value::Other(&text[start..parser.pos])
+            };
+
+            if members.iter().any(|(existing, _)| *existing == name) {
+                return None;
+            }
+
+            members.push((name, value));
+
+            if !parser.consume(b',') {
+                parser.expect(b'}')?;
+                break;
+            }
+        }
+    }
+
+    parser.skip_whitespace();
+    (parser.pos == text.len()).then_some(members)
+}
+
+/// The members of the protected header of a compact JOSE token, with strings
+/// unescaped and other values dropped.
+pub(crate) struct Header {
+    members: Vec<(String, Option<String>)>,
+}
+
+impl Header {
+    /// Decodes the first part of the token, returning `None` if it isn't a
+    /// base64url encoded JSON object.
+    pub(crate) fn from_token(token: &str) -> Option<Self> {
+        let encoded = token.split('.').next().unwrap_or_default();
+        let json = String::from_utf8(base64url_decode(encoded)?).ok()?;
+        let members = object(&json)?
+            .into_iter()
+            .map(|(name, value)| match value {
+                Value::String(s) => (name, Some(s)),
+                Value::Other(_) => (name, None),
+            })
+            .collect();
+
+        Some(Self { members })
+    }
+
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.members.iter().any(|(member, _)| member == name)
+    }
+
+    /// Returns the member if it's present, or the error if it isn't a string.
+    pub(crate) fn string<E>(&self, name: &str, error: E) -> Result<Option<String>, E> {
+        match self.members.iter().find(|(member, _)| member == name) {
+            Some((_, Some(s))) => Ok(Some(s.clone())),
+            Some((_, None)) => Err(error),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Writes a string with the quotes and escapes required by JSON.
+pub(crate) fn string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    /// Skips whitespace, returning the position of the next token.
+    fn skip_whitespace(&mut self) -> usize {
+        let bytes = self.text.as_bytes();
+
+        while matches!(bytes.get(self.pos), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+
+        self.pos
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn consume(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            return true;
+        }
+
+        false
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.consume(byte).then_some(())
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.expect(b'"')?;
+
+        let mut s = String::new();
+        let mut chars = self.text[self.pos..].char_indices();
+
+        loop {
+            let (i, c) = chars.next()?;
+
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Some(s);
+                }
+                '\\' => match chars.next()?.1 {
+                    '"' => s.push('"'),
+                    '\\' => s.push('\\'),
+                    '/' => s.push('/'),
+                    'b' => s.push('\u{8}'),
+                    'f' => s.push('\u{c}'),
+                    'n' => s.push('\n'),
+                    'r' => s.push('\r'),
+                    't' => s.push('\t'),
+                    'u' => {
+                        let mut unit = hex4(&mut chars)?;
+
+                        // A surrogate pair, written as two escapes.
+                        if (0xd800..0xdc00).contains(&unit) {
+                            if chars.next()?.1 != '\\' || chars.next()?.1 != 'u' {
+                                return None;
+                            }
+
+                            let low = hex4(&mut chars)?;
+                            if !(0xdc00..0xe000).contains(&low) {
+                                return None;
+                            }
+
+                            unit = 0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00);
+                        }
+
+                        s.push(char::from_u32(unit)?);
+                    }
+                    _ => return None,
+                },
+                c if (c as u32) < 0x20 => return None,
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// Skips over any value.
+    fn value(&mut self, depth: usize) -> Option<()> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        match self.peek()? {
+            b'"' => {
+                self.string()?;
+            }
+            open @ (b'{' | b'[') => {
+                let close = if open == b'{' { b'}' } else { b']' };
+                self.pos += 1;
+
+                if self.consume(close) {
+                    return Some(());
+                }
+
+                loop {
+                    if open == b'{' {
+                        self.string()?;
+                        self.expect(b':')?;
+                    }
+
+                    self.value(depth + 1)?;
+
+                    if !self.consume(b',') {
+                        self.expect(close)?;
+                        break;
+                    }
+                }
+            }
+            b't' | b'f' | b'n' => {
+                let rest = &self.text[self.pos..];
+                let literal = ["true", "false", "null"]
+                    .into_iter()
+                    .find(|literal| rest.starts_with(literal))?;
+                self.pos += literal.len();
+            }
+            _ => self.number()?,
+        }
+
+        Some(())
+    }
+
+    fn number(&mut self) -> Option<()> {
+        let bytes = self.text.as_bytes();
+        let digits = |pos: &mut usize| {
+            let start = *pos;
+            while bytes.get(*pos).is_some_and(u8::is_ascii_digit) {
+                *pos += 1;
+            }
+            *pos > start
+        };
+
+        let mut pos = self.pos;
+
+        if bytes.get(pos) == Some(&b'-') {
+            pos += 1;
+        }
+
+        if bytes.get(pos) == Some(&b'0') {
+            pos += 1;
+        } else if !digits(&mut pos) {
+            return None;
+        }
+
+        if bytes.get(pos) == Some(&b'.') {
+            pos += 1;
+            if !digits(&mut pos) {
+                return None;
+            }
+        }
+
+        if matches!(bytes.get(pos), Some(b'e' | b'E')) {
+            pos += 1;
+            if matches!(bytes.get(pos), Some(b'+' | b'-')) {
+                pos += 1;
+            }
+            if !digits(&mut pos) {
+                return None;
+            }
+        }
+
+        self.pos = pos;
+        Some(())
+    }
+}
+
+fn hex4(chars: &mut std::str::CharIndices<'_>) -> Option<u32> {
+    (0..4).try_fold(0, |n, _| Some(n << 4 | chars.next()?.1.to_digit(16)?))
+}
@@ -0,0 +1,197 @@
+//! JSON Web Signatures with the HMAC algorithms from RFC 7518, in the compact
+//! serialization from RFC 7515, as used by JWTs.
+//!
+//! ```
+//! # use common_crypto::jws::{self, Algorithm};
+//! let key = [0x42; 32];
+//! let token = jws::sign(Algorithm::HS256, &key, br#"{"sub":"1234567890"}"#).unwrap();
+//!
+//! assert_eq!(
+//!     jws::verify(Algorithm::HS256, &key, &token).unwrap(),
+//!     br#"{"sub":"1234567890"}"#
+//! );
+//! ```
+
+use crate::encoding::{base64url_decode, base64url_encode};
+use crate::hmac::{self, HMAC};
+use crate::json;
+use std::fmt::{self, Display};
+
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum JWSError {
+    /// The token or its header is malformed.
+    Format,
+    /// The header names a different algorithm to the one expected, or one
+    /// which isn't supported such as `none`.
+    Algorithm,
+    /// The header has a critical extension, such as an unencoded payload.
+    Unsupported,
+    /// The key is shorter than the hash, which RFC 7518 forbids.
+    KeySize,
+    /// The signature didn't match, due to the wrong key or a modified token.
+    Signature,
+}
+
+impl std::error::Error for JWSError {}
+
+impl Display for JWSError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Format => f.write_str("malformed token"),
+            Self::Algorithm => f.write_str("unexpected algorithm"),
+            Self::Unsupported => f.write_str("unsupported header parameter"),
+            Self::KeySize => f.write_str("key is shorter than the hash"),
+            Self::Signature => f.write_str("signature verification failed"),
+        }
+    }
+}
+
+/// The `alg` header parameter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Algorithm {
+    HS256,
+    HS384,
+    HS512,
+}
+
+impl Algorithm {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "HS256" => Some(Self::HS256),
+            "HS384" => Some(Self::HS384),
+            "HS512" => Some(Self::HS512),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::HS256 => "HS256",
+            Self::HS384 => "HS384",
+            Self::HS512 => "HS512",
+        }
+    }
+
+    /// The length of the signature, and the minimum length of the key.
+    pub fn signature_len(self) -> usize {
+        match self {
+            Self::HS256 => 32,
+            Self::HS384 => 48,
+            Self::HS512 => 64,
+        }
+    }
+
+    fn sign(self, key: &[u8], input: &[u8]) -> Result<Vec<u8>, JWSError> {
+        if key.len() < self.signature_len() {
+            return Err(JWSError::KeySize);
+        }
+
+        Ok(match self {
+            Self::HS256 => HMAC::sha256(key, input).to_vec(),
+            Self::HS384 => HMAC::sha384(key, input).to_vec(),
+            Self::HS512 => HMAC::sha512(key, input).to_vec(),
+        })
+    }
+}
+
+/// The protected header of a token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub alg: Algorithm,
+    /// The media type, usually `JWT`.
+    pub typ: Option<String>,
+    /// The key ID, for choosing the key.
+    pub kid: Option<String>,
+}
+
+impl Header {
+    /// Reads the header of a token without verifying it, which is only
+    /// useful for choosing the key.
+    pub fn from_token(token: &str) -> Result<Self, JWSError> {
+        let header = json::Header::from_token(token).ok_or(JWSError::Format)?;
+        let string = |name: &str| header.string(name, JWSError::Format);
+
+        // Critical extensions, such as `b64` from RFC 7797, must be
+        // understood.
+        if header.contains("crit") {
+            return Err(JWSError::Unsupported);
+        }
+
+        let alg = string("alg")?.ok_or(JWSError::Format)?;
+
+        Ok(Self {
+            alg: Algorithm::from_name(&alg).ok_or(JWSError::Algorithm)?,
+            typ: string("typ")?,
+            kid: string("kid")?,
+        })
+    }
+
+    fn to_json(&self) -> String {
+        let mut json = format!("{{\"alg\":{}", json::string(self.alg.name()));
+
+        for (name, value) in [("typ", &self.typ), ("kid", &self.kid)] {
+            if let Some(value) = value {
+                json.push_str(&format!(",{}:{}", json::string(name), json::string(value)));
+            }
+        }
+
+        json.push('}');
+        json
+    }
+}
+
+/// Signs the payload, returning the compact serialization.
+pub fn sign(alg: Algorithm, key: &[u8], payload: impl AsRef<[u8]>) -> Result<String, JWSError> {
+    let header = Header {
+        alg,
+        typ: None,
+        kid: None,
+    };
+
+    sign_with_header(&header, key, payload)
+}
+
+/// Signs the payload like [`sign`], with the media type and key ID from the
+/// header.
+pub fn sign_with_header(
+    header: &Header,
+    key: &[u8],
+    payload: impl AsRef<[u8]>,
+) -> Result<String, JWSError> {
+    let input = format!(
+        "{}.{}",
+        base64url_encode(header.to_json().as_bytes()),
+        base64url_encode(payload.as_ref())
+    );
+    let signature = header.alg.sign(key, input.as_bytes())?;
+
+    Ok(format!("{input}.{}", base64url_encode(&signature)))
+}
+
+/// Verifies a token in the compact serialization, returning the payload.
+///
+/// The algorithm in the header must be the one expected, rather than trusting
+/// the token to say how it should be verified.
+pub fn verify(alg: Algorithm, key: &[u8], token: &str) -> Result<Vec<u8>, JWSError> {
+    let (input, signature) = token.rsplit_once('.').ok_or(JWSError::Format)?;
+    let (_, payload) = input.split_once('.').ok_or(JWSError::Format)?;
+
+    if payload.contains('.') {
+        return Err(JWSError::Format);
+    }
+
+    if Header::from_token(token)?.alg != alg {
+        return Err(JWSError::Algorithm);
+    }
+
+    let payload = base64url_decode(payload).ok_or(JWSError::Format)?;
+    let signature = base64url_decode(signature).ok_or(JWSError::Format)?;
+
+    if !hmac::constant_time_eq(&alg.sign(key, input.as_bytes())?, &signature) {
+        return Err(JWSError::Signature);
+    }
+
+    Ok(payload)
+}
@@ -0,0 +1,112 @@
+//! Deriving keys from passwords.
+
+use crate::cryptor::{CryptorError, Status};
+use std::ffi::c_void;
+use std::time::Duration;
+
+/// The pseudorandom function used by PBKDF2, which is HMAC with the given
+/// hash.
+#[repr(u32)]
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PRF {
+    SHA1 = 1,
+    SHA224 = 2,
+    SHA256 = 3,
+    SHA384 = 4,
+    SHA512 = 5,
+}
+
+/// kCCPBKDF2, the only algorithm supported.
+const PBKDF2: u32 = 2;
+
+/// The longest salt CCCalibratePBKDF accepts.
+const MAX_SALT_LEN: usize = 128;
+
+extern "C" {
+    fn CCKeyDerivationPBKDF(
+        algorithm: u32,
+        password: *const c_void,
+        password_len: usize,
+        salt: *const u8,
+        salt_len: usize,
+        prf: PRF,
+        rounds: u32,
+        derived_key: *mut u8,
+        derived_key_len: usize,
+    ) -> Status;
+
+    fn CCCalibratePBKDF(
+        algorithm: u32,
+        password_len: usize,
+        salt_len: usize,
+        prf: PRF,
+        derived_key_len: usize,
+        msec: u32,
+    ) -> u32;
+}
+
+/// Derives a key of the given length with PBKDF2 from PKCS #5.
+///
+/// ```
+/// # use common_crypto::kdf::{self, PRF};
+/// let key = kdf::pbkdf2(b"password", b"salt", PRF::SHA256, 10_000, 32).unwrap();
+/// ```
+pub fn pbkdf2(
+    password: impl AsRef<[u8]>,
+    salt: impl AsRef<[u8]>,
+    prf: PRF,
+    rounds: u32,
+    len: usize,
+) -> Result<Vec<u8>, CryptorError> {
+    let password = password.as_ref();
+    let salt = salt.as_ref();
+    let mut key = vec![0u8; len];
+
+    let status = unsafe {
+        CCKeyDerivationPBKDF(
+            PBKDF2,
+            password.as_ptr() as *const c_void,
+            password.len(),
+            salt.as_ptr(),
+            salt.len(),
+            prf,
+            rounds,
+            key.as_mut_ptr(),
+            key.len(),
+        )
+    };
+
+    if status != Status::Success {
+        return Err(status.into());
+    }
+
+    Ok(key)
+}
+
+/// Estimates the number of PBKDF2 rounds which take the given time on this
+/// machine, to keep up with hardware rather than hard coding a count.
+///
+/// The salt must be between 1 and 128 bytes long, and the key mustn't be
+/// empty.
+pub fn calibrate_pbkdf2(
+    password_len: usize,
+    salt_len: usize,
+    prf: PRF,
+    len: usize,
+    duration: Duration,
+) -> Result<u32, CryptorError> {
+    if len == 0 || !(1..=MAX_SALT_LEN).contains(&salt_len) {
+        return Err(CryptorError::Param);
+    }
+
+    let msec = duration.as_millis().clamp(1, u32::MAX as u128) as u32;
+    let rounds = unsafe { CCCalibratePBKDF(PBKDF2, password_len, salt_len, prf, len, msec) };
+
+    // Errors are returned as (uint)-1 rather than a status.
+    if rounds == u32::MAX {
+        return Err(CryptorError::Param);
+    }
+
+    Ok(rounds)
+}
@@ -0,0 +1,545 @@
+//! Kerberos encryption types, using the simplified profile from RFC 3961 for
+//! AES (RFC 3962) and triple DES, and RC4-HMAC from RFC 4757.
+//!
+//! ```
+//! # use common_crypto::kerberos::{EncryptionType, Key};
+//! let key = Key::from_password(
+//!     EncryptionType::AES256CTS,
+//!     "password",
+//!     "ATHENA.MIT.EDUraeburn",
+//!     None,
+//! )
+//! .unwrap();
+//!
+//! let encrypted = key.encrypt(2, b"ticket").unwrap();
+//! assert_eq!(key.decrypt(2, encrypted).unwrap(), b"ticket");
+//! ```
+
+use crate::cryptor::{CryptorError, Mode, Padding};
+use crate::hash::MD5;
+use crate::hmac::{self, HMAC};
+use crate::kdf::{self, PRF};
+use crate::ntlm;
+use crate::random;
+use crate::spec::{Algorithm, CipherSpec};
+use std::fmt::{self, Display};
+
+/// The PBKDF2 iterations used by AES string-to-key when not specified.
+pub const DEFAULT_ITERATIONS: u32 = 4096;
+
+/// The constant used to derive the key from the password.
+const KERBEROS: &[u8] = b"kerberos";
+
+/// Weak and semi-weak DES keys, which random-to-key avoids.
+const WEAK_DES_KEYS: [[u8; 8]; 16] = [
+    [0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01],
+    [0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe],
+    [0x1f, 0x1f, 0x1f, 0x1f, 0x0e, 0x0e, 0x0e, 0x0e],
+    [0xe0, 0xe0, 0xe0, 0xe0, 0xf1, 0xf1, 0xf1, 0xf1],
+    [0x01, 0xfe, 0x01, 0xfe, 0x01, 0xfe, 0x01, 0xfe],
+    [0xfe, 0x01, 0xfe, 0x01, 0xfe, 0x01, 0xfe, 0x01],
+    [0x1f, 0xe0, 0x1f, 0xe0, 0x0e, 0xf1, 0x0e, 0xf1],
+    [0xe0, 0x1f, 0xe0, 0x1f, 0xf1, 0x0e, 0xf1, 0x0e],
+    [0x01, 0xe0, 0x01, 0xe0, 0x01, 0xf1, 0x01, 0xf1],
+    [0xe0, 0x01, 0xe0, 0x01, 0xf1, 0x01, 0xf1, 0x01],
+    [0x1f, 0xfe, 0x1f, 0xfe, 0x0e, 0xfe, 0x0e, 0xfe],
+    [0xfe, 0x1f, 0xfe, 0x1f, 0xfe, 0x0e, 0xfe, 0x0e],
+    [0x01, 0x1f, 0x01, 0x1f, 0x01, 0x0e, 0x01, 0x0e],
+    [0x1f, 0x01, 0x1f, 0x01, 0x0e, 0x01, 0x0e, 0x01],
+    [0xe0, 0xfe, 0xe0, 0xfe, 0xf1, 0xfe, 0xf1, 0xfe],
+    [0xfe, 0xe0, 0xfe, 0xe0, 0xfe, 0xf1, 0xfe, 0xf1],
+];
+
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum KerberosError {
+    /// The ciphertext or checksum is too short.
+    Format,
+    /// The checksum didn't match, due to the wrong key or key usage, or a
+    /// modified message.
+    Integrity,
+    /// Key derivation isn't defined for RC4-HMAC.
+    Unsupported,
+    Cryptor(CryptorError),
+}
+
+impl std::error::Error for KerberosError {}
+
+impl Display for KerberosError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Format => f.write_str("ciphertext is too short"),
+            Self::Integrity => f.write_str("checksum verification failed"),
+            Self::Unsupported => f.write_str("unsupported by the encryption type"),
+            Self::Cryptor(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<CryptorError> for KerberosError {
+    fn from(e: CryptorError) -> Self {
+        Self::Cryptor(e)
+    }
+}
+
+/// The encryption types, with their assigned numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EncryptionType {
+    /// `des3-cbc-sha1-kd`, deprecated by RFC 8429.
+    DES3CBCSHA1 = 16,
+    /// `aes128-cts-hmac-sha1-96`
+    AES128CTS = 17,
+    /// `aes256-cts-hmac-sha1-96`
+    AES256CTS = 18,
+    /// `rc4-hmac`, deprecated by RFC 8429.
+    RC4HMAC = 23,
+}
+
+impl EncryptionType {
+    pub fn from_id(id: i32) -> Option<Self> {
+        match id {
+            16 => Some(Self::DES3CBCSHA1),
+            17 => Some(Self::AES128CTS),
+            18 => Some(Self::AES256CTS),
+            23 => Some(Self::RC4HMAC),
+            _ => None,
+        }
+    }
+
+    pub fn key_len(self) -> usize {
+        match self {
+            Self::DES3CBCSHA1 => 24,
+            Self::AES128CTS | Self::RC4HMAC => 16,
+            Self::AES256CTS => 32,
+        }
+    }
+
+    /// The length of the random confounder prepended to the plaintext.
+    fn confounder_len(self) -> usize {
+        match self {
+            Self::AES128CTS | Self::AES256CTS => 16,
+            _ => 8,
+        }
+    }
+
+    /// The length of the checksums, which are truncated to 96 bits for AES.
+    fn checksum_len(self) -> usize {
+        match self {
+            Self::DES3CBCSHA1 => 20,
+            Self::AES128CTS | Self::AES256CTS => 12,
+            Self::RC4HMAC => 16,
+        }
+    }
+
+    fn cipher(self, mode: Option<Mode>) -> CipherSpec {
+        let algorithm = match self {
+            Self::DES3CBCSHA1 => Algorithm::TDES,
+            Self::AES128CTS | Self::AES256CTS => Algorithm::AES,
+            Self::RC4HMAC => Algorithm::RC4,
+        };
+
+        CipherSpec {
+            algorithm,
+            mode,
+            padding: Padding::None,
+            key_len: self.key_len(),
+        }
+    }
+}
+
+/// Folds or stretches the input to `len` bytes, as described by RFC 3961.
+///
+/// Copies of the input, each rotated right by a further 13 bits, are
+/// concatenated to the least common multiple of the lengths, then added in
+/// `len` byte chunks with one's complement addition.
+pub fn n_fold(input: &[u8], len: usize) -> Vec<u8> {
+    if input.is_empty() || len == 0 {
+        return vec![0; len];
+    }
+
+    let lcm = input.len() / gcd(input.len(), len) * len;
+    let mut output = vec![0; len];
+
+    let stretched: Vec<u8> = (0..lcm / input.len())
+        .flat_map(|i| rotate_right(input, 13 * i))
+        .collect();
+
+    for chunk in stretched.chunks_exact(len) {
+        let mut carry = 0;
+        for (sum, byte) in output.iter_mut().zip(chunk).rev() {
+            let n = *sum as u16 + *byte as u16 + carry;
+            *sum = n as u8;
+            carry = n >> 8;
+        }
+
+        // The carry wraps around to the least significant byte.
+        for sum in output.iter_mut().rev() {
+            if carry == 0 {
+                break;
+            }
+
+            let n = *sum as u16 + carry;
+            *sum = n as u8;
+            carry = n >> 8;
+        }
+    }
+
+    output
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn rotate_right(input: &[u8], bits: usize) -> Vec<u8> {
+    let len = input.len() * 8;
+    let bits = bits % len;
+    let bit = |i: usize| (input[i / 8] >> (7 - i % 8)) & 1;
+
+    (0..input.len())
+        .map(|byte| (0..8).fold(0, |n, i| n << 1 | bit((byte * 8 + i + len - bits) % len)))
+        .collect()
+}
+
+/// Expands 21 random bytes to a triple DES key, setting the parity bits.
+fn des3_random_to_key(random: &[u8]) -> Vec<u8> {
+    fn parity(byte: u8) -> u8 {
+        let byte = byte & 0xfe;
+        byte | (byte.count_ones() & 1 == 0) as u8
+    }
+
+    let mut key = Vec::with_capacity(24);
+
+    for seven in random.chunks_exact(7) {
+        let mut des_key = [0; 8];
+        for (i, byte) in seven.iter().enumerate() {
+            des_key[i] = parity(*byte);
+            // The low bits, overwritten by the parity, are stored in the
+            // last byte.
+            des_key[7] |= (byte & 1) << (i + 1);
+        }
+        des_key[7] = parity(des_key[7]);
+
+        if WEAK_DES_KEYS.contains(&des_key) {
+            des_key[7] ^= 0xf0;
+        }
+
+        key.extend_from_slice(&des_key);
+    }
+
+    key
+}
+
+/// The key usage number followed by a byte identifying the derived key.
+fn usage_constant(usage: u32, kind: u8) -> [u8; 5] {
+    let mut constant = [0; 5];
+    constant[..4].copy_from_slice(&usage.to_be_bytes());
+    constant[4] = kind;
+    constant
+}
+
+/// RC4-HMAC maps a couple of key usage numbers to Microsoft's, and encodes
+/// them in little endian.
+fn rc4_usage(usage: u32) -> [u8; 4] {
+    let usage = match usage {
+        3 => 8,
+        23 => 13,
+        _ => usage,
+    };
+
+    usage.to_le_bytes()
+}
+
+fn aes(key: &[u8], mode: Mode) -> CipherSpec {
+    CipherSpec {
+        algorithm: Algorithm::AES,
+        mode: Some(mode),
+        padding: Padding::None,
+        key_len: key.len(),
+    }
+}
+
+/// Encrypts with AES in CBC mode with ciphertext stealing, swapping the last
+/// two blocks even when the input is a multiple of the block size.
+fn cts_encrypt(key: &[u8], input: &[u8]) -> Result<Vec<u8>, CryptorError> {
+    let cbc = aes(key, Mode::CBC);
+    let iv = [0; 16];
+
+    if input.len() <= 16 {
+        return cbc.encrypt(key, Some(&iv), input);
+    }
+
+    let mut padded = input.to_vec();
+    padded.resize(input.len().div_ceil(16) * 16, 0);
+
+    let mut output = cbc.encrypt(key, Some(&iv), padded)?;
+    let len = output.len();
+    let (first, last) = output.split_at_mut(len - 16);
+    first[len - 32..].swap_with_slice(last);
+    output.truncate(input.len());
+
+    Ok(output)
+}
+
+fn cts_decrypt(key: &[u8], input: &[u8]) -> Result<Vec<u8>, CryptorError> {
+    let cbc = aes(key, Mode::CBC);
+    let ecb = aes(key, Mode::ECB);
+    let iv = [0; 16];
+
+    if input.len() <= 16 {
+        return cbc.decrypt(key, Some(&iv), input);
+    }
+
+    // The last block may be partial, preceded by the full final block.
+    let partial = (input.len() - 1) % 16 + 1;
+    let (head, tail) = input.split_at(input.len() - partial - 16);
+    let (last_block, partial_block) = tail.split_at(16);
+
+    // The final block decrypts to the partial block's plaintext xor its
+    // ciphertext, which was padded with the end of this.
+    let decrypted = ecb.decrypt(key, None, last_block)?;
+    let last: Vec<u8> = decrypted
+        .iter()
+        .zip(partial_block)
+        .map(|(a, b)| a ^ b)
+        .collect();
+
+    let mut stolen = head.to_vec();
+    stolen.extend_from_slice(partial_block);
+    stolen.extend_from_slice(&decrypted[partial..]);
+
+    let mut output = cbc.decrypt(key, Some(&iv), stolen)?;
+    output.extend_from_slice(&last);
+
+    Ok(output)
+}
+
+/// A key for an encryption type.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Key {
+    etype: EncryptionType,
+    key: Vec<u8>,
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Key")
+            .field("etype", &self.etype)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Key {
+    pub fn new(etype: EncryptionType, key: impl Into<Vec<u8>>) -> Result<Self, KerberosError> {
+        let key = key.into();
+        if key.len() != etype.key_len() {
+            return Err(CryptorError::KeySize.into());
+        }
+
+        Ok(Self { etype, key })
+    }
+
+    /// Derives a key from the password and salt, which is usually the realm
+    /// followed by the principal's name. The iterations are only used by AES,
+    /// and RC4-HMAC uses the NT hash without a salt.
+    pub fn from_password(
+        etype: EncryptionType,
+        password: &str,
+        salt: impl AsRef<[u8]>,
+        iterations: Option<u32>,
+    ) -> Result<Self, KerberosError> {
+        let salt = salt.as_ref();
+
+        let key = match etype {
+            EncryptionType::AES128CTS | EncryptionType::AES256CTS => kdf::pbkdf2(
+                password,
+                salt,
+                PRF::SHA1,
+                iterations.unwrap_or(DEFAULT_ITERATIONS),
+                etype.key_len(),
+            )?,
+            EncryptionType::DES3CBCSHA1 => {
+                let folded = n_fold(&[password.as_bytes(), salt].concat(), 21);
+                des3_random_to_key(&folded)
+            }
+            EncryptionType::RC4HMAC => return Self::new(etype, ntlm::nt_hash(password)),
+        };
+
+        Self::new(etype, key)?.derive_key(KERBEROS)
+    }
+
+    pub fn etype(&self) -> EncryptionType {
+        self.etype
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// `DR`, which encrypts the constant folded to the block size, then
+    /// repeatedly encrypts the previous block until there are enough bytes
+    /// for a key.
+    pub fn derive_random(&self, constant: &[u8]) -> Result<Vec<u8>, KerberosError> {
+        let (cipher, block_size, len) = match self.etype {
+            EncryptionType::DES3CBCSHA1 => (self.etype.cipher(Some(Mode::CBC)), 8, 21),
+            EncryptionType::AES128CTS | EncryptionType::AES256CTS => {
+                (self.etype.cipher(Some(Mode::ECB)), 16, self.key.len())
+            }
+            EncryptionType::RC4HMAC => return Err(KerberosError::Unsupported),
+        };
+
+        let iv = vec![0; block_size];
+        let iv = (cipher.mode == Some(Mode::CBC)).then_some(&iv[..]);
+
+        let mut block = n_fold(constant, block_size);
+        let mut random = Vec::with_capacity(len + block_size);
+
+        while random.len() < len {
+            block = cipher.encrypt(&self.key, iv, &block)?;
+            random.extend_from_slice(&block);
+        }
+
+        random.truncate(len);
+        Ok(random)
+    }
+
+    /// `DK`, which converts the output of [`derive_random`](Self::derive_random)
+    /// to a key.
+    pub fn derive_key(&self, constant: &[u8]) -> Result<Self, KerberosError> {
+        let random = self.derive_random(constant)?;
+
+        let key = match self.etype {
+            EncryptionType::DES3CBCSHA1 => des3_random_to_key(&random),
+            _ => random,
+        };
+
+        Self::new(self.etype, key)
+    }
+
+    /// Encrypts the plaintext with a random confounder, followed by a
+    /// checksum.
+    pub fn encrypt(
+        &self,
+        usage: u32,
+        plaintext: impl AsRef<[u8]>,
+    ) -> Result<Vec<u8>, KerberosError> {
+        let mut data = vec![0; self.etype.confounder_len()];
+        random::fill(&mut data)?;
+        data.extend_from_slice(plaintext.as_ref());
+
+        if self.etype == EncryptionType::RC4HMAC {
+            let k1 = HMAC::md5(&self.key, rc4_usage(usage));
+            let checksum = HMAC::md5(k1, &data);
+            let k3 = HMAC::md5(k1, checksum);
+
+            let encrypted = self.etype.cipher(None).encrypt(k3, None, &data)?;
+            return Ok([&checksum[..], &encrypted].concat());
+        }
+
+        let ke = self.derive_key(&usage_constant(usage, 0xaa))?;
+        let ki = self.derive_key(&usage_constant(usage, 0x55))?;
+
+        let mut output = if self.etype == EncryptionType::DES3CBCSHA1 {
+            // The padding isn't removed when decrypting.
+            data.resize(data.len().div_ceil(8) * 8, 0);
+
+            let cbc = self.etype.cipher(Some(Mode::CBC));
+            cbc.encrypt(&ke.key, Some(&[0; 8]), &data)?
+        } else {
+            cts_encrypt(&ke.key, &data)?
+        };
+
+        let checksum = HMAC::sha1(&ki.key, &data);
+        output.extend_from_slice(&checksum[..self.etype.checksum_len()]);
+
+        Ok(output)
+    }
+
+    /// Decrypts and verifies the checksum, removing the confounder. Triple DES
+    /// plaintext is padded with zeroes to a multiple of 8 bytes, which is left
+    /// to the caller to remove.
+    pub fn decrypt(
+        &self,
+        usage: u32,
+        ciphertext: impl AsRef<[u8]>,
+    ) -> Result<Vec<u8>, KerberosError> {
+        let ciphertext = ciphertext.as_ref();
+        let confounder_len = self.etype.confounder_len();
+        let checksum_len = self.etype.checksum_len();
+
+        if ciphertext.len() < confounder_len + checksum_len {
+            return Err(KerberosError::Format);
+        }
+
+        if self.etype == EncryptionType::RC4HMAC {
+            let (checksum, encrypted) = ciphertext.split_at(checksum_len);
+            let k1 = HMAC::md5(&self.key, rc4_usage(usage));
+            let k3 = HMAC::md5(k1, checksum);
+
+            let data = self.etype.cipher(None).decrypt(k3, None, encrypted)?;
+            if !hmac::constant_time_eq(&HMAC::md5(k1, &data), checksum) {
+                return Err(KerberosError::Integrity);
+            }
+
+            return Ok(data[confounder_len..].to_vec());
+        }
+
+        let (encrypted, checksum) = ciphertext.split_at(ciphertext.len() - checksum_len);
+        let ke = self.derive_key(&usage_constant(usage, 0xaa))?;
+        let ki = self.derive_key(&usage_constant(usage, 0x55))?;
+
+        let data = if self.etype == EncryptionType::DES3CBCSHA1 {
+            let cbc = self.etype.cipher(Some(Mode::CBC));
+            cbc.decrypt(&ke.key, Some(&[0; 8]), encrypted)?
+        } else {
+            cts_decrypt(&ke.key, encrypted)?
+        };
+
+        let expected = HMAC::sha1(&ki.key, &data);
+        if !hmac::constant_time_eq(&expected[..checksum_len], checksum) {
+            return Err(KerberosError::Integrity);
+        }
+
+        Ok(data[confounder_len..].to_vec())
+    }
+
+    /// The keyed checksum of the data: `hmac-sha1-96-aes128`,
+    /// `hmac-sha1-96-aes256`, `hmac-sha1-des3-kd` or `hmac-md5` for RC4.
+    pub fn checksum(&self, usage: u32, data: impl AsRef<[u8]>) -> Result<Vec<u8>, KerberosError> {
+        let data = data.as_ref();
+
+        if self.etype == EncryptionType::RC4HMAC {
+            let signing_key = HMAC::md5(&self.key, b"signaturekey\0");
+            let mut md5 = MD5::new();
+            md5.update(rc4_usage(usage));
+            md5.update(data);
+
+            return Ok(HMAC::md5(signing_key, md5.finish()).to_vec());
+        }
+
+        let kc = self.derive_key(&usage_constant(usage, 0x99))?;
+        let checksum = HMAC::sha1(&kc.key, data);
+
+        Ok(checksum[..self.etype.checksum_len()].to_vec())
+    }
+
+    pub fn verify_checksum(
+        &self,
+        usage: u32,
+        data: impl AsRef<[u8]>,
+        checksum: impl AsRef<[u8]>,
+    ) -> Result<(), KerberosError> {
+        let expected = self.checksum(usage, data)?;
+
+        if !hmac::constant_time_eq(&expected, checksum.as_ref()) {
+            return Err(KerberosError::Integrity);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+//! AES key wrap from RFC 3394, for protecting keys with a key encryption key.
+//!
+//! ```
+//! # use common_crypto::keywrap;
+//! let kek = [0x42; 16];
+//! let wrapped = keywrap::wrap(&kek, [0x17; 32]).unwrap();
+//! assert_eq!(wrapped.len(), 40);
+//! assert_eq!(keywrap::unwrap(&kek, wrapped).unwrap(), [0x17; 32]);
+//! ```
+
+use crate::cryptor::{CryptorError, Status};
+
+/// kCCWRAPAES, the only algorithm supported.
+const WRAP_AES: u32 = 1;
+
+extern "C" {
+    static CCrfc3394_iv: *const u8;
+    static CCrfc3394_ivLen: usize;
+
+    fn CCSymmetricKeyWrap(
+        algorithm: u32,
+        iv: *const u8,
+        iv_len: usize,
+        kek: *const u8,
+        kek_len: usize,
+        raw_key: *const u8,
+        raw_key_len: usize,
+        wrapped_key: *mut u8,
+        wrapped_key_len: *mut usize,
+    ) -> i32;
+
+    fn CCSymmetricKeyUnwrap(
+        algorithm: u32,
+        iv: *const u8,
+        iv_len: usize,
+        kek: *const u8,
+        kek_len: usize,
+        wrapped_key: *const u8,
+        wrapped_key_len: usize,
+        raw_key: *mut u8,
+        raw_key_len: *mut usize,
+    ) -> i32;
+}
+
+/// Wraps a key, which must be a multiple of 8 bytes and at least 16, adding 8
+/// bytes. The key encryption key must be 16, 24 or 32 bytes.
+pub fn wrap(kek: &[u8], key: impl AsRef<[u8]>) -> Result<Vec<u8>, CryptorError> {
+    let key = key.as_ref();
+    check_kek(kek)?;
+
+    if key.len() < 16 || !key.len().is_multiple_of(8) {
+        return Err(CryptorError::Param);
+    }
+
+    let mut wrapped = vec![0u8; key.len() + 8];
+    let mut wrapped_len = wrapped.len();
+
+    let status = unsafe {
+        CCSymmetricKeyWrap(
+            WRAP_AES,
+            CCrfc3394_iv,
+            CCrfc3394_ivLen,
+            kek.as_ptr(),
+            kek.len(),
+            key.as_ptr(),
+            key.len(),
+            wrapped.as_mut_ptr(),
+            &mut wrapped_len,
+        )
+    };
+
+    check(status)?;
+
+    wrapped.truncate(wrapped_len);
+    Ok(wrapped)
+}
+
+/// Unwraps a key, failing with [`CryptorError::Decode`] if the integrity
+/// check fails due to the wrong key encryption key or a modified key.
+pub fn unwrap(kek: &[u8], wrapped: impl AsRef<[u8]>) -> Result<Vec<u8>, CryptorError> {
+    let wrapped = wrapped.as_ref();
+    check_kek(kek)?;
+
+    if wrapped.len() < 24 || !wrapped.len().is_multiple_of(8) {
+        return Err(CryptorError::Param);
+    }
+
+    let mut key = vec![0u8; wrapped.len() - 8];
+    let mut key_len = key.len();
+
+    let status = unsafe {
+        CCSymmetricKeyUnwrap(
+            WRAP_AES,
+            CCrfc3394_iv,
+            CCrfc3394_ivLen,
+            kek.as_ptr(),
+            kek.len(),
+            wrapped.as_ptr(),
+            wrapped.len(),
+            key.as_mut_ptr(),
+            &mut key_len,
+        )
+    };
+
+    check(status)?;
+
+    key.truncate(key_len);
+    Ok(key)
+}
+
+/// The key encryption key is an AES key.
+fn check_kek(kek: &[u8]) -> Result<(), CryptorError> {
+    match kek.len() {
+        16 | 24 | 32 => Ok(()),
+        _ => Err(CryptorError::KeySize),
+    }
+}
+
+/// These functions are declared to return an `int` rather than a
+/// `CCCryptorStatus`, so other values are possible.
+fn check(status: i32) -> Result<(), CryptorError> {
+    let known = [
+        Status::Success,
+        Status::ParamError,
+        Status::DecodeError,
+        Status::MemoryFailure,
+        Status::UnspecifiedError,
+    ];
+
+    match known.into_iter().find(|known| *known as i32 == status) {
+        Some(Status::Success) => Ok(()),
+        Some(status) => Err(status.into()),
+        None => Err(CryptorError::Unexpected(status)),
+    }
+}
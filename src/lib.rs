@@ -3,5 +3,30 @@
 //! The relevant Apple documentation is in the man pages, see `man CCCryptor`.
 
 pub mod cryptor;
+pub mod ctr;
+mod der;
+#[cfg(feature = "encoding")]
+pub mod encoding;
+// Without the feature only the parts other modules use are reachable, and the
+// names match the public ones.
+#[cfg(not(feature = "encoding"))]
+#[allow(dead_code, clippy::upper_case_acronyms)]
+mod encoding;
+pub mod envelope;
 pub mod hash;
 pub mod hmac;
+mod json;
+pub mod jwe;
+pub mod jws;
+pub mod kdf;
+pub mod kerberos;
+pub mod keywrap;
+pub mod ntlm;
+pub mod openpgp;
+pub mod openssl;
+pub mod otp;
+pub mod pkcs12;
+pub mod pkcs8;
+pub mod random;
+pub mod spec;
+pub mod zip;
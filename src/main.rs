@@ -0,0 +1,294 @@
+//! Exposes the library to shell scripts.
+
+use common_crypto::encoding::{base64_decode, base64_encode, hex_decode, hex_encode};
+use common_crypto::hash::{self, Algorithm, Hasher};
+use common_crypto::hmac;
+use common_crypto::spec::CipherSpec;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage:
+    common-crypto hash --alg ALG [--base64] [FILE...]
+    common-crypto hmac --alg ALG (--key-hex HEX | --key TEXT) [--base64] [FILE]
+    common-crypto enc --cipher NAME --key-hex HEX [--iv-hex HEX] [--base64] [FILE]
+    common-crypto dec --cipher NAME --key-hex HEX [--iv-hex HEX] [--base64] [FILE]
+
+Input is read from the files, or stdin when none or - is given.
+
+Hash algorithms are md2, md4, md5, sha1, sha224, sha256, sha384 and sha512,
+of which md5 and the sha family are supported by hmac. Ciphers are named as
+by openssl, such as aes-256-cbc.
+
+Options:
+    --base64    Output digests and ciphertext as base64 instead of hex and raw
+                bytes, and decrypt base64 input.
+";
+
+/// Errors which are the caller's fault, for which the usage is shown.
+#[derive(Debug)]
+struct UsageError(String);
+
+impl Error for UsageError {}
+
+impl std::fmt::Display for UsageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn usage_error(message: impl Into<String>) -> Box<dyn Error> {
+    Box::new(UsageError(message.into()))
+}
+
+#[derive(Debug, Default)]
+struct Options {
+    algorithm: Option<Algorithm>,
+    cipher: Option<CipherSpec>,
+    key: Option<Vec<u8>>,
+    iv: Option<Vec<u8>>,
+    base64: bool,
+    files: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, Box<dyn Error>> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| usage_error(format!("{arg} requires a value")))
+            };
+            let hex = |s: String| hex_decode(&s).ok_or_else(|| usage_error("invalid hex"));
+
+            match arg.as_str() {
+                "--alg" => {
+                    let value = value()?;
+                    let algorithm = value
+                        .parse()
+                        .map_err(|_| usage_error(format!("unknown hash algorithm {value}")))?;
+                    options.algorithm = Some(algorithm);
+                }
+                "--cipher" => {
+                    let value = value()?;
+                    let cipher = value
+                        .parse()
+                        .map_err(|e| usage_error(format!("{value}: {e}")))?;
+                    options.cipher = Some(cipher);
+                }
+                "--key-hex" => options.key = Some(hex(value()?)?),
+                "--key" => options.key = Some(value()?.into_bytes()),
+                "--iv-hex" => options.iv = Some(hex(value()?)?),
+                "--base64" => options.base64 = true,
+                "-" => options.files.push(arg),
+                _ if arg.starts_with('-') => {
+                    return Err(usage_error(format!("unknown option {arg}")))
+                }
+                _ => options.files.push(arg),
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn algorithm(&self) -> Result<Algorithm, Box<dyn Error>> {
+        self.algorithm
+            .ok_or_else(|| usage_error("--alg is required"))
+    }
+
+    fn key(&self) -> Result<&[u8], Box<dyn Error>> {
+        self.key
+            .as_deref()
+            .ok_or_else(|| usage_error("a key is required"))
+    }
+
+    /// The single input, which defaults to stdin.
+    fn input(&self) -> Result<Box<dyn Read>, Box<dyn Error>> {
+        match &self.files[..] {
+            [] => open("-"),
+            [file] => open(file),
+            _ => Err(usage_error("only one input file may be given")),
+        }
+    }
+
+    fn encode(&self, digest: &[u8]) -> String {
+        if self.base64 {
+            base64_encode(digest)
+        } else {
+            hex_encode(digest)
+        }
+    }
+}
+
+fn open(file: &str) -> Result<Box<dyn Read>, Box<dyn Error>> {
+    if file == "-" {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+
+    let f = File::open(file).map_err(|e| format!("{file}: {e}"))?;
+    Ok(Box::new(f))
+}
+
+/// Prints a line per file in the format of `sha256sum`.
+fn hash(options: &Options) -> Result<(), Box<dyn Error>> {
+    let algorithm = options.algorithm()?;
+    let files = match &options.files[..] {
+        [] => &["-".to_string()][..],
+        files => files,
+    };
+
+    let mut stdout = io::stdout().lock();
+
+    for file in files {
+        let digest = if file == "-" {
+            let mut hasher = Hasher::new(algorithm);
+            io::copy(&mut io::stdin().lock(), &mut hasher)?;
+            hasher.finish()
+        } else {
+            hash::hash_file(file, algorithm).map_err(|e| format!("{file}: {e}"))?
+        };
+
+        writeln!(stdout, "{}  {file}", options.encode(&digest))?;
+    }
+
+    Ok(())
+}
+
+fn hmac(options: &Options) -> Result<(), Box<dyn Error>> {
+    let algorithm = options.algorithm()?;
+    let key = options.key()?;
+    let mut input = BufReader::new(options.input()?);
+
+    macro_rules! auth_code {
+        ($context:ident) => {{
+            let mut context = hmac::$context::new(key);
+            for_each_chunk(&mut input, |chunk| context.update(chunk))?;
+            context.finish().to_vec()
+        }};
+    }
+
+    let auth_code = match algorithm {
+        Algorithm::MD5 => auth_code!(MD5),
+        Algorithm::SHA1 => auth_code!(SHA1),
+        Algorithm::SHA224 => auth_code!(SHA224),
+        Algorithm::SHA256 => auth_code!(SHA256),
+        Algorithm::SHA384 => auth_code!(SHA384),
+        Algorithm::SHA512 => auth_code!(SHA512),
+        _ => return Err(usage_error(format!("{algorithm} is not supported by hmac"))),
+    };
+
+    println!("{}", options.encode(&auth_code));
+
+    Ok(())
+}
+
+/// Passes each buffered chunk of the reader to `f` until the end of input.
+fn for_each_chunk(mut reader: impl BufRead, mut f: impl FnMut(&[u8])) -> io::Result<()> {
+    loop {
+        let chunk = match reader.fill_buf() {
+            Ok([]) => return Ok(()),
+            Ok(chunk) => chunk,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        let len = chunk.len();
+        f(chunk);
+        reader.consume(len);
+    }
+}
+
+/// Streams the input through the cipher to stdout.
+fn crypt(options: &Options, encrypt: bool) -> Result<(), Box<dyn Error>> {
+    let cipher = options
+        .cipher
+        .as_ref()
+        .ok_or_else(|| usage_error("--cipher is required"))?;
+    let key = options.key()?;
+    let iv = options.iv.as_deref();
+
+    let cryptor = if encrypt {
+        cipher.new_encryptor(key, iv)?
+    } else {
+        cipher.new_decryptor(key, iv)?
+    };
+
+    let mut input = options.input()?;
+    if options.base64 && !encrypt {
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+        let data = base64_decode(&text).ok_or("invalid base64 input")?;
+        input = Box::new(io::Cursor::new(data));
+    }
+
+    let mut ciphertext = Vec::new();
+    let mut stdout = io::stdout().lock();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut output = Vec::new();
+
+    loop {
+        let read = match input.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        cryptor.update(&buffer[..read], &mut output)?;
+
+        if options.base64 && encrypt {
+            ciphertext.extend_from_slice(&output);
+        } else {
+            stdout.write_all(&output)?;
+        }
+    }
+
+    cryptor.finish(&mut output)?;
+
+    if options.base64 && encrypt {
+        ciphertext.extend_from_slice(&output);
+        writeln!(stdout, "{}", base64_encode(&ciphertext))?;
+    } else {
+        stdout.write_all(&output)?;
+    }
+
+    Ok(())
+}
+
+fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let command = args
+        .next()
+        .ok_or_else(|| usage_error("a command is required"))?;
+
+    if command == "-h" || command == "--help" {
+        print!("{USAGE}");
+        return Ok(());
+    }
+
+    let options = Options::parse(args)?;
+
+    match command.as_str() {
+        "hash" => hash(&options),
+        "hmac" => hmac(&options),
+        "enc" => crypt(&options, true),
+        "dec" => crypt(&options, false),
+        _ => Err(usage_error(format!("unknown command {command}"))),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("common-crypto: {e}");
+            if e.is::<UsageError>() {
+                eprint!("\n{USAGE}");
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! NTLM password hashes and challenge responses, as described by MS-NLMP.
+//!
+//! Only the cryptography is provided, the messages themselves have to be
+//! built and parsed by the caller.
+//!
+//! ```
+//! # use common_crypto::ntlm;
+//! let hash = ntlm::nt_hash_v2("Password", "User", "Domain");
+//! let server_challenge = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+//! let client_challenge = [0xaa; 8];
+//! let timestamp = ntlm::timestamp(std::time::SystemTime::now());
+//!
+//! let response = ntlm::v2_response(&hash, &server_challenge, &client_challenge, timestamp, &[]);
+//! ```
+
+use crate::cryptor::{Config, Cryptor, CryptorError, Padding};
+use crate::hash::{Hash, MD5};
+use crate::hmac::{self, HMAC};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The plaintext encrypted with the halves of the password by the LM hash.
+const LM_MAGIC: &[u8; 8] = b"KGS!@#$%";
+
+/// The seconds between 1601, the Windows epoch, and 1970.
+const WINDOWS_EPOCH_OFFSET: u64 = 11_644_473_600;
+
+fn utf16le(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+/// The NT hash, `NTOWFv1`, which is the MD4 of the UTF-16LE password.
+pub fn nt_hash(password: &str) -> [u8; 16] {
+    Hash::md4(utf16le(password))
+}
+
+/// The LM hash, `LMOWFv1`, which encrypts a constant with the halves of the
+/// upper cased password, truncated or padded to 14 bytes. Only ASCII
+/// passwords produce the same hash as Windows, which uses the OEM code page.
+pub fn lm_hash(password: &str) -> Result<[u8; 16], CryptorError> {
+    let mut key = [0; 14];
+    let password = password.to_uppercase();
+    let len = password.len().min(14);
+    key[..len].copy_from_slice(&password.as_bytes()[..len]);
+
+    let mut hash = [0; 16];
+    for (half, key) in hash.chunks_exact_mut(8).zip(key.chunks_exact(7)) {
+        half.copy_from_slice(&des(key.try_into().unwrap(), LM_MAGIC)?);
+    }
+
+    Ok(hash)
+}
+
+/// Expands a 7 byte key to an 8 byte DES key, inserting an odd parity bit
+/// after every 7 bits.
+pub fn expand_des_key(key: &[u8; 7]) -> [u8; 8] {
+    let bits = key.iter().fold(0u64, |n, b| n << 8 | *b as u64);
+    let mut expanded = [0; 8];
+
+    for (i, byte) in expanded.iter_mut().enumerate() {
+        let seven = (bits >> (49 - 7 * i)) as u8 & 0x7f;
+        let parity = (seven.count_ones() & 1 == 0) as u8;
+        *byte = seven << 1 | parity;
+    }
+
+    expanded
+}
+
+fn des(key: &[u8; 7], data: &[u8; 8]) -> Result<[u8; 8], CryptorError> {
+    let key = expand_des_key(key);
+    let encrypted = Cryptor::encrypt(&Config::des(&key).ecb(Padding::None), data)?;
+    Ok(encrypted.try_into().unwrap())
+}
+
+/// `DESL`, which encrypts the data with each third of the 16 byte key padded
+/// with zeroes to 21 bytes.
+pub fn desl(key: &[u8; 16], data: &[u8; 8]) -> Result<[u8; 24], CryptorError> {
+    let mut padded = [0; 21];
+    padded[..16].copy_from_slice(key);
+
+    let mut output = [0; 24];
+    for (block, key) in output.chunks_exact_mut(8).zip(padded.chunks_exact(7)) {
+        block.copy_from_slice(&des(key.try_into().unwrap(), data)?);
+    }
+
+    Ok(output)
+}
+
+/// The NTLMv1 response to the server challenge, given the NT hash for the
+/// `NtChallengeResponse` or the LM hash for the `LmChallengeResponse`.
+pub fn v1_response(hash: &[u8; 16], server_challenge: &[u8; 8]) -> Result<[u8; 24], CryptorError> {
+    desl(hash, server_challenge)
+}
+
+/// The NTLMv1 response with extended session security, which also covers the
+/// client challenge. The `LmChallengeResponse` is then the client challenge
+/// followed by 16 zeroes.
+pub fn v1_ess_response(
+    nt_hash: &[u8; 16],
+    server_challenge: &[u8; 8],
+    client_challenge: &[u8; 8],
+) -> Result<[u8; 24], CryptorError> {
+    let mut md5 = MD5::new();
+    md5.update(server_challenge);
+    md5.update(client_challenge);
+
+    desl(nt_hash, md5.finish()[..8].try_into().unwrap())
+}
+
+/// The NTLMv1 session base key, which is the MD4 of the NT hash.
+pub fn v1_session_base_key(nt_hash: &[u8; 16]) -> [u8; 16] {
+    Hash::md4(nt_hash)
+}
+
+/// `NTOWFv2`, which is the HMAC-MD5 of the upper cased user name and the
+/// domain, keyed with the NT hash.
+pub fn nt_hash_v2(password: &str, user: &str, domain: &str) -> [u8; 16] {
+    let identity = utf16le(&(user.to_uppercase() + domain));
+    HMAC::md5(nt_hash(password), identity)
+}
+
+/// Converts a time to a Windows `FILETIME`, the 100 nanosecond intervals
+/// since 1601, as used by NTLMv2 responses.
+pub fn timestamp(time: SystemTime) -> u64 {
+    let since_1970 = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    (since_1970.as_secs() + WINDOWS_EPOCH_OFFSET) * 10_000_000
+        + since_1970.subsec_nanos() as u64 / 100
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct V2Response {
+    /// `NTProofStr` followed by the client blob.
+    pub nt_response: Vec<u8>,
+    /// The LMv2 response, which clients replace with zeroes when the server
+    /// sends a timestamp in its target info.
+    pub lm_response: [u8; 24],
+    pub session_base_key: [u8; 16],
+}
+
+impl V2Response {
+    /// `NTProofStr`, the HMAC at the start of the response.
+    pub fn nt_proof(&self) -> &[u8] {
+        &self.nt_response[..16]
+    }
+}
+
+/// Computes the NTLMv2 responses, where `target_info` is the AV pairs from
+/// the server's challenge message, including the terminating `MsvAvEOL`.
+pub fn v2_response(
+    nt_hash_v2: &[u8; 16],
+    server_challenge: &[u8; 8],
+    client_challenge: &[u8; 8],
+    timestamp: u64,
+    target_info: &[u8],
+) -> V2Response {
+    let mut blob = vec![1, 1, 0, 0, 0, 0, 0, 0];
+    blob.extend_from_slice(&timestamp.to_le_bytes());
+    blob.extend_from_slice(client_challenge);
+    blob.extend_from_slice(&[0; 4]);
+    blob.extend_from_slice(target_info);
+    blob.extend_from_slice(&[0; 4]);
+
+    let mut hmac = hmac::MD5::new(nt_hash_v2);
+    hmac.update(server_challenge);
+    hmac.update(&blob);
+    let nt_proof = hmac.finish();
+
+    let mut hmac = hmac::MD5::new(nt_hash_v2);
+    hmac.update(server_challenge);
+    hmac.update(client_challenge);
+
+    let mut lm_response = [0; 24];
+    lm_response[..16].copy_from_slice(&hmac.finish());
+    lm_response[16..].copy_from_slice(client_challenge);
+
+    V2Response {
+        nt_response: [&nt_proof[..], &blob].concat(),
+        lm_response,
+        session_base_key: HMAC::md5(nt_hash_v2, nt_proof),
+    }
+}
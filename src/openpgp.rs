@@ -0,0 +1,622 @@
+//! Decrypting password protected OpenPGP messages, as written by
+//! `gpg --symmetric`, described by RFC 4880.
+//!
+//! Messages start with a symmetric-key encrypted session key packet
+//! specifying the cipher and how the key is derived from the password (the
+//! S2K), followed by the encrypted data. That's usually a symmetrically
+//! encrypted integrity protected data packet (SEIPD), using OpenPGP's CFB
+//! mode and a SHA-1 modification detection code (MDC).
+//!
+//! ```no_run
+//! # use common_crypto::openpgp::{self, Message};
+//! let file = std::fs::read("file.gpg").unwrap();
+//!
+//! match openpgp::decrypt(file, "password").unwrap() {
+//!     Message::Literal(literal) => println!("{} bytes", literal.data.len()),
+//!     Message::Compressed { .. } => println!("decompress and read_message"),
+//!     _ => {}
+//! }
+//! ```
+//!
+//! GnuPG compresses messages by default, which Common Crypto can't undo, so
+//! the compressed packets are returned as they are.
+
+use crate::cryptor::{CryptorError, Mode, Padding};
+use crate::encoding;
+use crate::hash::{self, Hasher, SHA1};
+use crate::hmac;
+use crate::spec::{Algorithm, CipherSpec};
+use std::fmt::{self, Display};
+
+const PKESK: u8 = 1;
+const SIGNATURE: u8 = 2;
+const SKESK: u8 = 3;
+const ONE_PASS_SIGNATURE: u8 = 4;
+const COMPRESSED_DATA: u8 = 8;
+const SED: u8 = 9;
+const MARKER: u8 = 10;
+const LITERAL_DATA: u8 = 11;
+const SEIPD: u8 = 18;
+const MDC: u8 = 19;
+
+/// The bytes hashed by S2K at a time, when iterating.
+const S2K_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The most uncompressed compressed data packets read inside each other. Each
+/// only costs a few bytes, so the recursion needs a limit.
+const MAX_NESTING: usize = 8;
+
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum OpenPGPError {
+    /// The input isn't an OpenPGP message, or is malformed.
+    Format,
+    /// The message uses a cipher, hash or packet version which isn't
+    /// supported, or isn't encrypted with a password.
+    Unsupported,
+    /// The quick check of the first block failed, due to the wrong password.
+    Decryption,
+    /// The modification detection code didn't match, so the message has been
+    /// modified or corrupted.
+    Integrity,
+    /// The message has no integrity protection, so may have been modified.
+    /// It can be decrypted with [`decrypt_unprotected`].
+    Unprotected,
+    Cryptor(CryptorError),
+}
+
+impl std::error::Error for OpenPGPError {}
+
+impl Display for OpenPGPError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Format => f.write_str("input is not an OpenPGP message"),
+            Self::Unsupported => f.write_str("unsupported OpenPGP algorithm or packet"),
+            Self::Decryption => f.write_str("decryption failed, the password may be wrong"),
+            Self::Integrity => f.write_str("the message has been modified"),
+            Self::Unprotected => f.write_str("the message is not integrity protected"),
+            Self::Cryptor(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<CryptorError> for OpenPGPError {
+    fn from(e: CryptorError) -> Self {
+        Self::Cryptor(e)
+    }
+}
+
+/// The symmetric ciphers provided by Common Crypto, with their OpenPGP IDs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SymmetricAlgorithm {
+    TDES = 2,
+    CAST5 = 3,
+    Blowfish = 4,
+    AES128 = 7,
+    AES192 = 8,
+    AES256 = 9,
+}
+
+impl SymmetricAlgorithm {
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            2 => Some(Self::TDES),
+            3 => Some(Self::CAST5),
+            4 => Some(Self::Blowfish),
+            7 => Some(Self::AES128),
+            8 => Some(Self::AES192),
+            9 => Some(Self::AES256),
+            _ => None,
+        }
+    }
+
+    pub fn key_len(self) -> usize {
+        match self {
+            Self::TDES | Self::AES192 => 24,
+            Self::AES256 => 32,
+            _ => 16,
+        }
+    }
+
+    pub fn block_size(self) -> usize {
+        match self {
+            Self::AES128 | Self::AES192 | Self::AES256 => 16,
+            _ => 8,
+        }
+    }
+
+    /// Plain CFB mode, of which OpenPGP's is a variant.
+    fn cfb(self) -> CipherSpec {
+        let algorithm = match self {
+            Self::TDES => Algorithm::TDES,
+            Self::CAST5 => Algorithm::CAST,
+            Self::Blowfish => Algorithm::Blowfish,
+            _ => Algorithm::AES,
+        };
+
+        CipherSpec {
+            algorithm,
+            mode: Some(Mode::CFB),
+            padding: Padding::None,
+            key_len: self.key_len(),
+        }
+    }
+}
+
+fn hash_from_id(id: u8) -> Option<hash::Algorithm> {
+    match id {
+        1 => Some(hash::Algorithm::MD5),
+        2 => Some(hash::Algorithm::SHA1),
+        8 => Some(hash::Algorithm::SHA256),
+        9 => Some(hash::Algorithm::SHA384),
+        10 => Some(hash::Algorithm::SHA512),
+        11 => Some(hash::Algorithm::SHA224),
+        _ => None,
+    }
+}
+
+/// A string-to-key specifier, which derives a key from a password.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum S2K {
+    Simple {
+        hash: hash::Algorithm,
+    },
+    Salted {
+        hash: hash::Algorithm,
+        salt: [u8; 8],
+    },
+    /// The salt and password are hashed repeatedly until `count` bytes have
+    /// been hashed, or once if they're longer.
+    Iterated {
+        hash: hash::Algorithm,
+        salt: [u8; 8],
+        count: u32,
+    },
+}
+
+impl S2K {
+    fn read(reader: &mut &[u8]) -> Result<Self, OpenPGPError> {
+        let (&[kind, hash], rest) = split(reader).ok_or(OpenPGPError::Format)?;
+        *reader = rest;
+
+        let hash = hash_from_id(hash).ok_or(OpenPGPError::Unsupported)?;
+        let mut salt = || -> Result<[u8; 8], OpenPGPError> {
+            let (salt, rest) = split(reader).ok_or(OpenPGPError::Format)?;
+            *reader = rest;
+            Ok(*salt)
+        };
+
+        match kind {
+            0 => Ok(Self::Simple { hash }),
+            1 => Ok(Self::Salted {
+                hash,
+                salt: salt()?,
+            }),
+            3 => {
+                let salt = salt()?;
+                let (&[coded], rest) = split(reader).ok_or(OpenPGPError::Format)?;
+                *reader = rest;
+
+                Ok(Self::Iterated {
+                    hash,
+                    salt,
+                    count: (16 + (coded as u32 & 15)) << ((coded >> 4) + 6),
+                })
+            }
+            _ => Err(OpenPGPError::Unsupported),
+        }
+    }
+
+    /// Derives a key of the given length. Keys longer than the digest use
+    /// further hashes preloaded with an increasing number of zeroes.
+    pub fn derive_key(&self, password: impl AsRef<[u8]>, len: usize) -> Vec<u8> {
+        let password = password.as_ref();
+        let (hash, salt, count) = match self {
+            Self::Simple { hash } => (*hash, &[][..], 0),
+            Self::Salted { hash, salt } => (*hash, &salt[..], 0),
+            Self::Iterated { hash, salt, count } => (*hash, &salt[..], *count as usize),
+        };
+
+        let data = [salt, password].concat();
+        let count = count.max(data.len());
+        let repeated: Vec<u8> = if data.is_empty() {
+            Vec::new()
+        } else {
+            let repeats = (S2K_BUFFER_SIZE / data.len()).max(1);
+            data.iter()
+                .copied()
+                .cycle()
+                .take(data.len() * repeats)
+                .collect()
+        };
+
+        let mut key = Vec::with_capacity(len + hash.digest_len());
+
+        let mut preload = 0;
+        while key.len() < len {
+            let mut hasher = Hasher::new(hash);
+            hasher.update(vec![0; preload]);
+
+            let mut remaining = count;
+            while remaining > 0 {
+                let n = remaining.min(repeated.len());
+                hasher.update(&repeated[..n]);
+                remaining -= n;
+            }
+
+            key.extend_from_slice(&hasher.finish());
+            preload += 1;
+        }
+
+        key.truncate(len);
+        key
+    }
+}
+
+/// A literal data packet, which holds the plaintext.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Literal {
+    /// `b` for binary, `t` for text or `u` for UTF-8.
+    pub format: u8,
+    pub filename: Vec<u8>,
+    /// The modification time in seconds since the Unix epoch, or 0.
+    pub date: u32,
+    pub data: Vec<u8>,
+}
+
+/// The decrypted contents of a message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Message {
+    Literal(Literal),
+    /// Compressed packets, which once decompressed can be read with
+    /// [`read_message`]. The algorithm is 1 for raw DEFLATE, 2 for zlib and 3
+    /// for bzip2.
+    Compressed {
+        algorithm: u8,
+        data: Vec<u8>,
+    },
+}
+
+/// Decrypts a binary or ASCII armored message, requiring it to be integrity
+/// protected.
+pub fn decrypt(
+    input: impl AsRef<[u8]>,
+    password: impl AsRef<[u8]>,
+) -> Result<Message, OpenPGPError> {
+    let packets = decrypt_packets(input.as_ref(), password.as_ref(), false)?;
+    read_message(&packets)
+}
+
+/// Decrypts a message like [`decrypt`], but also accepts symmetrically
+/// encrypted data packets without integrity protection, which were written
+/// before 2003 and by `gpg --rfc2440`. These may have been modified.
+pub fn decrypt_unprotected(
+    input: impl AsRef<[u8]>,
+    password: impl AsRef<[u8]>,
+) -> Result<Message, OpenPGPError> {
+    let packets = decrypt_packets(input.as_ref(), password.as_ref(), true)?;
+    read_message(&packets)
+}
+
+/// Reads decrypted or decompressed packets, returning the first literal or
+/// compressed data packet. Signature packets are skipped.
+pub fn read_message(input: &[u8]) -> Result<Message, OpenPGPError> {
+    read_nested_message(input, 0)
+}
+
+fn read_nested_message(input: &[u8], depth: usize) -> Result<Message, OpenPGPError> {
+    let mut input = input;
+
+    while !input.is_empty() {
+        let (tag, body) = read_packet(&mut input).ok_or(OpenPGPError::Format)?;
+
+        match tag {
+            LITERAL_DATA => return read_literal(&body).map(Message::Literal),
+            COMPRESSED_DATA => {
+                let (&algorithm, data) = body.split_first().ok_or(OpenPGPError::Format)?;
+
+                // Uncompressed.
+                if algorithm == 0 {
+                    if depth == MAX_NESTING {
+                        return Err(OpenPGPError::Format);
+                    }
+
+                    return read_nested_message(data, depth + 1);
+                }
+
+                return Ok(Message::Compressed {
+                    algorithm,
+                    data: data.to_vec(),
+                });
+            }
+            ONE_PASS_SIGNATURE | SIGNATURE | MARKER => {}
+            _ => return Err(OpenPGPError::Format),
+        }
+    }
+
+    Err(OpenPGPError::Format)
+}
+
+fn read_literal(body: &[u8]) -> Result<Literal, OpenPGPError> {
+    let (&[format, name_len], rest) = split(body).ok_or(OpenPGPError::Format)?;
+    let filename = rest.get(..name_len as usize).ok_or(OpenPGPError::Format)?;
+    let (date, data) = split(&rest[name_len as usize..]).ok_or(OpenPGPError::Format)?;
+
+    Ok(Literal {
+        format,
+        filename: filename.to_vec(),
+        date: u32::from_be_bytes(*date),
+        data: data.to_vec(),
+    })
+}
+
+/// Splits off the first `N` bytes as an array.
+fn split<const N: usize>(input: &[u8]) -> Option<(&[u8; N], &[u8])> {
+    if input.len() < N {
+        return None;
+    }
+
+    let (first, rest) = input.split_at(N);
+    Some((first.try_into().unwrap(), rest))
+}
+
+/// Removes the ASCII armor, if there is any. The checksum isn't verified
+/// since the message is integrity protected.
+fn dearmor(input: &[u8]) -> Result<Vec<u8>, OpenPGPError> {
+    let text = match std::str::from_utf8(input) {
+        Ok(text) if text.trim_start().starts_with("-----BEGIN PGP MESSAGE-----") => text,
+        _ => return Ok(input.to_vec()),
+    };
+
+    let text: String = text
+        .lines()
+        .filter(|line| !line.starts_with('='))
+        .flat_map(|line| [line, "\n"])
+        .collect();
+
+    match encoding::pem_decode(&text) {
+        Some(pem) if pem.label == "PGP MESSAGE" => Ok(pem.data),
+        _ => Err(OpenPGPError::Format),
+    }
+}
+
+/// Reads a packet in either the old or new format, returning its tag and
+/// body, where partial body lengths have been joined.
+fn read_packet(input: &mut &[u8]) -> Option<(u8, Vec<u8>)> {
+    let (&header, rest) = input.split_first()?;
+    *input = rest;
+
+    if header & 0x80 == 0 {
+        return None;
+    }
+
+    // The old format encodes the size of the length in the header.
+    if header & 0x40 == 0 {
+        let tag = (header >> 2) & 0x0f;
+        let len = match header & 3 {
+            0 => read_len::<1>(input)?,
+            1 => read_len::<2>(input)?,
+            2 => read_len::<4>(input)?,
+            // Indeterminate, until the end of the input.
+            _ => input.len(),
+        };
+
+        let body = input.get(..len)?.to_vec();
+        *input = &input[len..];
+        return Some((tag, body));
+    }
+
+    let tag = header & 0x3f;
+    let mut body = Vec::new();
+
+    loop {
+        let (&first, rest) = input.split_first()?;
+        *input = rest;
+
+        let (len, partial) = match first {
+            0..=191 => (first as usize, false),
+            192..=223 => {
+                let (&second, rest) = input.split_first()?;
+                *input = rest;
+                (
+                    (((first as usize) - 192) << 8) + second as usize + 192,
+                    false,
+                )
+            }
+            224..=254 => (1 << (first & 0x1f), true),
+            255 => (read_len::<4>(input)?, false),
+        };
+
+        body.extend_from_slice(input.get(..len)?);
+        *input = &input[len..];
+
+        if !partial {
+            return Some((tag, body));
+        }
+    }
+}
+
+fn read_len<const N: usize>(input: &mut &[u8]) -> Option<usize> {
+    let (bytes, rest) = split::<N>(input)?;
+    *input = rest;
+    Some(bytes.iter().fold(0, |n, b| n << 8 | *b as usize))
+}
+
+/// A symmetric-key encrypted session key packet.
+struct SessionKeyPacket {
+    algorithm: SymmetricAlgorithm,
+    s2k: S2K,
+    encrypted_key: Vec<u8>,
+}
+
+impl SessionKeyPacket {
+    fn read(body: &[u8]) -> Result<Self, OpenPGPError> {
+        let (&[version, algorithm], mut rest) = split(body).ok_or(OpenPGPError::Format)?;
+
+        // Versions 5 and 6 use AEAD.
+        if version != 4 {
+            return Err(OpenPGPError::Unsupported);
+        }
+
+        let algorithm = SymmetricAlgorithm::from_id(algorithm).ok_or(OpenPGPError::Unsupported)?;
+        let s2k = S2K::read(&mut rest)?;
+
+        Ok(Self {
+            algorithm,
+            s2k,
+            encrypted_key: rest.to_vec(),
+        })
+    }
+
+    /// Derives the key from the password, which is used directly unless a
+    /// session key was encrypted with it.
+    fn session_key(&self, password: &[u8]) -> Result<(SymmetricAlgorithm, Vec<u8>), OpenPGPError> {
+        let key = self.s2k.derive_key(password, self.algorithm.key_len());
+
+        if self.encrypted_key.is_empty() {
+            return Ok((self.algorithm, key));
+        }
+
+        let iv = vec![0; self.algorithm.block_size()];
+        let decrypted = self
+            .algorithm
+            .cfb()
+            .decrypt(&key, Some(&iv), &self.encrypted_key)?;
+
+        // A wrong password results in a garbage algorithm or key length.
+        let (&algorithm, session_key) = decrypted.split_first().ok_or(OpenPGPError::Format)?;
+        match SymmetricAlgorithm::from_id(algorithm) {
+            Some(algorithm) if algorithm.key_len() == session_key.len() => {
+                Ok((algorithm, session_key.to_vec()))
+            }
+            _ => Err(OpenPGPError::Decryption),
+        }
+    }
+}
+
+fn decrypt_packets(
+    input: &[u8],
+    password: &[u8],
+    allow_unprotected: bool,
+) -> Result<Vec<u8>, OpenPGPError> {
+    let input = dearmor(input)?;
+    let mut input = &input[..];
+    let mut session_key_packets = Vec::new();
+
+    while !input.is_empty() {
+        let (tag, body) = read_packet(&mut input).ok_or(OpenPGPError::Format)?;
+
+        match tag {
+            SKESK => session_key_packets.push(SessionKeyPacket::read(&body)?),
+            SEIPD | SED => {
+                if tag == SED && !allow_unprotected {
+                    return Err(OpenPGPError::Unprotected);
+                }
+
+                // Without any session key packets, the message is encrypted
+                // to a public key.
+                if session_key_packets.is_empty() {
+                    return Err(OpenPGPError::Unsupported);
+                }
+
+                // Any of the session key packets may match the password, so
+                // the error from the last is returned.
+                let mut result = Err(OpenPGPError::Unsupported);
+                for packet in &session_key_packets {
+                    result = packet.session_key(password).and_then(|(algorithm, key)| {
+                        if tag == SEIPD {
+                            decrypt_seipd(algorithm, &key, &body)
+                        } else {
+                            decrypt_sed(algorithm, &key, &body)
+                        }
+                    });
+
+                    if result.is_ok() {
+                        break;
+                    }
+                }
+
+                return result;
+            }
+            // Public key encrypted session keys are skipped, in case the
+            // message can also be decrypted with a password.
+            PKESK | MARKER => {}
+            _ => return Err(OpenPGPError::Format),
+        }
+    }
+
+    Err(OpenPGPError::Format)
+}
+
+/// Checks that the last two bytes of the random prefix are repeated, which
+/// detects a wrong key before decrypting everything.
+fn quick_check(prefix: &[u8], block_size: usize) -> Result<(), OpenPGPError> {
+    if prefix.len() < block_size + 2
+        || prefix[block_size - 2..block_size] != prefix[block_size..block_size + 2]
+    {
+        return Err(OpenPGPError::Decryption);
+    }
+
+    Ok(())
+}
+
+/// Decrypts a symmetrically encrypted integrity protected data packet, which
+/// uses CFB mode with a zero initialization vector over a random prefix, the
+/// packets and the MDC packet, which contains the SHA-1 of everything before
+/// its hash.
+fn decrypt_seipd(
+    algorithm: SymmetricAlgorithm,
+    key: &[u8],
+    body: &[u8],
+) -> Result<Vec<u8>, OpenPGPError> {
+    let (&version, ciphertext) = body.split_first().ok_or(OpenPGPError::Format)?;
+    if version != 1 {
+        return Err(OpenPGPError::Unsupported);
+    }
+
+    let block_size = algorithm.block_size();
+    if ciphertext.len() < block_size + 2 + 22 {
+        return Err(OpenPGPError::Format);
+    }
+
+    let iv = vec![0; block_size];
+    let plaintext = algorithm.cfb().decrypt(key, Some(&iv), ciphertext)?;
+    quick_check(&plaintext, block_size)?;
+
+    let (hashed, mdc) = plaintext.split_at(plaintext.len() - 20);
+    let mut sha1 = SHA1::new();
+    sha1.update(hashed);
+
+    if hashed[hashed.len() - 2..] != [0xc0 | MDC, 20]
+        || !hmac::constant_time_eq(&sha1.finish(), mdc)
+    {
+        return Err(OpenPGPError::Integrity);
+    }
+
+    Ok(hashed[block_size + 2..hashed.len() - 2].to_vec())
+}
+
+/// Decrypts a symmetrically encrypted data packet, which uses OpenPGP's CFB
+/// variant: the random prefix is encrypted with a zero initialization vector,
+/// then the cipher is resynchronized to use the last block of ciphertext as
+/// the initialization vector for the packets.
+fn decrypt_sed(
+    algorithm: SymmetricAlgorithm,
+    key: &[u8],
+    body: &[u8],
+) -> Result<Vec<u8>, OpenPGPError> {
+    let block_size = algorithm.block_size();
+    if body.len() < block_size + 2 {
+        return Err(OpenPGPError::Format);
+    }
+
+    let (prefix, ciphertext) = body.split_at(block_size + 2);
+    let cfb = algorithm.cfb();
+
+    let iv = vec![0; block_size];
+    quick_check(&cfb.decrypt(key, Some(&iv), prefix)?, block_size)?;
+
+    Ok(cfb.decrypt(key, Some(&prefix[2..]), ciphertext)?)
+}
@@ -0,0 +1,316 @@
+//! The salted file format written by `openssl enc`.
+//!
+//! Files start with the magic `Salted__` and an 8-byte salt, from which the
+//! key and initialization vector are derived along with the password, and
+//! are followed by the ciphertext.
+//!
+//! ```
+//! # use common_crypto::openssl::{Enc, KeyDerivation};
+//! // openssl enc -aes-256-cbc -pbkdf2
+//! let enc = Enc::new("aes-256-cbc".parse().unwrap(), KeyDerivation::pbkdf2());
+//!
+//! let encrypted = enc.encrypt(b"password", b"The quick brown fox").unwrap();
+//! assert_eq!(&encrypted[..8], b"Salted__");
+//! assert_eq!(enc.decrypt(b"password", encrypted).unwrap(), b"The quick brown fox");
+//! ```
+//!
+//! Traditional encrypted PEM keys, which derive their key in the same way, are
+//! handled by [`pem`].
+
+use crate::cryptor::{Cryptor, CryptorError};
+use crate::hash::{self, Hasher};
+use crate::kdf::{self, PRF};
+use crate::random;
+use crate::spec::CipherSpec;
+use std::fmt::{self, Display};
+use std::io::{self, Read, Write};
+
+pub mod pem;
+
+const MAGIC: &[u8; 8] = b"Salted__";
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// How the key and initialization vector are derived from the password and
+/// salt, matching the `-md`, `-pbkdf2` and `-iter` options.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyDerivation {
+    /// OpenSSL's `EVP_BytesToKey` with a single iteration, which was the only
+    /// option before OpenSSL 1.1.1. The digest defaults to SHA-256 since
+    /// OpenSSL 1.1.0 and was MD5 before.
+    BytesToKey(hash::Algorithm),
+    PBKDF2 {
+        prf: PRF,
+        iterations: u32,
+    },
+}
+
+impl KeyDerivation {
+    /// PBKDF2 with OpenSSL's defaults of HMAC-SHA256 and 10000 iterations.
+    pub fn pbkdf2() -> Self {
+        Self::PBKDF2 {
+            prf: PRF::SHA256,
+            iterations: 10_000,
+        }
+    }
+
+    /// Derives `len` bytes, to be split into the key and initialization
+    /// vector.
+    fn derive(self, password: &[u8], salt: &[u8], len: usize) -> Result<Vec<u8>, CryptorError> {
+        match self {
+            Self::BytesToKey(algorithm) => Ok(bytes_to_key(algorithm, password, salt, len)),
+            Self::PBKDF2 { prf, iterations } => kdf::pbkdf2(password, salt, prf, iterations, len),
+        }
+    }
+}
+
+/// `EVP_BytesToKey` with a single iteration, where each block is the digest
+/// of the previous block, the password and the salt.
+pub(crate) fn bytes_to_key(
+    algorithm: hash::Algorithm,
+    password: &[u8],
+    salt: &[u8],
+    len: usize,
+) -> Vec<u8> {
+    let mut derived = Vec::with_capacity(len + algorithm.digest_len());
+    let mut block: Vec<u8> = Vec::new();
+
+    while derived.len() < len {
+        let mut hasher = Hasher::new(algorithm);
+        hasher.update(&block);
+        hasher.update(password);
+        hasher.update(salt);
+        block = hasher.finish();
+        derived.extend_from_slice(&block);
+    }
+
+    derived.truncate(len);
+    derived
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum EncError {
+    /// The input doesn't start with `Salted__` and a salt.
+    Format,
+    Cryptor(CryptorError),
+    Io(io::Error),
+}
+
+impl std::error::Error for EncError {}
+
+impl Display for EncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Format => f.write_str("input is not in the openssl salted format"),
+            Self::Cryptor(e) => e.fmt(f),
+            Self::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<CryptorError> for EncError {
+    fn from(e: CryptorError) -> Self {
+        Self::Cryptor(e)
+    }
+}
+
+/// Unwraps the [`CryptorError`]s returned by the reader and writer.
+impl From<io::Error> for EncError {
+    fn from(e: io::Error) -> Self {
+        match e.downcast::<CryptorError>() {
+            Ok(e) => Self::Cryptor(e),
+            Err(e) => Self::Io(e),
+        }
+    }
+}
+
+/// The options of an `openssl enc` invocation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Enc {
+    cipher: CipherSpec,
+    key_derivation: KeyDerivation,
+}
+
+impl Enc {
+    pub fn new(cipher: CipherSpec, key_derivation: KeyDerivation) -> Self {
+        Self {
+            cipher,
+            key_derivation,
+        }
+    }
+
+    fn cryptor(&self, password: &[u8], salt: &[u8], encrypt: bool) -> Result<Cryptor, EncError> {
+        let key_len = self.cipher.key_len;
+        let derived = self
+            .key_derivation
+            .derive(password, salt, key_len + self.cipher.iv_len())?;
+        let (key, iv) = derived.split_at(key_len);
+        let iv = Some(iv).filter(|iv| !iv.is_empty());
+
+        let cryptor = if encrypt {
+            self.cipher.new_encryptor(key, iv)?
+        } else {
+            self.cipher.new_decryptor(key, iv)?
+        };
+
+        Ok(cryptor)
+    }
+
+    /// Encrypts with a random salt.
+    pub fn encrypt(
+        &self,
+        password: impl AsRef<[u8]>,
+        input: impl AsRef<[u8]>,
+    ) -> Result<Vec<u8>, EncError> {
+        self.encrypt_with_salt(password, random::bytes()?, input)
+    }
+
+    /// Encrypts with the given salt, which must be random unless the output
+    /// needs to be reproducible.
+    pub fn encrypt_with_salt(
+        &self,
+        password: impl AsRef<[u8]>,
+        salt: [u8; 8],
+        input: impl AsRef<[u8]>,
+    ) -> Result<Vec<u8>, EncError> {
+        let mut writer = EncWriter::with_salt(self, password, salt, Vec::new())?;
+        writer.write_all(input.as_ref())?;
+        Ok(writer.finish()?)
+    }
+
+    pub fn decrypt(
+        &self,
+        password: impl AsRef<[u8]>,
+        input: impl AsRef<[u8]>,
+    ) -> Result<Vec<u8>, EncError> {
+        let mut reader = EncReader::new(self, password, input.as_ref())?;
+        let mut output = Vec::new();
+
+        reader.read_to_end(&mut output)?;
+
+        Ok(output)
+    }
+}
+
+/// Encrypts everything written to it, writing the header first.
+///
+/// [`EncWriter::finish`] must be called to write the final block.
+#[derive(Debug)]
+pub struct EncWriter<W: Write> {
+    inner: W,
+    cryptor: Cryptor,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncWriter<W> {
+    /// Writes the header with a random salt.
+    pub fn new(enc: &Enc, password: impl AsRef<[u8]>, inner: W) -> Result<Self, EncError> {
+        Self::with_salt(enc, password, random::bytes()?, inner)
+    }
+
+    pub fn with_salt(
+        enc: &Enc,
+        password: impl AsRef<[u8]>,
+        salt: [u8; 8],
+        mut inner: W,
+    ) -> Result<Self, EncError> {
+        let cryptor = enc.cryptor(password.as_ref(), &salt, true)?;
+
+        inner.write_all(MAGIC)?;
+        inner.write_all(&salt)?;
+
+        Ok(Self {
+            inner,
+            cryptor,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Writes any remaining data, returning the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.cryptor
+            .finish(&mut self.buffer)
+            .map_err(io::Error::other)?;
+        self.inner.write_all(&self.buffer)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.cryptor
+            .update(buf, &mut self.buffer)
+            .map_err(io::Error::other)?;
+        self.inner.write_all(&self.buffer)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts as it's read, after reading the header.
+///
+/// Errors from decrypting, such as bad padding due to the wrong password, are
+/// returned as [`io::Error`]s wrapping a [`CryptorError`].
+#[derive(Debug)]
+pub struct EncReader<R: Read> {
+    inner: R,
+    /// `None` once the final block has been decrypted.
+    cryptor: Option<Cryptor>,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> EncReader<R> {
+    pub fn new(enc: &Enc, password: impl AsRef<[u8]>, mut inner: R) -> Result<Self, EncError> {
+        let mut header = [0u8; 16];
+        inner.read_exact(&mut header).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => EncError::Format,
+            _ => EncError::Io(e),
+        })?;
+
+        let (magic, salt) = header.split_at(8);
+        if magic != MAGIC {
+            return Err(EncError::Format);
+        }
+
+        Ok(Self {
+            inner,
+            cryptor: Some(enc.cryptor(password.as_ref(), salt, false)?),
+            input: vec![0u8; BUFFER_SIZE],
+            output: Vec::new(),
+            position: 0,
+        })
+    }
+}
+
+impl<R: Read> Read for EncReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.output.len() {
+            let Some(cryptor) = &self.cryptor else {
+                return Ok(0);
+            };
+
+            let read = self.inner.read(&mut self.input)?;
+            let result = if read == 0 {
+                self.cryptor.take().unwrap().finish(&mut self.output)
+            } else {
+                cryptor.update(&self.input[..read], &mut self.output)
+            };
+
+            result.map_err(io::Error::other)?;
+            self.position = 0;
+        }
+
+        let len = buf.len().min(self.output.len() - self.position);
+        buf[..len].copy_from_slice(&self.output[self.position..self.position + len]);
+        self.position += len;
+
+        Ok(len)
+    }
+}
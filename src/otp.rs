@@ -0,0 +1,417 @@
+//! One-time passwords, HOTP from RFC 4226 and TOTP from RFC 6238, and the
+//! `otpauth://` key URIs read by authenticator apps.
+//!
+//! ```
+//! # use common_crypto::otp::TOTP;
+//! # use std::time::SystemTime;
+//! let totp = TOTP::new(b"12345678901234567890");
+//! let code = totp.generate(SystemTime::now()).unwrap();
+//! assert!(totp.verify(&code, SystemTime::now()).is_some());
+//! ```
+
+use crate::encoding::{base32_decode, base32_encode};
+use crate::hmac::{self, HMAC};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum OTPError {
+    /// The URI is malformed, or its secret or a parameter is invalid.
+    Format,
+    /// The URI names a type or algorithm which isn't supported.
+    Unsupported,
+}
+
+impl std::error::Error for OTPError {}
+
+impl Display for OTPError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Format => f.write_str("malformed key URI"),
+            Self::Unsupported => f.write_str("unsupported type or algorithm"),
+        }
+    }
+}
+
+/// The hash used by HMAC.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Algorithm {
+    #[default]
+    SHA1,
+    SHA256,
+    SHA512,
+}
+
+impl Algorithm {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "SHA1" => Some(Self::SHA1),
+            "SHA256" => Some(Self::SHA256),
+            "SHA512" => Some(Self::SHA512),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::SHA1 => "SHA1",
+            Self::SHA256 => "SHA256",
+            Self::SHA512 => "SHA512",
+        }
+    }
+}
+
+/// HMAC-based one-time passwords, six digits with SHA-1 unless configured
+/// otherwise.
+#[derive(Clone, PartialEq, Eq)]
+pub struct HOTP {
+    secret: Vec<u8>,
+    algorithm: Algorithm,
+    digits: u32,
+}
+
+impl fmt::Debug for HOTP {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HOTP")
+            .field("algorithm", &self.algorithm)
+            .field("digits", &self.digits)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HOTP {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            secret: secret.into(),
+            algorithm: Algorithm::SHA1,
+            digits: 6,
+        }
+    }
+
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// # Panics
+    ///
+    /// If the number of digits isn't between 6 and 10.
+    pub fn digits(mut self, digits: u32) -> Self {
+        assert!((6..=10).contains(&digits), "invalid number of digits");
+        self.digits = digits;
+        self
+    }
+
+    pub fn secret(&self) -> &[u8] {
+        &self.secret
+    }
+
+    pub fn generate(&self, counter: u64) -> String {
+        let counter = counter.to_be_bytes();
+        let hash = match self.algorithm {
+            Algorithm::SHA1 => HMAC::sha1(&self.secret, counter).to_vec(),
+            Algorithm::SHA256 => HMAC::sha256(&self.secret, counter).to_vec(),
+            Algorithm::SHA512 => HMAC::sha512(&self.secret, counter).to_vec(),
+        };
+
+        // Dynamic truncation, reading 31 bits from an offset chosen by the
+        // last nibble.
+        let offset = (hash[hash.len() - 1] & 0xf) as usize;
+        let value = u32::from_be_bytes(hash[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+
+        format!(
+            "{:0width$}",
+            value as u64 % 10u64.pow(self.digits),
+            width = self.digits as usize
+        )
+    }
+
+    /// Checks the code against the counter and up to `look_ahead` after it,
+    /// returning the matching counter. The next counter expected is the one
+    /// after it.
+    ///
+    /// Each counter checked costs an HMAC, so the look-ahead should be small,
+    /// and attempts should be rate limited as it widens the window for
+    /// guessing.
+    pub fn verify(&self, code: &str, counter: u64, look_ahead: u64) -> Option<u64> {
+        let last = counter.saturating_add(look_ahead);
+        (counter..=last).find(|counter| self.matches(code, *counter))
+    }
+
+    fn matches(&self, code: &str, counter: u64) -> bool {
+        hmac::constant_time_eq(self.generate(counter).as_bytes(), code.as_bytes())
+    }
+}
+
+/// Time-based one-time passwords, using 30 second time steps and accepting
+/// codes from one step either side of the current one unless configured
+/// otherwise.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TOTP {
+    hotp: HOTP,
+    period: u64,
+    skew: u64,
+}
+
+impl TOTP {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            hotp: HOTP::new(secret),
+            period: 30,
+            skew: 1,
+        }
+    }
+
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.hotp = self.hotp.algorithm(algorithm);
+        self
+    }
+
+    /// # Panics
+    ///
+    /// If the number of digits isn't between 6 and 10.
+    pub fn digits(mut self, digits: u32) -> Self {
+        self.hotp = self.hotp.digits(digits);
+        self
+    }
+
+    /// Sets the time step in seconds.
+    ///
+    /// # Panics
+    ///
+    /// If the period is zero.
+    pub fn period(mut self, period: u64) -> Self {
+        assert!(period > 0, "the period must not be zero");
+        self.period = period;
+        self
+    }
+
+    /// Sets the number of time steps either side of the current one which
+    /// are accepted, to allow for clock drift and delays in entering codes.
+    ///
+    /// Verifying costs an HMAC per time step checked, `2 * skew + 1`, so this
+    /// should be small.
+    pub fn skew(mut self, skew: u64) -> Self {
+        self.skew = skew;
+        self
+    }
+
+    pub fn secret(&self) -> &[u8] {
+        self.hotp.secret()
+    }
+
+    /// The time step, the number of periods since the Unix epoch, or `None`
+    /// if the time is before it, which usually means the clock is wrong.
+    pub fn time_step(&self, time: SystemTime) -> Option<u64> {
+        let seconds = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        Some(seconds / self.period)
+    }
+
+    /// Generates the code for the time, or `None` if it's before the Unix
+    /// epoch.
+    pub fn generate(&self, time: SystemTime) -> Option<String> {
+        self.time_step(time)
+            .map(|time_step| self.hotp.generate(time_step))
+    }
+
+    /// Checks the code against the time steps within the skew of the given
+    /// time, returning the matching time step. Codes should only be accepted
+    /// once, by rejecting time steps at or before the last one used. Nothing
+    /// is accepted if the time is before the Unix epoch.
+    pub fn verify(&self, code: &str, time: SystemTime) -> Option<u64> {
+        let time_step = self.time_step(time)?;
+        let first = time_step.saturating_sub(self.skew);
+        let last = time_step.saturating_add(self.skew);
+
+        (first..=last).find(|time_step| self.hotp.matches(code, *time_step))
+    }
+}
+
+/// The generator described by a key URI.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Generator {
+    HOTP { hotp: HOTP, counter: u64 },
+    TOTP(TOTP),
+}
+
+/// An `otpauth://` URI, as encoded in the QR codes scanned by authenticator
+/// apps:
+///
+/// ```text
+/// otpauth://totp/Example:alice@example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example
+/// ```
+///
+/// The skew isn't part of the URI, so TOTP generators have the default.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyURI {
+    pub issuer: Option<String>,
+    pub account: String,
+    pub generator: Generator,
+}
+
+impl FromStr for KeyURI {
+    type Err = OTPError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .get(.."otpauth://".len())
+            .filter(|scheme| scheme.eq_ignore_ascii_case("otpauth://"))
+            .map(|scheme| &s[scheme.len()..])
+            .ok_or(OTPError::Format)?;
+
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let (kind, label) = path.split_once('/').ok_or(OTPError::Format)?;
+
+        let mut parameters: Vec<(&str, String)> = Vec::new();
+        for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+            let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            parameters.push((name, percent_decode(value).ok_or(OTPError::Format)?));
+        }
+
+        let parameter = |name: &str| {
+            parameters
+                .iter()
+                .find(|(parameter, _)| *parameter == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let number = |name: &str| -> Result<Option<u64>, OTPError> {
+            parameter(name)
+                .map(|value| value.parse().map_err(|_| OTPError::Format))
+                .transpose()
+        };
+
+        // The label is split before decoding, so that colons in the issuer
+        // or account survive as `%3A`. An encoded separator is only
+        // recognised when the prefix matches the issuer parameter.
+        let decode = |s: &str| percent_decode(s).ok_or(OTPError::Format);
+        let (prefix, account) = match label.split_once(':') {
+            Some((issuer, account)) => (Some(decode(issuer)?), decode(account)?),
+            None => {
+                let label = decode(label)?;
+                match label.split_once(':') {
+                    Some((issuer, account)) if parameter("issuer") == Some(issuer) => {
+                        (Some(issuer.to_string()), account.to_string())
+                    }
+                    _ => (None, label),
+                }
+            }
+        };
+
+        let secret = parameter("secret")
+            .and_then(base32_decode)
+            .filter(|secret| !secret.is_empty())
+            .ok_or(OTPError::Format)?;
+
+        let algorithm = match parameter("algorithm") {
+            Some(name) => Algorithm::from_name(name).ok_or(OTPError::Unsupported)?,
+            None => Algorithm::SHA1,
+        };
+
+        let digits = number("digits")?.unwrap_or(6);
+        if !(6..=10).contains(&digits) {
+            return Err(OTPError::Format);
+        }
+
+        let hotp = HOTP::new(secret).algorithm(algorithm).digits(digits as u32);
+
+        let generator = match kind.to_ascii_lowercase().as_str() {
+            "hotp" => Generator::HOTP {
+                hotp,
+                counter: number("counter")?.ok_or(OTPError::Format)?,
+            },
+            "totp" => {
+                let period = number("period")?.unwrap_or(30);
+                if period == 0 {
+                    return Err(OTPError::Format);
+                }
+
+                Generator::TOTP(TOTP {
+                    hotp,
+                    period,
+                    skew: 1,
+                })
+            }
+            _ => return Err(OTPError::Unsupported),
+        };
+
+        Ok(Self {
+            issuer: parameter("issuer").map(str::to_string).or(prefix),
+            account: account.trim_start().to_string(),
+            generator,
+        })
+    }
+}
+
+impl Display for KeyURI {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, hotp) = match &self.generator {
+            Generator::HOTP { hotp, .. } => ("hotp", hotp),
+            Generator::TOTP(totp) => ("totp", &totp.hotp),
+        };
+
+        f.write_str("otpauth://")?;
+        f.write_str(kind)?;
+        f.write_str("/")?;
+
+        if let Some(issuer) = &self.issuer {
+            write!(f, "{}:", percent_encode(issuer))?;
+        }
+
+        write!(
+            f,
+            "{}?secret={}",
+            percent_encode(&self.account),
+            base32_encode(&hotp.secret).trim_end_matches('=')
+        )?;
+
+        if let Some(issuer) = &self.issuer {
+            write!(f, "&issuer={}", percent_encode(issuer))?;
+        }
+
+        write!(
+            f,
+            "&algorithm={}&digits={}",
+            hotp.algorithm.name(),
+            hotp.digits
+        )?;
+
+        match &self.generator {
+            Generator::HOTP { counter, .. } => write!(f, "&counter={counter}"),
+            Generator::TOTP(totp) => write!(f, "&period={}", totp.period),
+        }
+    }
+}
+
+/// Encodes everything but the unreserved characters from RFC 3986.
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    encoded
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let mut digit = || (iter.next()? as char).to_digit(16);
+            bytes.push((digit()? << 4 | digit()?) as u8);
+        } else {
+            bytes.push(byte);
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
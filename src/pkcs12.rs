@@ -0,0 +1,560 @@
+//! PKCS #12 files, also known as `.p12` or `.pfx` files, as described by
+//! RFC 7292.
+//!
+//! Files using the password integrity and privacy modes can be decrypted,
+//! whether they use the legacy algorithms defined by PKCS #12 itself, as
+//! written by OpenSSL's `pkcs12 -export -legacy` and most other tools until
+//! recently, or the PKCS #5 schemes supported by [`crate::pkcs8`].
+//!
+//! ```no_run
+//! # use common_crypto::pkcs12::{self, Bag};
+//! let file = std::fs::read("identity.p12").unwrap();
+//!
+//! for bag in pkcs12::decrypt(file, "password").unwrap() {
+//!     match bag {
+//!         Bag::Key(der) => println!("private key of {} bytes", der.len()),
+//!         Bag::Certificate(der) => println!("certificate of {} bytes", der.len()),
+//!         _ => {}
+//!     }
+//! }
+//! ```
+
+use crate::cryptor::{Config, Cryptor, CryptorError, Padding};
+use crate::der::{self, Reader};
+use crate::hash::{self, Hasher};
+use crate::hmac::{self, HMAC};
+use crate::pkcs8::{EncryptedPrivateKeyInfo, EncryptionAlgorithm, PKCS8Error};
+use crate::random;
+use std::fmt::{self, Display};
+
+/// The prefix of the OIDs of the algorithms in [`PBEAlgorithm`],
+/// 1.2.840.113549.1.12.1.
+const PBE_OID_PREFIX: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x01];
+
+/// 1.2.840.113549.1.7.1
+const DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01];
+/// 1.2.840.113549.1.7.6
+const ENCRYPTED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x06];
+
+/// 1.2.840.113549.1.12.10.1.1 to 6.
+const KEY_BAG: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x01,
+];
+const SHROUDED_KEY_BAG: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x02,
+];
+const CERT_BAG: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x03,
+];
+const SAFE_CONTENTS_BAG: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x06,
+];
+
+/// 1.2.840.113549.1.9.22.1
+const X509_CERTIFICATE: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x16, 0x01];
+
+/// The digests which may be used for the MAC.
+const DIGESTS: [(&[u8], hash::Algorithm); 6] = [
+    (
+        &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x05],
+        hash::Algorithm::MD5,
+    ),
+    (&[0x2b, 0x0e, 0x03, 0x02, 0x1a], hash::Algorithm::SHA1),
+    (
+        &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x04],
+        hash::Algorithm::SHA224,
+    ),
+    (
+        &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01],
+        hash::Algorithm::SHA256,
+    ),
+    (
+        &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02],
+        hash::Algorithm::SHA384,
+    ),
+    (
+        &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03],
+        hash::Algorithm::SHA512,
+    ),
+];
+
+/// What the key derivation function's output is used for, which is mixed
+/// into the derivation so that each is independent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Purpose {
+    Key = 1,
+    IV = 2,
+    MAC = 3,
+}
+
+/// The key derivation function from appendix B of RFC 7292.
+///
+/// The password is encoded as a null terminated `BMPString`, that is UTF-16
+/// big endian. The password based encryption algorithms always use SHA-1,
+/// while the MAC may use other hashes.
+pub fn derive_key(
+    algorithm: hash::Algorithm,
+    password: &str,
+    salt: &[u8],
+    purpose: Purpose,
+    iterations: u32,
+    len: usize,
+) -> Vec<u8> {
+    let v = block_len(algorithm);
+    let password: Vec<u8> = password
+        .encode_utf16()
+        .chain([0])
+        .flat_map(u16::to_be_bytes)
+        .collect();
+
+    // The salt and password are each repeated to a multiple of the block
+    // length, with empty inputs staying empty.
+    let repeat = |input: &[u8]| -> Vec<u8> {
+        let len = input.len().div_ceil(v) * v;
+        input.iter().copied().cycle().take(len).collect()
+    };
+    let mut i = [repeat(salt), repeat(&password)].concat();
+    let d = vec![purpose as u8; v];
+
+    let mut derived = Vec::with_capacity(len + algorithm.digest_len());
+
+    while derived.len() < len {
+        let mut hasher = Hasher::new(algorithm);
+        hasher.update(&d);
+        hasher.update(&i);
+        let mut a = hasher.finish();
+
+        for _ in 1..iterations {
+            let mut hasher = Hasher::new(algorithm);
+            hasher.update(&a);
+            a = hasher.finish();
+        }
+
+        derived.extend_from_slice(&a);
+
+        // Each block of I is replaced by (I + B + 1) mod 2^(8v), where B is A
+        // repeated to the block length.
+        let b: Vec<u8> = a.iter().copied().cycle().take(v).collect();
+        for block in i.chunks_mut(v) {
+            let mut carry = 1u16;
+            for (x, y) in block.iter_mut().zip(&b).rev() {
+                let sum = *x as u16 + *y as u16 + carry;
+                *x = sum as u8;
+                carry = sum >> 8;
+            }
+        }
+    }
+
+    derived.truncate(len);
+    derived
+}
+
+/// The input block size of the hash.
+fn block_len(algorithm: hash::Algorithm) -> usize {
+    match algorithm {
+        hash::Algorithm::MD2 => 16,
+        hash::Algorithm::SHA384 | hash::Algorithm::SHA512 => 128,
+        _ => 64,
+    }
+}
+
+/// The password based encryption algorithms defined by PKCS #12, all of which
+/// use SHA-1 to derive the key and initialization vector.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PBEAlgorithm {
+    SHA1And128BitRC4 = 1,
+    SHA1And40BitRC4 = 2,
+    SHA1And3KeyTDES = 3,
+    SHA1And2KeyTDES = 4,
+    SHA1And128BitRC2 = 5,
+    SHA1And40BitRC2 = 6,
+}
+
+impl PBEAlgorithm {
+    pub(crate) fn from_oid(oid: &[u8]) -> Option<Self> {
+        let number = oid.strip_prefix(PBE_OID_PREFIX)?;
+
+        match number {
+            [1] => Some(Self::SHA1And128BitRC4),
+            [2] => Some(Self::SHA1And40BitRC4),
+            [3] => Some(Self::SHA1And3KeyTDES),
+            [4] => Some(Self::SHA1And2KeyTDES),
+            [5] => Some(Self::SHA1And128BitRC2),
+            [6] => Some(Self::SHA1And40BitRC2),
+            _ => None,
+        }
+    }
+
+    fn key_len(self) -> usize {
+        match self {
+            Self::SHA1And40BitRC4 | Self::SHA1And40BitRC2 => 5,
+            Self::SHA1And3KeyTDES => 24,
+            _ => 16,
+        }
+    }
+
+    fn is_stream_cipher(self) -> bool {
+        matches!(self, Self::SHA1And128BitRC4 | Self::SHA1And40BitRC4)
+    }
+}
+
+/// The parameters of password based encryption.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PBE {
+    pub algorithm: PBEAlgorithm,
+    pub salt: Vec<u8>,
+    pub iterations: u32,
+}
+
+impl PBE {
+    /// Uses a random 8-byte salt and 2048 iterations, as OpenSSL does.
+    pub fn new(algorithm: PBEAlgorithm) -> Result<Self, CryptorError> {
+        Ok(Self {
+            algorithm,
+            salt: random::bytes::<8>()?.to_vec(),
+            iterations: 2048,
+        })
+    }
+
+    /// Reads the `pkcs-12PbeParams` following the algorithm's OID.
+    pub(crate) fn from_parameters(
+        algorithm: PBEAlgorithm,
+        mut parameters: Reader<'_>,
+    ) -> Option<Self> {
+        let mut sequence = parameters.sequence()?;
+        let salt = sequence.octet_string()?.to_vec();
+        let iterations = sequence.u32()?;
+        sequence.finish()?;
+        parameters.finish()?;
+
+        (iterations > 0).then_some(Self {
+            algorithm,
+            salt,
+            iterations,
+        })
+    }
+
+    pub fn encrypt(&self, password: &str, data: impl AsRef<[u8]>) -> Result<Vec<u8>, CryptorError> {
+        self.crypt(password, data.as_ref(), true)
+    }
+
+    /// Decrypting with the wrong password usually fails with
+    /// [`CryptorError::Decode`] due to invalid padding, but may succeed with
+    /// garbage output, and always does for RC4.
+    pub fn decrypt(&self, password: &str, data: impl AsRef<[u8]>) -> Result<Vec<u8>, CryptorError> {
+        self.crypt(password, data.as_ref(), false)
+    }
+
+    fn crypt(&self, password: &str, data: &[u8], encrypt: bool) -> Result<Vec<u8>, CryptorError> {
+        let derive = |purpose, len| {
+            derive_key(
+                hash::Algorithm::SHA1,
+                password,
+                &self.salt,
+                purpose,
+                self.iterations,
+                len,
+            )
+        };
+
+        let mut key = derive(Purpose::Key, self.algorithm.key_len());
+        let iv: [u8; 8] = if self.algorithm.is_stream_cipher() {
+            [0; 8]
+        } else {
+            derive(Purpose::IV, 8).try_into().unwrap()
+        };
+
+        let config = match self.algorithm {
+            PBEAlgorithm::SHA1And128BitRC4 | PBEAlgorithm::SHA1And40BitRC4 => Config::rc4(&key),
+            PBEAlgorithm::SHA1And3KeyTDES | PBEAlgorithm::SHA1And2KeyTDES => {
+                // Two key triple DES reuses the first key as the third.
+                if key.len() == 16 {
+                    key.extend_from_within(..8);
+                }
+                Config::tdes(key[..].try_into().unwrap()).cbc(&iv, Padding::PKCS7)
+            }
+            PBEAlgorithm::SHA1And128BitRC2 | PBEAlgorithm::SHA1And40BitRC2 => {
+                Config::rc2(&key).cbc(&iv, Padding::PKCS7)
+            }
+        };
+
+        if encrypt {
+            Cryptor::encrypt(&config, data)
+        } else {
+            Cryptor::decrypt(&config, data)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum PKCS12Error {
+    /// The input isn't a DER encoded PKCS #12 file.
+    Format,
+    /// The file uses public key integrity or privacy, or unsupported
+    /// algorithms.
+    Unsupported,
+    /// The MAC didn't match, due to the wrong password or a corrupt file.
+    MAC,
+    Cryptor(CryptorError),
+}
+
+impl std::error::Error for PKCS12Error {}
+
+impl Display for PKCS12Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Format => f.write_str("input is not a PKCS #12 file"),
+            Self::Unsupported => f.write_str("unsupported PKCS #12 algorithm"),
+            Self::MAC => f.write_str("MAC verification failed, the password may be wrong"),
+            Self::Cryptor(e) => e.fmt(f),
+        }
+    }
+}
+
+/// Only the errors from parsing the encryption algorithm are mapped, since
+/// decryption is the responsibility of this module.
+impl From<PKCS8Error> for PKCS12Error {
+    fn from(e: PKCS8Error) -> Self {
+        match e {
+            PKCS8Error::Unsupported => Self::Unsupported,
+            PKCS8Error::Cryptor(e) => Self::Cryptor(e),
+            _ => Self::Format,
+        }
+    }
+}
+
+impl From<CryptorError> for PKCS12Error {
+    fn from(e: CryptorError) -> Self {
+        Self::Cryptor(e)
+    }
+}
+
+/// The contents of a file, in the order they appear.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Bag {
+    /// A DER encoded PKCS #8 `PrivateKeyInfo`, which has been decrypted if it
+    /// was shrouded.
+    Key(Vec<u8>),
+    /// A DER encoded X.509 certificate.
+    Certificate(Vec<u8>),
+    /// Any other bag, such as a CRL or secret, with its type's OID in dotted
+    /// form and its DER encoded value.
+    Other { oid: String, value: Vec<u8> },
+}
+
+/// Verifies the MAC, if there is one, and decrypts the bags.
+pub fn decrypt(input: impl AsRef<[u8]>, password: &str) -> Result<Vec<Bag>, PKCS12Error> {
+    let (auth_safe, mac) = read_pfx(input.as_ref()).ok_or(PKCS12Error::Format)?;
+
+    if let Some(mac) = mac {
+        mac.verify(password, auth_safe)?;
+    }
+
+    let mut reader = Reader::new(auth_safe);
+    let mut content_infos = reader.sequence().ok_or(PKCS12Error::Format)?;
+    reader.finish().ok_or(PKCS12Error::Format)?;
+
+    let mut bags = Vec::new();
+
+    while !content_infos.is_empty() {
+        let contents = match read_content_info(&mut content_infos)? {
+            Content::Data(data) => data.to_vec(),
+            Content::Encrypted(algorithm, ciphertext) => algorithm.decrypt(password, ciphertext)?,
+        };
+
+        read_safe_contents(&contents, password, &mut bags)?;
+    }
+
+    Ok(bags)
+}
+
+struct MacData<'a> {
+    algorithm: hash::Algorithm,
+    digest: &'a [u8],
+    salt: &'a [u8],
+    iterations: u32,
+}
+
+impl MacData<'_> {
+    fn verify(&self, password: &str, data: &[u8]) -> Result<(), PKCS12Error> {
+        let key = derive_key(
+            self.algorithm,
+            password,
+            self.salt,
+            Purpose::MAC,
+            self.iterations,
+            self.algorithm.digest_len(),
+        );
+        let mac =
+            HMAC::with_algorithm(self.algorithm, &key, data).ok_or(PKCS12Error::Unsupported)?;
+
+        if hmac::constant_time_eq(&mac, self.digest) {
+            Ok(())
+        } else {
+            Err(PKCS12Error::MAC)
+        }
+    }
+}
+
+/// Reads the `PFX`, returning the content of the authenticated safe and the
+/// MAC data.
+fn read_pfx(input: &[u8]) -> Option<(&[u8], Option<MacData<'_>>)> {
+    let mut reader = Reader::new(input);
+    let mut pfx = reader.sequence()?;
+    reader.finish()?;
+
+    if pfx.u32()? != 3 {
+        return None;
+    }
+
+    // Public key integrity uses signed data, which isn't supported.
+    let mut auth_safe = pfx.sequence()?;
+    if auth_safe.oid()? != DATA {
+        return None;
+    }
+    let mut content = Reader::new(auth_safe.read(der::explicit(0))?);
+    let data = content.octet_string()?;
+    content.finish()?;
+    auth_safe.finish()?;
+
+    let mac = if pfx.is_empty() {
+        None
+    } else {
+        let mut mac_data = pfx.sequence()?;
+        let mut digest_info = mac_data.sequence()?;
+        let (oid, _) = digest_info.algorithm()?;
+        let digest = digest_info.octet_string()?;
+        digest_info.finish()?;
+        let salt = mac_data.octet_string()?;
+        let iterations = if mac_data.is_empty() {
+            1
+        } else {
+            mac_data.u32()?
+        };
+        mac_data.finish()?;
+
+        let (_, algorithm) = DIGESTS.into_iter().find(|(o, _)| *o == oid)?;
+
+        Some(MacData {
+            algorithm,
+            digest,
+            salt,
+            iterations,
+        })
+    };
+
+    pfx.finish()?;
+
+    Some((data, mac))
+}
+
+enum Content<'a> {
+    Data(&'a [u8]),
+    Encrypted(EncryptionAlgorithm, &'a [u8]),
+}
+
+fn read_content_info<'a>(reader: &mut Reader<'a>) -> Result<Content<'a>, PKCS12Error> {
+    let mut content_info = reader.sequence().ok_or(PKCS12Error::Format)?;
+    let oid = content_info.oid().ok_or(PKCS12Error::Format)?;
+    let mut content = content_info
+        .read(der::explicit(0))
+        .map(Reader::new)
+        .ok_or(PKCS12Error::Format)?;
+    content_info.finish().ok_or(PKCS12Error::Format)?;
+
+    let result = match oid {
+        DATA => content.octet_string().map(Content::Data),
+        ENCRYPTED_DATA => {
+            let (oid, parameters, ciphertext) =
+                read_encrypted_data(&mut content).ok_or(PKCS12Error::Format)?;
+            let algorithm = EncryptionAlgorithm::from_der(oid, parameters)?;
+            Some(Content::Encrypted(algorithm, ciphertext))
+        }
+        // Public key privacy uses enveloped data.
+        _ => return Err(PKCS12Error::Unsupported),
+    };
+
+    content.finish().and(result).ok_or(PKCS12Error::Format)
+}
+
+/// Reads `EncryptedData`, returning the encryption algorithm's OID and
+/// parameters, and the ciphertext.
+fn read_encrypted_data<'a>(reader: &mut Reader<'a>) -> Option<(&'a [u8], Reader<'a>, &'a [u8])> {
+    let mut encrypted_data = reader.sequence()?;
+    encrypted_data.u32()?;
+    let mut info = encrypted_data.sequence()?;
+    encrypted_data.finish()?;
+
+    if info.oid()? != DATA {
+        return None;
+    }
+    let (oid, parameters) = info.algorithm()?;
+    let ciphertext = info.read(der::implicit(0))?;
+    info.finish()?;
+
+    Some((oid, parameters, ciphertext))
+}
+
+fn read_safe_contents(
+    input: &[u8],
+    password: &str,
+    bags: &mut Vec<Bag>,
+) -> Result<(), PKCS12Error> {
+    let mut reader = Reader::new(input);
+    let mut safe_contents = reader.sequence().ok_or(PKCS12Error::Format)?;
+    reader.finish().ok_or(PKCS12Error::Format)?;
+
+    while !safe_contents.is_empty() {
+        let mut safe_bag = safe_contents.sequence().ok_or(PKCS12Error::Format)?;
+        let oid = safe_bag.oid().ok_or(PKCS12Error::Format)?;
+        let value = safe_bag.read(der::explicit(0)).ok_or(PKCS12Error::Format)?;
+
+        // The attributes, such as the friendly name, are ignored.
+        safe_bag.read_optional(der::SET);
+        safe_bag.finish().ok_or(PKCS12Error::Format)?;
+
+        match oid {
+            KEY_BAG => bags.push(Bag::Key(value.to_vec())),
+            SHROUDED_KEY_BAG => bags.push(Bag::Key(decrypt_shrouded_key(value, password)?)),
+            CERT_BAG => bags.push(read_cert_bag(value).ok_or(PKCS12Error::Format)?),
+            SAFE_CONTENTS_BAG => read_safe_contents(value, password, bags)?,
+            _ => bags.push(Bag::Other {
+                oid: der::oid_to_string(oid).ok_or(PKCS12Error::Format)?,
+                value: value.to_vec(),
+            }),
+        }
+    }
+
+    Ok(())
+}
+
+/// Decrypts a PKCS #8 `EncryptedPrivateKeyInfo`.
+fn decrypt_shrouded_key(input: &[u8], password: &str) -> Result<Vec<u8>, PKCS12Error> {
+    let info = EncryptedPrivateKeyInfo::from_der(input)?;
+    Ok(info.algorithm.decrypt(password, &info.encrypted_data)?)
+}
+
+fn read_cert_bag(input: &[u8]) -> Option<Bag> {
+    let mut reader = Reader::new(input);
+    let mut cert_bag = reader.sequence()?;
+    reader.finish()?;
+
+    let oid = cert_bag.oid()?;
+    let mut value = Reader::new(cert_bag.read(der::explicit(0))?);
+    cert_bag.finish()?;
+
+    if oid != X509_CERTIFICATE {
+        return Some(Bag::Other {
+            oid: der::oid_to_string(CERT_BAG)?,
+            value: input.to_vec(),
+        });
+    }
+
+    let certificate = value.octet_string()?;
+    value.finish()?;
+
+    Some(Bag::Certificate(certificate.to_vec()))
+}
@@ -0,0 +1,27 @@
+//! Cryptographically secure random numbers.
+
+use crate::cryptor::{CryptorError, Status};
+use std::ffi::c_void;
+
+extern "C" {
+    fn CCRandomGenerateBytes(bytes: *mut c_void, count: usize) -> Status;
+}
+
+/// Fills the buffer with random bytes, suitable for keys, salts and
+/// initialization vectors.
+pub fn fill(bytes: &mut [u8]) -> Result<(), CryptorError> {
+    let status = unsafe { CCRandomGenerateBytes(bytes.as_mut_ptr() as *mut c_void, bytes.len()) };
+
+    if status != Status::Success {
+        return Err(status.into());
+    }
+
+    Ok(())
+}
+
+/// Returns an array of random bytes.
+pub fn bytes<const N: usize>() -> Result<[u8; N], CryptorError> {
+    let mut bytes = [0u8; N];
+    fill(&mut bytes)?;
+    Ok(bytes)
+}
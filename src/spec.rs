@@ -0,0 +1,481 @@
+//! Owned cipher descriptions which can be stored, parsed and paired with a key
+//! at runtime.
+
+pub use crate::cryptor::Algorithm;
+use crate::cryptor::{BlockCipher, Config, Cryptor, CryptorError, Mode, Padding};
+use std::{fmt::Display, str::FromStr};
+
+impl Algorithm {
+    const fn name(&self) -> &'static str {
+        match self {
+            Algorithm::AES => "aes",
+            Algorithm::DES => "des",
+            Algorithm::TDES => "des-ede3",
+            Algorithm::CAST => "cast5",
+            Algorithm::RC4 => "rc4",
+            Algorithm::RC2 => "rc2",
+            Algorithm::Blowfish => "bf",
+        }
+    }
+
+    fn supports_key_len(&self, len: usize) -> bool {
+        match self {
+            Algorithm::AES => matches!(len, 16 | 24 | 32),
+            Algorithm::DES => len == 8,
+            Algorithm::TDES => len == 24,
+            Algorithm::CAST => (5..=16).contains(&len),
+            Algorithm::RC4 => (1..=512).contains(&len),
+            Algorithm::RC2 => (1..=128).contains(&len),
+            Algorithm::Blowfish => (8..=56).contains(&len),
+        }
+    }
+
+    /// The key length used when none is given in a cipher name, in bytes.
+    const fn default_key_len(&self) -> Option<usize> {
+        match self {
+            Algorithm::AES => None,
+            Algorithm::DES => Some(8),
+            Algorithm::TDES => Some(24),
+            Algorithm::CAST | Algorithm::RC4 | Algorithm::RC2 | Algorithm::Blowfish => Some(16),
+        }
+    }
+}
+
+const fn mode_name(mode: Mode) -> &'static str {
+    match mode {
+        Mode::ECB => "ecb",
+        Mode::CBC => "cbc",
+        Mode::CFB => "cfb",
+        Mode::CTR => "ctr",
+        Mode::OFB => "ofb",
+        Mode::XTS => "xts",
+        Mode::CFB8 => "cfb8",
+    }
+}
+
+fn parse_mode(name: &str) -> Option<Mode> {
+    [
+        Mode::ECB,
+        Mode::CBC,
+        Mode::CFB,
+        Mode::CTR,
+        Mode::OFB,
+        Mode::CFB8,
+    ]
+    .into_iter()
+    .find(|mode| mode_name(*mode) == name)
+}
+
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ParseCipherError {
+    Algorithm,
+    KeySize,
+    Mode,
+    Unsupported,
+}
+
+impl std::error::Error for ParseCipherError {}
+
+impl Display for ParseCipherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Algorithm => "unknown cipher algorithm",
+            Self::KeySize => "invalid key size for the cipher algorithm",
+            Self::Mode => "invalid mode for the cipher algorithm",
+            Self::Unsupported => "cipher is not supported by common crypto",
+        };
+
+        f.write_str(s)
+    }
+}
+
+/// An owned description of a cipher, which can be combined with a key and
+/// initialization vector to create a [`Cryptor`].
+///
+/// Specs are usually parsed from OpenSSL's cipher names such as `aes-256-cbc`.
+/// The padding is PKCS#7 for ECB and CBC modes, as with OpenSSL, and none
+/// otherwise.
+///
+/// ```
+/// # use common_crypto::spec::CipherSpec;
+/// let spec: CipherSpec = "aes-256-cbc".parse().unwrap();
+/// let key = b"0123456789abcdef0123456789abcdef";
+/// let iv: &[u8] = b"use random iv :)";
+///
+/// let encrypted = spec.encrypt(key, Some(iv), b"Hello").unwrap();
+/// assert_eq!(encrypted.len(), 16);
+/// assert_eq!(spec.decrypt(key, Some(iv), encrypted).unwrap(), b"Hello");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CipherSpec {
+    pub algorithm: Algorithm,
+    /// Must be `None` for RC4, and must be specified for others.
+    pub mode: Option<Mode>,
+    pub padding: Padding,
+    /// The key length in bytes.
+    pub key_len: usize,
+}
+
+impl CipherSpec {
+    /// The padding implied by a cipher name.
+    fn default_padding(&self) -> Padding {
+        match self.mode {
+            Some(Mode::ECB) | Some(Mode::CBC) => Padding::PKCS7,
+            _ => Padding::None,
+        }
+    }
+
+    /// The required initialization vector length in bytes, which is 0 when
+    /// none is used.
+    pub fn iv_len(&self) -> usize {
+        match (self.algorithm, self.mode) {
+            (Algorithm::RC4, _) | (_, None) | (_, Some(Mode::ECB)) => 0,
+            (Algorithm::AES, _) => 16,
+            _ => 8,
+        }
+    }
+
+    /// Creates a config for the key and initialization vector, checking that
+    /// they're valid for the spec.
+    pub fn config<'a>(
+        &self,
+        key: &'a [u8],
+        iv: Option<&'a [u8]>,
+    ) -> Result<Config<'a>, CryptorError> {
+        fn array<const N: usize>(slice: &[u8]) -> Result<&[u8; N], CryptorError> {
+            slice.try_into().map_err(|_| CryptorError::KeySize)
+        }
+
+        if key.len() != self.key_len {
+            return Err(CryptorError::KeySize);
+        }
+
+        match self.algorithm {
+            Algorithm::AES => match key.len() {
+                16 => self.with_mode(Config::aes128(array(key)?), iv),
+                24 => self.with_mode(Config::aes192(array(key)?), iv),
+                _ => self.with_mode(Config::aes256(array(key)?), iv),
+            },
+            Algorithm::DES => self.with_mode(Config::des(array(key)?), iv),
+            Algorithm::TDES => self.with_mode(Config::tdes(array(key)?), iv),
+            Algorithm::CAST => self.with_mode(Config::cast(key), iv),
+            Algorithm::RC2 => self.with_mode(Config::rc2(key), iv),
+            Algorithm::Blowfish => self.with_mode(Config::blowfish(key), iv),
+            Algorithm::RC4 if self.mode.is_none() && iv.is_none() => Ok(Config::rc4(key)),
+            Algorithm::RC4 => Err(CryptorError::Param),
+        }
+    }
+
+    fn with_mode<'a, const BLOCK_SIZE: usize>(
+        &self,
+        cipher: BlockCipher<'a, BLOCK_SIZE>,
+        iv: Option<&'a [u8]>,
+    ) -> Result<Config<'a>, CryptorError> {
+        let iv = iv
+            .map(<&[u8; BLOCK_SIZE]>::try_from)
+            .transpose()
+            .map_err(|_| CryptorError::Param)?;
+
+        let padded = matches!(self.mode, Some(Mode::ECB) | Some(Mode::CBC));
+        if !padded && self.padding != Padding::None {
+            return Err(CryptorError::Param);
+        }
+
+        let config = match (self.mode, iv) {
+            (Some(Mode::ECB), None) => cipher.ecb(self.padding),
+            (Some(Mode::ECB), Some(_)) => return Err(CryptorError::InitializationVectorPresent),
+            (Some(Mode::CBC), Some(iv)) => cipher.cbc(iv, self.padding),
+            (Some(Mode::CFB), Some(iv)) => cipher.cfb(iv),
+            (Some(Mode::CFB8), Some(iv)) => cipher.cfb8(iv),
+            (Some(Mode::OFB), Some(iv)) => cipher.ofb(iv),
+            (Some(Mode::CTR), Some(iv)) => cipher.ctr(iv),
+            _ => return Err(CryptorError::Param),
+        };
+
+        Ok(config)
+    }
+
+    pub fn new_encryptor(
+        &self,
+        key: impl AsRef<[u8]>,
+        iv: Option<&[u8]>,
+    ) -> Result<Cryptor, CryptorError> {
+        Cryptor::new_encryptor(&self.config(key.as_ref(), iv)?)
+    }
+
+    pub fn new_decryptor(
+        &self,
+        key: impl AsRef<[u8]>,
+        iv: Option<&[u8]>,
+    ) -> Result<Cryptor, CryptorError> {
+        Cryptor::new_decryptor(&self.config(key.as_ref(), iv)?)
+    }
+
+    pub fn encrypt(
+        &self,
+        key: impl AsRef<[u8]>,
+        iv: Option<&[u8]>,
+        input: impl AsRef<[u8]>,
+    ) -> Result<Vec<u8>, CryptorError> {
+        Cryptor::encrypt(&self.config(key.as_ref(), iv)?, input)
+    }
+
+    pub fn decrypt(
+        &self,
+        key: impl AsRef<[u8]>,
+        iv: Option<&[u8]>,
+        input: impl AsRef<[u8]>,
+    ) -> Result<Vec<u8>, CryptorError> {
+        Cryptor::decrypt(&self.config(key.as_ref(), iv)?, input)
+    }
+}
+
+/// OpenSSL's names which don't follow the `algorithm[-bits]-mode` form.
+const ALIASES: &[(&str, &str)] = &[
+    ("aes128", "aes-128-cbc"),
+    ("aes192", "aes-192-cbc"),
+    ("aes256", "aes-256-cbc"),
+    ("des", "des-cbc"),
+    ("des3", "des-ede3-cbc"),
+    ("des-ede3", "des-ede3-ecb"),
+    ("cast", "cast5-cbc"),
+    ("cast-cbc", "cast5-cbc"),
+    ("bf", "bf-cbc"),
+    ("blowfish", "bf-cbc"),
+    ("rc2", "rc2-cbc"),
+    ("rc2-40", "rc2-40-cbc"),
+    ("rc2-64", "rc2-64-cbc"),
+    ("rc2-128", "rc2-cbc"),
+];
+
+/// Algorithms known to OpenSSL which Common Crypto doesn't provide. Two key
+/// triple DES is `des-ede`.
+const UNSUPPORTED_ALGORITHMS: &[&str] = &[
+    "des-ede", "desx", "aria", "camellia", "chacha20", "idea", "seed", "sm4", "rc5",
+];
+
+/// Modes known to OpenSSL which Common Crypto doesn't provide. XTS is
+/// excluded because the cryptor doesn't accept a tweak key.
+const UNSUPPORTED_MODES: &[&str] = &[
+    "cfb1",
+    "xts",
+    "gcm",
+    "ccm",
+    "ocb",
+    "siv",
+    "wrap",
+    "wrap-pad",
+    "cbc-hmac-sha1",
+    "cbc-hmac-sha256",
+    "hmac-md5",
+];
+
+/// Whether the name starts with the prefix followed by a hyphen or nothing.
+fn has_prefix(name: &str, prefix: &str) -> bool {
+    name.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+}
+
+impl FromStr for CipherSpec {
+    type Err = ParseCipherError;
+
+    /// Parses the cipher names used by OpenSSL, such as `aes-128-cfb8`,
+    /// `des-ede3-cbc` or `rc2-40-cbc`, ignoring case.
+    ///
+    /// Names have the form `algorithm[-bits][-mode]`, where the key size in
+    /// bits is required for AES and optional for algorithms with variable key
+    /// sizes, along with OpenSSL's aliases such as `aes256` and `bf`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_ascii_lowercase();
+        let name = ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map_or(name.as_str(), |(_, name)| name);
+
+        if UNSUPPORTED_ALGORITHMS
+            .iter()
+            .any(|prefix| has_prefix(name, prefix))
+            || name.ends_with("-wrap")
+            || name.ends_with("-wrap-pad")
+        {
+            return Err(ParseCipherError::Unsupported);
+        }
+
+        let algorithm = [
+            Algorithm::AES,
+            Algorithm::TDES,
+            Algorithm::DES,
+            Algorithm::CAST,
+            Algorithm::RC4,
+            Algorithm::RC2,
+            Algorithm::Blowfish,
+        ]
+        .into_iter()
+        .find(|algorithm| has_prefix(name, algorithm.name()))
+        .ok_or(ParseCipherError::Algorithm)?;
+
+        let is_bits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+        let (bits, mode) = match name[algorithm.name().len()..].strip_prefix('-') {
+            None => (None, None),
+            Some(rest) => match rest.split_once('-') {
+                Some((bits, mode)) if is_bits(bits) => (Some(bits), Some(mode)),
+                _ if is_bits(rest) => (Some(rest), None),
+                _ => (None, Some(rest)),
+            },
+        };
+
+        let key_len = match (bits, algorithm.default_key_len()) {
+            (Some(bits), _) => match bits.parse::<usize>() {
+                Ok(bits) if bits.is_multiple_of(8) => bits / 8,
+                _ => return Err(ParseCipherError::KeySize),
+            },
+            (None, Some(key_len)) => key_len,
+            (None, None) => return Err(ParseCipherError::KeySize),
+        };
+
+        if !algorithm.supports_key_len(key_len) {
+            return Err(ParseCipherError::KeySize);
+        }
+
+        let mode = match mode {
+            Some(mode) if UNSUPPORTED_MODES.contains(&mode) => {
+                return Err(ParseCipherError::Unsupported)
+            }
+            Some(mode) => Some(parse_mode(mode).ok_or(ParseCipherError::Mode)?),
+            None => None,
+        };
+
+        if mode.is_none() != (algorithm == Algorithm::RC4) {
+            return Err(ParseCipherError::Mode);
+        }
+
+        let mut spec = Self {
+            algorithm,
+            mode,
+            padding: Padding::None,
+            key_len,
+        };
+
+        spec.padding = spec.default_padding();
+
+        Ok(spec)
+    }
+}
+
+impl Display for CipherSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.algorithm.name())?;
+
+        if self.algorithm.default_key_len() != Some(self.key_len) {
+            write!(f, "-{}", self.key_len * 8)?;
+        }
+
+        if let Some(mode) = self.mode {
+            write!(f, "-{}", mode_name(mode))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Specs are serialised as their name, or as a map of the name and padding
+/// when the padding differs from the one implied by the name.
+///
+/// Deserialising has to tell these apart, so only self-describing formats
+/// such as JSON, TOML or YAML are supported. Formats like bincode and
+/// postcard fail at runtime; store the name and padding separately with
+/// those.
+///
+/// ```
+/// # use common_crypto::cryptor::Padding;
+/// # use common_crypto::spec::CipherSpec;
+/// let spec: CipherSpec = serde_json::from_str(r#""aes-256-cbc""#).unwrap();
+/// assert_eq!(spec.padding, Padding::PKCS7);
+///
+/// let spec: CipherSpec =
+///     serde_json::from_str(r#"{ "cipher": "aes-256-cbc", "padding": "none" }"#).unwrap();
+/// assert_eq!(spec.padding, Padding::None);
+/// ```
+#[cfg(feature = "serde")]
+impl serde::Serialize for CipherSpec {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        if self.padding == self.default_padding() {
+            return serializer.collect_str(self);
+        }
+
+        let mut state = serializer.serialize_struct("CipherSpec", 2)?;
+        state.serialize_field("cipher", &self.to_string())?;
+        state.serialize_field("padding", padding_name(self.padding))?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+const fn padding_name(padding: Padding) -> &'static str {
+    match padding {
+        Padding::None => "none",
+        Padding::PKCS7 => "pkcs7",
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CipherSpec {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::{Error, MapAccess, Visitor};
+
+        struct SpecVisitor;
+
+        impl<'de> Visitor<'de> for SpecVisitor {
+            type Value = CipherSpec;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a cipher name, or a map of the cipher name and padding")
+            }
+
+            fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut spec: Option<CipherSpec> = None;
+                let mut padding = None;
+
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "cipher" => {
+                            spec = Some(
+                                map.next_value::<String>()?
+                                    .parse()
+                                    .map_err(A::Error::custom)?,
+                            )
+                        }
+                        "padding" => {
+                            let name = map.next_value::<String>()?;
+                            padding = Some(
+                                [Padding::None, Padding::PKCS7]
+                                    .into_iter()
+                                    .find(|padding| padding_name(*padding) == name)
+                                    .ok_or_else(|| {
+                                        A::Error::unknown_variant(&name, &["none", "pkcs7"])
+                                    })?,
+                            );
+                        }
+                        other => {
+                            return Err(A::Error::unknown_field(other, &["cipher", "padding"]))
+                        }
+                    }
+                }
+
+                let mut spec = spec.ok_or_else(|| A::Error::missing_field("cipher"))?;
+                spec.padding = padding.unwrap_or(spec.padding);
+                Ok(spec)
+            }
+        }
+
+        // Either form may appear, so the format has to say which it is.
+        deserializer.deserialize_any(SpecVisitor)
+    }
+}
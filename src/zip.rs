@@ -0,0 +1,538 @@
+//! Encrypted ZIP archive entries, using WinZip's AES encryption (AE-1 and
+//! AE-2) or PKZIP's traditional encryption.
+//!
+//! Entries are found through the central directory, and decrypting one
+//! returns its data still compressed with [`Entry::compression_method`], which
+//! is left to the caller to inflate unless it's 0, meaning stored.
+//!
+//! ```no_run
+//! # use common_crypto::zip;
+//! let archive = std::fs::read("archive.zip").unwrap();
+//!
+//! for entry in zip::entries(&archive).unwrap() {
+//!     let data = entry.decrypt("password").unwrap();
+//!     println!("{}: {} bytes", entry.name, data.len());
+//! }
+//! ```
+
+use crate::cryptor::{CryptorError, Mode, Padding};
+use crate::ctr::{Counter, Endianness, CTR};
+use crate::hmac;
+use crate::kdf::{self, PRF};
+use crate::random;
+use crate::spec::{Algorithm, CipherSpec};
+use std::fmt::{self, Display};
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+
+/// The compression method recorded for AES encrypted entries, whose actual
+/// method is in the extra field.
+const AES_METHOD: u16 = 99;
+const AES_EXTRA_FIELD: u16 = 0x9901;
+
+const FLAG_ENCRYPTED: u16 = 1;
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+const FLAG_STRONG_ENCRYPTION: u16 = 1 << 6;
+
+/// The PBKDF2 iterations used by WinZip.
+const AES_ITERATIONS: u32 = 1000;
+const AES_VERIFIER_LEN: usize = 2;
+/// The HMAC-SHA1 is truncated to 80 bits.
+const AES_MAC_LEN: usize = 10;
+
+/// The length of the random header prepended by traditional encryption.
+const TRADITIONAL_HEADER_LEN: usize = 12;
+
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ZIPError {
+    /// The archive or an encrypted entry is malformed.
+    Format,
+    /// The archive uses ZIP64, strong encryption or an unknown AES strength.
+    Unsupported,
+    /// The password verification value didn't match, so the password is
+    /// wrong.
+    Password,
+    /// The AES authentication code didn't match, so the entry has been
+    /// modified or corrupted.
+    Authentication,
+    /// The CRC of a stored entry didn't match.
+    CRC,
+    Cryptor(CryptorError),
+}
+
+impl std::error::Error for ZIPError {}
+
+impl Display for ZIPError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Format => f.write_str("input is not a ZIP archive, or is malformed"),
+            Self::Unsupported => f.write_str("unsupported ZIP feature"),
+            Self::Password => f.write_str("the password is wrong"),
+            Self::Authentication => f.write_str("the entry has been modified"),
+            Self::CRC => f.write_str("CRC mismatch"),
+            Self::Cryptor(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<CryptorError> for ZIPError {
+    fn from(e: CryptorError) -> Self {
+        Self::Cryptor(e)
+    }
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                crc >> 1 ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+};
+
+fn crc32_update(crc: u32, byte: u8) -> u32 {
+    CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ crc >> 8
+}
+
+/// The CRC-32 stored for each entry.
+pub fn crc32(data: impl AsRef<[u8]>) -> u32 {
+    !data
+        .as_ref()
+        .iter()
+        .fold(!0, |crc, b| crc32_update(crc, *b))
+}
+
+/// The AES key size, as stored in the extra field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AESStrength {
+    AES128 = 1,
+    AES192 = 2,
+    AES256 = 3,
+}
+
+impl AESStrength {
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Self::AES128),
+            2 => Some(Self::AES192),
+            3 => Some(Self::AES256),
+            _ => None,
+        }
+    }
+
+    pub fn key_len(self) -> usize {
+        match self {
+            Self::AES128 => 16,
+            Self::AES192 => 24,
+            Self::AES256 => 32,
+        }
+    }
+
+    /// The salt is half the length of the key.
+    pub fn salt_len(self) -> usize {
+        self.key_len() / 2
+    }
+}
+
+/// The keys derived from the password, and the value used to check it.
+struct AESKeys {
+    encryption: Vec<u8>,
+    authentication: Vec<u8>,
+    verifier: Vec<u8>,
+}
+
+impl AESKeys {
+    fn new(strength: AESStrength, password: &[u8], salt: &[u8]) -> Result<Self, CryptorError> {
+        let key_len = strength.key_len();
+        let mut keys = kdf::pbkdf2(
+            password,
+            salt,
+            PRF::SHA1,
+            AES_ITERATIONS,
+            2 * key_len + AES_VERIFIER_LEN,
+        )?;
+
+        let verifier = keys.split_off(2 * key_len);
+        let authentication = keys.split_off(key_len);
+
+        Ok(Self {
+            encryption: keys,
+            authentication,
+            verifier,
+        })
+    }
+
+    /// Applies AES in CTR mode with a little-endian counter spanning the
+    /// block, starting at 1.
+    fn apply_keystream(&self, data: &mut [u8]) -> Result<(), CryptorError> {
+        let spec = CipherSpec {
+            algorithm: Algorithm::AES,
+            mode: Some(Mode::CTR),
+            padding: Padding::None,
+            key_len: self.encryption.len(),
+        };
+
+        let mut iv = [0; 16];
+        iv[0] = 1;
+
+        let counter = Counter {
+            endianness: Endianness::Little,
+            bits: 128,
+        };
+
+        CTR::with_counter(&spec.config(&self.encryption, Some(&iv))?, counter)?
+            .apply_keystream(data)
+    }
+
+    fn mac(&self, ciphertext: &[u8]) -> [u8; AES_MAC_LEN] {
+        let mut hmac = hmac::SHA1::new(&self.authentication);
+        hmac.update(ciphertext);
+        hmac.finish()[..AES_MAC_LEN].try_into().unwrap()
+    }
+}
+
+/// Encrypts the entry's data with WinZip AES, returning the salt, password
+/// verification value, ciphertext and authentication code.
+pub fn encrypt_aes(
+    strength: AESStrength,
+    password: impl AsRef<[u8]>,
+    data: impl AsRef<[u8]>,
+) -> Result<Vec<u8>, ZIPError> {
+    let mut salt = vec![0; strength.salt_len()];
+    random::fill(&mut salt)?;
+
+    let keys = AESKeys::new(strength, password.as_ref(), &salt)?;
+    let mut ciphertext = data.as_ref().to_vec();
+    keys.apply_keystream(&mut ciphertext)?;
+
+    Ok([
+        &salt,
+        &keys.verifier,
+        &ciphertext[..],
+        &keys.mac(&ciphertext),
+    ]
+    .concat())
+}
+
+/// Decrypts the entry's data encrypted with WinZip AES, checking the password
+/// verification value and then the authentication code.
+pub fn decrypt_aes(
+    strength: AESStrength,
+    password: impl AsRef<[u8]>,
+    data: impl AsRef<[u8]>,
+) -> Result<Vec<u8>, ZIPError> {
+    let data = data.as_ref();
+    let salt_len = strength.salt_len();
+
+    if data.len() < salt_len + AES_VERIFIER_LEN + AES_MAC_LEN {
+        return Err(ZIPError::Format);
+    }
+
+    let (salt, rest) = data.split_at(salt_len);
+    let (verifier, rest) = rest.split_at(AES_VERIFIER_LEN);
+    let (ciphertext, mac) = rest.split_at(rest.len() - AES_MAC_LEN);
+
+    let keys = AESKeys::new(strength, password.as_ref(), salt)?;
+
+    // The verifier is only 16 bits, so a wrong password passes 1 in 65536
+    // times and is then caught by the authentication code.
+    if keys.verifier != verifier {
+        return Err(ZIPError::Password);
+    }
+
+    if !hmac::constant_time_eq(&keys.mac(ciphertext), mac) {
+        return Err(ZIPError::Authentication);
+    }
+
+    let mut plaintext = ciphertext.to_vec();
+    keys.apply_keystream(&mut plaintext)?;
+
+    Ok(plaintext)
+}
+
+/// The state of PKZIP's traditional stream cipher.
+struct TraditionalKeys([u32; 3]);
+
+impl TraditionalKeys {
+    fn new(password: &[u8]) -> Self {
+        let mut keys = Self([0x12345678, 0x23456789, 0x34567890]);
+        for byte in password {
+            keys.update(*byte);
+        }
+
+        keys
+    }
+
+    fn update(&mut self, plain: u8) {
+        let [k0, k1, k2] = &mut self.0;
+
+        *k0 = crc32_update(*k0, plain);
+        *k1 = k1
+            .wrapping_add(*k0 & 0xff)
+            .wrapping_mul(134775813)
+            .wrapping_add(1);
+        *k2 = crc32_update(*k2, (*k1 >> 24) as u8);
+    }
+
+    fn keystream_byte(&self) -> u8 {
+        let temp = (self.0[2] | 2) as u16;
+        (temp.wrapping_mul(temp ^ 1) >> 8) as u8
+    }
+
+    fn encrypt(&mut self, data: &mut [u8]) {
+        for byte in data {
+            let plain = *byte;
+            *byte ^= self.keystream_byte();
+            self.update(plain);
+        }
+    }
+
+    fn decrypt(&mut self, data: &mut [u8]) {
+        for byte in data {
+            *byte ^= self.keystream_byte();
+            self.update(*byte);
+        }
+    }
+}
+
+/// Encrypts the entry's data with PKZIP's traditional encryption, which is
+/// weak and only for compatibility. The check byte is the high byte of the
+/// CRC, or of the modification time if the entry has a data descriptor.
+pub fn encrypt_traditional(
+    password: impl AsRef<[u8]>,
+    data: impl AsRef<[u8]>,
+    check: u8,
+) -> Result<Vec<u8>, ZIPError> {
+    let mut output = vec![0; TRADITIONAL_HEADER_LEN];
+    random::fill(&mut output[..TRADITIONAL_HEADER_LEN - 1])?;
+    output[TRADITIONAL_HEADER_LEN - 1] = check;
+    output.extend_from_slice(data.as_ref());
+
+    TraditionalKeys::new(password.as_ref()).encrypt(&mut output);
+    Ok(output)
+}
+
+/// Decrypts the entry's data encrypted with PKZIP's traditional encryption,
+/// checking the last byte of the header against the check byte. This detects
+/// most wrong passwords, but 1 in 256 pass.
+pub fn decrypt_traditional(
+    password: impl AsRef<[u8]>,
+    data: impl AsRef<[u8]>,
+    check: u8,
+) -> Result<Vec<u8>, ZIPError> {
+    let mut data = data.as_ref().to_vec();
+    if data.len() < TRADITIONAL_HEADER_LEN {
+        return Err(ZIPError::Format);
+    }
+
+    TraditionalKeys::new(password.as_ref()).decrypt(&mut data);
+
+    if data[TRADITIONAL_HEADER_LEN - 1] != check {
+        return Err(ZIPError::Password);
+    }
+
+    Ok(data.split_off(TRADITIONAL_HEADER_LEN))
+}
+
+/// The WinZip AES extra field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AESExtraField {
+    /// 1 for AE-1, or 2 for AE-2 where the CRC isn't stored.
+    pub version: u16,
+    pub strength: AESStrength,
+    pub compression_method: u16,
+}
+
+impl AESExtraField {
+    /// Finds the field among the entry's extra fields.
+    pub fn from_extra(extra: &[u8]) -> Result<Option<Self>, ZIPError> {
+        let mut extra = extra;
+
+        while extra.len() >= 4 {
+            let id = u16_at(extra, 0);
+            let len = u16_at(extra, 2) as usize;
+            let field = extra.get(4..4 + len).ok_or(ZIPError::Format)?;
+            extra = &extra[4 + len..];
+
+            if id != AES_EXTRA_FIELD {
+                continue;
+            }
+
+            if len != 7 || &field[2..4] != b"AE" {
+                return Err(ZIPError::Format);
+            }
+
+            return Ok(Some(Self {
+                version: u16_at(field, 0),
+                strength: AESStrength::from_id(field[4]).ok_or(ZIPError::Unsupported)?,
+                compression_method: u16_at(field, 5),
+            }));
+        }
+
+        Ok(None)
+    }
+}
+
+/// An entry from the central directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry<'a> {
+    /// The name, which is UTF-8 if bit 11 of the flags is set and is
+    /// otherwise usually code page 437, converted lossily.
+    pub name: String,
+    pub flags: u16,
+    /// The compression method in the header, which is 99 for AES.
+    pub method: u16,
+    /// The MS-DOS modification time.
+    pub mod_time: u16,
+    pub crc32: u32,
+    pub aes: Option<AESExtraField>,
+    /// The encrypted and compressed data.
+    pub data: &'a [u8],
+}
+
+impl Entry<'_> {
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & FLAG_ENCRYPTED != 0
+    }
+
+    /// The method the data is compressed with once decrypted, 0 for stored or
+    /// 8 for deflate.
+    pub fn compression_method(&self) -> u16 {
+        self.aes.map_or(self.method, |aes| aes.compression_method)
+    }
+
+    /// Decrypts the data, which is returned unchanged if it isn't encrypted.
+    /// The CRC is checked for stored entries, except for AE-2 which doesn't
+    /// store it.
+    pub fn decrypt(&self, password: impl AsRef<[u8]>) -> Result<Vec<u8>, ZIPError> {
+        if self.flags & FLAG_STRONG_ENCRYPTION != 0 {
+            return Err(ZIPError::Unsupported);
+        }
+
+        let data = match (self.is_encrypted(), self.aes) {
+            (false, _) => self.data.to_vec(),
+            (true, Some(aes)) => decrypt_aes(aes.strength, password, self.data)?,
+            (true, None) => {
+                let check = if self.flags & FLAG_DATA_DESCRIPTOR != 0 {
+                    (self.mod_time >> 8) as u8
+                } else {
+                    (self.crc32 >> 24) as u8
+                };
+
+                decrypt_traditional(password, self.data, check)?
+            }
+        };
+
+        let has_crc = self.aes.is_none_or(|aes| aes.version != 2);
+        if self.compression_method() == 0 && has_crc && crc32(&data) != self.crc32 {
+            return Err(ZIPError::CRC);
+        }
+
+        Ok(data)
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/// Reads the entries listed in the archive's central directory.
+pub fn entries(archive: &[u8]) -> Result<Vec<Entry<'_>>, ZIPError> {
+    if archive.len() < 22 {
+        return Err(ZIPError::Format);
+    }
+
+    // The end of central directory record is followed by a comment of up to
+    // 65535 bytes.
+    let end = (0..=archive.len() - 22)
+        .rev()
+        .take(65536)
+        .find(|&i| u32_at(archive, i) == END_OF_CENTRAL_DIRECTORY)
+        .ok_or(ZIPError::Format)?;
+
+    let count = u16_at(archive, end + 10);
+    let offset = u32_at(archive, end + 16);
+
+    if count == 0xffff || offset == 0xffffffff {
+        return Err(ZIPError::Unsupported);
+    }
+
+    let mut directory = archive.get(offset as usize..end).ok_or(ZIPError::Format)?;
+    let mut entries = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+        if directory.len() < 46 || u32_at(directory, 0) != CENTRAL_HEADER {
+            return Err(ZIPError::Format);
+        }
+
+        let compressed_size = u32_at(directory, 20);
+        let name_len = u16_at(directory, 28) as usize;
+        let extra_len = u16_at(directory, 30) as usize;
+        let comment_len = u16_at(directory, 32) as usize;
+        let local_offset = u32_at(directory, 42);
+
+        if compressed_size == 0xffffffff || local_offset == 0xffffffff {
+            return Err(ZIPError::Unsupported);
+        }
+
+        let name = directory.get(46..46 + name_len).ok_or(ZIPError::Format)?;
+        let extra = directory
+            .get(46 + name_len..46 + name_len + extra_len)
+            .ok_or(ZIPError::Format)?;
+
+        let method = u16_at(directory, 10);
+        let aes = AESExtraField::from_extra(extra)?;
+        if method == AES_METHOD && aes.is_none() {
+            return Err(ZIPError::Format);
+        }
+
+        entries.push(Entry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            flags: u16_at(directory, 8),
+            method,
+            mod_time: u16_at(directory, 12),
+            crc32: u32_at(directory, 16),
+            aes,
+            data: local_data(archive, local_offset as usize, compressed_size as usize)?,
+        });
+
+        directory = directory
+            .get(46 + name_len + extra_len + comment_len..)
+            .ok_or(ZIPError::Format)?;
+    }
+
+    Ok(entries)
+}
+
+/// The data following a local header, whose name and extra field may differ
+/// in length from the central directory's.
+fn local_data(archive: &[u8], offset: usize, len: usize) -> Result<&[u8], ZIPError> {
+    let header = archive.get(offset..offset + 30).ok_or(ZIPError::Format)?;
+    if u32_at(header, 0) != LOCAL_HEADER {
+        return Err(ZIPError::Format);
+    }
+
+    let start = offset + 30 + u16_at(header, 26) as usize + u16_at(header, 28) as usize;
+    archive.get(start..start + len).ok_or(ZIPError::Format)
+}
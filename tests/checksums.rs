@@ -0,0 +1,160 @@
+use common_crypto::hash::checksums::{self, Entry, Format, Status};
+use common_crypto::hash::{Algorithm, Hash};
+use std::path::PathBuf;
+
+const SHA256_DATA: &str = "3a6eb0790f39ac87c94f3856b2dd2c5d110e6811602261a9a923d3bb23adc8b7";
+const MD5_DATA: &str = "8d777f385d3dfec8815d20f7496026dc";
+
+fn entry(algorithm: Algorithm, digest: &str, path: &str, binary: bool) -> Entry {
+    Entry {
+        algorithm,
+        digest: hex::decode(digest).unwrap(),
+        path: PathBuf::from(path),
+        binary,
+    }
+}
+
+#[test]
+fn parse_gnu() {
+    let text = format!(
+        "{SHA256_DATA}  data.txt\n\n# comment\n{SHA256_DATA} *dir/with spaces.bin\r\n\\{SHA256_DATA}  a\\\\b\\nc\n"
+    );
+
+    assert_eq!(
+        checksums::parse(&text, None).unwrap(),
+        [
+            entry(Algorithm::SHA256, SHA256_DATA, "data.txt", false),
+            entry(Algorithm::SHA256, SHA256_DATA, "dir/with spaces.bin", true),
+            entry(Algorithm::SHA256, SHA256_DATA, "a\\b\nc", false),
+        ]
+    );
+}
+
+#[test]
+fn parse_infers_algorithm() {
+    let text = format!("{MD5_DATA}  a\n{SHA256_DATA}  b\n");
+
+    assert_eq!(
+        checksums::parse(&text, None).unwrap(),
+        [
+            entry(Algorithm::MD5, MD5_DATA, "a", false),
+            entry(Algorithm::SHA256, SHA256_DATA, "b", false),
+        ]
+    );
+
+    // The given algorithm must match the digest length.
+    assert_eq!(
+        checksums::parse(&format!("{MD5_DATA}  a\n"), Some(Algorithm::MD4)).unwrap(),
+        [entry(Algorithm::MD4, MD5_DATA, "a", false)]
+    );
+    assert_eq!(
+        checksums::parse(&format!("{MD5_DATA}  a\n"), Some(Algorithm::SHA1))
+            .unwrap_err()
+            .line,
+        1
+    );
+}
+
+#[test]
+fn parse_bsd() {
+    let text = format!("SHA256 (data (1).txt) = {SHA256_DATA}\nMD5 (b) = {MD5_DATA}\n");
+
+    assert_eq!(
+        checksums::parse(&text, Some(Algorithm::SHA512)).unwrap(),
+        [
+            entry(Algorithm::SHA256, SHA256_DATA, "data (1).txt", false),
+            entry(Algorithm::MD5, MD5_DATA, "b", false),
+        ]
+    );
+}
+
+#[test]
+fn parse_errors() {
+    let invalid = [
+        "not a checksum".to_string(),
+        format!("{SHA256_DATA} data.txt"),
+        format!("{SHA256_DATA}  "),
+        format!("{}  data.txt", &SHA256_DATA[1..]),
+        format!("SHA3 (data.txt) = {SHA256_DATA}"),
+        format!("MD5 (data.txt) = {SHA256_DATA}"),
+        format!("\\{SHA256_DATA}  a\\b"),
+    ];
+
+    for line in invalid {
+        let text = format!("{SHA256_DATA}  ok\n{line}\n");
+        let error = checksums::parse(&text, None).unwrap_err();
+        assert_eq!(error.line, 2, "{line}");
+        assert_eq!(error.to_string(), "improperly formatted checksum line 2");
+    }
+}
+
+#[test]
+fn write_round_trips() {
+    let entries = [
+        entry(Algorithm::SHA256, SHA256_DATA, "data.txt", false),
+        entry(Algorithm::MD5, MD5_DATA, "data.bin", true),
+        entry(Algorithm::SHA256, SHA256_DATA, "a\\b\nc", false),
+    ];
+
+    let mut gnu = Vec::new();
+    checksums::write(&mut gnu, &entries, Format::GNU).unwrap();
+    assert_eq!(
+        String::from_utf8(gnu.clone()).unwrap(),
+        format!("{SHA256_DATA}  data.txt\n{MD5_DATA} *data.bin\n\\{SHA256_DATA}  a\\\\b\\nc\n")
+    );
+    assert_eq!(
+        checksums::parse(std::str::from_utf8(&gnu).unwrap(), None).unwrap(),
+        entries
+    );
+
+    let mut bsd = Vec::new();
+    checksums::write(&mut bsd, &entries, Format::BSD).unwrap();
+    assert_eq!(
+        String::from_utf8(bsd.clone()).unwrap(),
+        format!("SHA256 (data.txt) = {SHA256_DATA}\nMD5 (data.bin) = {MD5_DATA}\n\\SHA256 (a\\\\b\\nc) = {SHA256_DATA}\n")
+    );
+
+    let parsed = checksums::parse(std::str::from_utf8(&bsd).unwrap(), None).unwrap();
+    assert_eq!(parsed.len(), 3);
+    assert!(parsed
+        .iter()
+        .zip(&entries)
+        .all(|(p, e)| p.algorithm == e.algorithm && p.digest == e.digest && p.path == e.path));
+}
+
+#[test]
+fn generate_and_verify() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("checksums");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a"), b"data").unwrap();
+    std::fs::write(dir.join("b"), b"more data").unwrap();
+    std::fs::write(dir.join("c"), b"even more data").unwrap();
+
+    let paths = [dir.join("a"), dir.join("b"), dir.join("c")];
+    let entries = checksums::generate(&paths, Algorithm::SHA256).unwrap();
+    assert_eq!(entries[0].digest, Hash::sha256(b"data"));
+    assert_eq!(entries[2].path, paths[2]);
+
+    assert!(checksums::generate(&[dir.join("missing")], Algorithm::SHA256).is_err());
+
+    std::fs::write(dir.join("b"), b"tampered").unwrap();
+    std::fs::remove_file(dir.join("c")).unwrap();
+
+    // Relative to the base directory, with a BSD entry using another algorithm.
+    let text = format!(
+        "{SHA256_DATA}  a\n{}  b\n{}  c\nMD5 (a) = {MD5_DATA}\n",
+        hex::encode(&entries[1].digest),
+        hex::encode(&entries[2].digest),
+    );
+    let entries = checksums::parse(&text, None).unwrap();
+    let statuses = checksums::verify(&entries, &dir);
+
+    assert!(matches!(
+        statuses[..],
+        [Status::Ok, Status::Failed, Status::Missing, Status::Ok]
+    ));
+    assert_eq!(
+        statuses.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        ["OK", "FAILED", "FAILED open or read: missing", "OK"]
+    );
+}
@@ -0,0 +1,128 @@
+use common_crypto::hash::Hash;
+use common_crypto::hmac::HMAC;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+const IV: &str = "0f0e0d0c0b0a09080706050403020100";
+
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_common-crypto"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(args: &[&str], stdin: &[u8]) -> Vec<u8> {
+    let output = run(args, stdin);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output.stdout
+}
+
+#[test]
+fn hash() {
+    let sha256 = hex::encode(Hash::sha256(b"data"));
+    assert_eq!(
+        stdout(&["hash", "--alg", "sha256"], b"data"),
+        format!("{sha256}  -\n").as_bytes()
+    );
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("cli");
+    std::fs::create_dir_all(&dir).unwrap();
+    let a = dir.join("a");
+    let b = dir.join("b");
+    std::fs::write(&a, b"data").unwrap();
+    std::fs::write(&b, b"more data").unwrap();
+    let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+
+    assert_eq!(
+        String::from_utf8(stdout(&["hash", "--alg", "MD5", a, b], b"")).unwrap(),
+        format!(
+            "{}  {a}\n{}  {b}\n",
+            hex::encode(Hash::md5(b"data")),
+            hex::encode(Hash::md5(b"more data"))
+        )
+    );
+
+    assert_eq!(
+        stdout(&["hash", "--alg", "sha1", "--base64"], b""),
+        b"2jmj7l5rSw0yVb/vlWAYkK/YBwk=  -\n"
+    );
+}
+
+#[test]
+fn hmac() {
+    let expected = hex::encode(HMAC::sha512(b"key", b"data"));
+    assert_eq!(
+        stdout(&["hmac", "--alg", "sha512", "--key-hex", "6b6579"], b"data"),
+        format!("{expected}\n").as_bytes()
+    );
+    assert_eq!(
+        stdout(&["hmac", "--alg", "sha512", "--key", "key", "-"], b"data"),
+        format!("{expected}\n").as_bytes()
+    );
+}
+
+#[test]
+fn encrypt_and_decrypt() {
+    let plaintext = vec![0x5a; 100_000];
+    let args = ["--cipher", "aes-256-cbc", "--key-hex", KEY, "--iv-hex", IV];
+
+    let ciphertext = stdout(&[&["enc"], &args[..]].concat(), &plaintext);
+    assert_eq!(
+        ciphertext.len(),
+        plaintext.len() + 16 - plaintext.len() % 16
+    );
+    assert_eq!(
+        stdout(&[&["dec"], &args[..]].concat(), &ciphertext),
+        plaintext
+    );
+
+    let base64 = stdout(&[&["enc", "--base64"], &args[..]].concat(), &plaintext);
+    assert!(base64.ends_with(b"\n"));
+    assert_eq!(
+        stdout(&[&["dec", "--base64"], &args[..]].concat(), &base64),
+        plaintext
+    );
+}
+
+#[test]
+fn errors() {
+    let cases: [&[&str]; 7] = [
+        &[],
+        &["frobnicate"],
+        &["hash"],
+        &["hash", "--alg", "sha3"],
+        &["hmac", "--alg", "md2", "--key", "key"],
+        &["enc", "--cipher", "aes-256-cbc", "--key-hex", "abc"],
+        &["hash", "--alg", "sha256", "--unknown"],
+    ];
+
+    for args in cases {
+        let output = run(args, b"");
+        assert!(!output.status.success(), "{args:?}");
+        assert!(output.stderr.starts_with(b"common-crypto: "), "{args:?}");
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains("Usage:"),
+            "{args:?}"
+        );
+    }
+
+    let output = run(&["hash", "--alg", "sha256", "does not exist"], b"");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("common-crypto: does not exist: "));
+
+    let output = run(&["enc", "--cipher", "aes-256-cbc", "--key-hex", "00"], b"");
+    assert_eq!(output.stderr, b"common-crypto: key size is invalid\n");
+}
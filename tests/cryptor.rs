@@ -2,7 +2,7 @@ use common_crypto::cryptor::*;
 
 #[test]
 fn rc4_ecb_encrypt() {
-    let config = Config::RC4 { key: b"Key" };
+    let config = Config::rc4(b"Key");
 
     assert_eq!(
         Cryptor::encrypt(&config, b"Plaintext").unwrap(),
@@ -12,11 +12,7 @@ fn rc4_ecb_encrypt() {
 
 #[test]
 fn aes256_round_trip() {
-    let config = Config::AES256 {
-        mode: Mode::CTR,
-        iv: Some(b"use random iv :)"),
-        key: b"0123456789abcdef0123456789abcdef",
-    };
+    let config = Config::aes256(b"0123456789abcdef0123456789abcdef").ctr(b"use random iv :)");
 
     let encrypted = Cryptor::encrypt(&config, b"Hello").unwrap();
     assert!(!encrypted.is_empty());
@@ -25,35 +21,85 @@ fn aes256_round_trip() {
 }
 
 #[test]
-fn iv_with_ecb_is_error() {
-    let config = Config::AES256 {
-        mode: Mode::ECB,
-        iv: Some(b"use random iv :)"),
-        key: b"0123456789abcdef0123456789abcdef",
-    };
+fn padding() {
+    let config = Config::aes256(b"0123456789abcdef0123456789abcdef")
+        .cbc(b"use random iv :)", Padding::PKCS7);
 
-    assert_eq!(
-        Cryptor::encrypt(&config, b"Hello").unwrap_err(),
-        CryptorError::InitializationVectorPresent
-    );
+    let encrypted = Cryptor::encrypt(&config, b"Hello").unwrap();
+    assert_eq!(encrypted.len(), 16);
+    let decrypted = Cryptor::decrypt(&config, encrypted).unwrap();
+    assert_eq!(decrypted, b"Hello");
 }
 
 #[test]
 fn iv_is_used() {
-    let config = Config::AES256 {
-        mode: Mode::CTR,
-        iv: Some(b"use random iv :)"),
-        key: b"0123456789abcdef0123456789abcdef",
-    };
+    let config = Config::aes256(b"0123456789abcdef0123456789abcdef").ctr(b"use random iv :)");
 
     let encrypted = Cryptor::encrypt(&config, b"Hello").unwrap();
     assert!(!encrypted.is_empty());
 
-    let new_config = Config::AES256 {
-        mode: Mode::CTR,
-        iv: Some(b"very  random  iv"),
-        key: b"0123456789abcdef0123456789abcdef",
-    };
+    let new_config = Config::aes256(b"0123456789abcdef0123456789abcdef").ctr(b"very  random  iv");
 
     assert_ne!(Cryptor::encrypt(&new_config, b"Hello").unwrap(), encrypted);
 }
+
+#[test]
+fn tdes_iv_is_used() {
+    let config = Config::tdes(b"0123456789abcdef01234567").cbc(b"iv bytes", Padding::None);
+    let new_config = Config::tdes(b"0123456789abcdef01234567").cbc(b"IV BYTES", Padding::None);
+
+    assert_ne!(
+        Cryptor::encrypt(&config, b"sixteen byte msg").unwrap(),
+        Cryptor::encrypt(&new_config, b"sixteen byte msg").unwrap()
+    );
+}
+
+#[test]
+fn invalid_key_size() {
+    let config = Config::blowfish(b"short").ecb(Padding::None);
+
+    assert_eq!(
+        Cryptor::encrypt(&config, b"Hello").unwrap_err(),
+        CryptorError::KeySize
+    );
+}
+
+#[test]
+fn compile_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
+
+#[test]
+fn padding_is_finished() {
+    // CAST5-CBC from Python's cryptography, with PKCS #7 padding.
+    let config = Config::cast(b"0123456789abcdef").cbc(b"iv bytes", Padding::PKCS7);
+    let expected = [0x5c, 0x74, 0x65, 0x93, 0x5d, 0xdb, 0x03, 0x56];
+
+    let encrypted = Cryptor::encrypt(&config, b"Hello").unwrap();
+    assert_eq!(encrypted, expected);
+    assert_eq!(Cryptor::decrypt(&config, encrypted).unwrap(), b"Hello");
+
+    // The padded block is only written by finish, into an empty buffer.
+    let cryptor = Cryptor::new_encryptor(&config).unwrap();
+    let mut output = Vec::new();
+    cryptor.update(b"Hello", &mut output).unwrap();
+    assert!(output.is_empty());
+    cryptor.finish(&mut output).unwrap();
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn tdes_cbc() {
+    // openssl enc -des-ede3-cbc -nopad \
+    //     -K 303132333435363738396162636465663031323334353637 -iv 6976206279746573
+    let config = Config::tdes(b"0123456789abcdef01234567").cbc(b"iv bytes", Padding::None);
+
+    assert_eq!(
+        Cryptor::encrypt(&config, b"sixteen byte msg").unwrap(),
+        [
+            0x7a, 0xcd, 0x32, 0x50, 0x81, 0xc9, 0x0e, 0xb2, 0x29, 0x99, 0x74, 0x6a, 0xf0, 0x6a,
+            0x29, 0x53
+        ]
+    );
+}
@@ -0,0 +1,151 @@
+use common_crypto::cryptor::{Config, Cryptor, CryptorError, Padding};
+use common_crypto::ctr::{CTRReader, Counter, Endianness, CTR};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+const KEY: &[u8; 32] = b"0123456789abcdef0123456789abcdef";
+// The low bytes are close to overflowing so that the carry is exercised.
+const IV: &[u8; 16] = b"\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a\xff\xff\xff\xff\xfe";
+
+fn config() -> Config<'static> {
+    Config::aes256(KEY).ctr(IV)
+}
+
+fn plaintext() -> Vec<u8> {
+    (0..1000u32).map(|i| (i * 7 % 251) as u8).collect()
+}
+
+#[test]
+fn matches_sequential_decryption() {
+    let encrypted = Cryptor::encrypt(&config(), plaintext()).unwrap();
+
+    let mut ctr = CTR::new(&config()).unwrap();
+    let mut decrypted = encrypted.clone();
+    ctr.apply_keystream(&mut decrypted[..5]).unwrap();
+    ctr.apply_keystream(&mut decrypted[5..37]).unwrap();
+    ctr.apply_keystream(&mut decrypted[37..]).unwrap();
+
+    assert_eq!(decrypted, plaintext());
+    assert_eq!(ctr.position(), 1000);
+}
+
+#[test]
+fn seek_to_any_offset() {
+    let plaintext = plaintext();
+    let encrypted = Cryptor::encrypt(&config(), &plaintext).unwrap();
+    let mut ctr = CTR::new(&config()).unwrap();
+
+    for offset in [0, 1, 15, 16, 17, 64, 100, 999] {
+        ctr.seek(offset as u64);
+        let mut decrypted = encrypted[offset..].to_vec();
+        ctr.apply_keystream(&mut decrypted).unwrap();
+        assert_eq!(decrypted, &plaintext[offset..], "offset {}", offset);
+    }
+}
+
+#[test]
+fn reader_seeks() {
+    let plaintext = plaintext();
+    let encrypted = Cryptor::encrypt(&config(), &plaintext).unwrap();
+    let mut reader = CTRReader::new(Cursor::new(encrypted), CTR::new(&config()).unwrap());
+
+    let mut buf = [0u8; 50];
+    reader.seek(SeekFrom::Start(123)).unwrap();
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf[..], &plaintext[123..173]);
+
+    reader.seek(SeekFrom::Current(-100)).unwrap();
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf[..], &plaintext[73..123]);
+
+    reader.seek(SeekFrom::End(-10)).unwrap();
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, &plaintext[990..]);
+}
+
+#[test]
+fn requires_ctr_mode() {
+    let config = Config::aes256(KEY).cbc(IV, Padding::None);
+
+    assert!(CTR::new(&config).is_err());
+}
+
+#[test]
+fn little_endian_counter() {
+    let iv = b"\xfe\xff\x00\x00nonce bytes!";
+    let config = Config::aes128(b"0123456789abcdef").ctr(iv);
+
+    let counter = Counter {
+        endianness: Endianness::Little,
+        bits: 32,
+    };
+
+    let mut keystream = [0u8; 48];
+    CTR::with_counter(&config, counter)
+        .unwrap()
+        .apply_keystream(&mut keystream)
+        .unwrap();
+
+    let ecb = Config::aes128(b"0123456789abcdef").ecb(Padding::None);
+
+    let blocks = [
+        &b"\xfe\xff\x00\x00nonce bytes!"[..],
+        b"\xff\xff\x00\x00nonce bytes!",
+        b"\x00\x00\x01\x00nonce bytes!",
+    ]
+    .concat();
+
+    assert_eq!(&keystream[..], Cryptor::encrypt(&ecb, blocks).unwrap());
+}
+
+#[test]
+fn narrow_counter_matches_full_width_until_it_wraps() {
+    let iv = b"96 bit nonce\xff\xff\xff\xfe";
+    let config = Config::aes256(KEY).ctr(iv);
+
+    let counter = Counter {
+        endianness: Endianness::Big,
+        bits: 32,
+    };
+
+    let mut ctr = CTR::with_counter(&config, counter).unwrap();
+    let mut data = [0u8; 32];
+    ctr.apply_keystream(&mut data).unwrap();
+    assert_eq!(&data[..], Cryptor::encrypt(&config, [0u8; 32]).unwrap());
+
+    assert_eq!(
+        ctr.apply_keystream(&mut [0u8; 1]).unwrap_err(),
+        CryptorError::Overflow
+    );
+
+    ctr.seek(31);
+    ctr.apply_keystream(&mut [0u8; 1]).unwrap();
+}
+
+#[test]
+fn full_width_counter_does_not_wrap() {
+    let config = Config::aes128(b"0123456789abcdef").ctr(&[0xff; 16]);
+
+    let mut ctr = CTR::new(&config).unwrap();
+    ctr.apply_keystream(&mut [0u8; 16]).unwrap();
+
+    assert_eq!(
+        ctr.apply_keystream(&mut [0u8; 1]).unwrap_err(),
+        CryptorError::Overflow
+    );
+}
+
+#[test]
+fn invalid_counter_width() {
+    for bits in [0, 12, 136] {
+        let counter = Counter {
+            endianness: Endianness::Big,
+            bits,
+        };
+
+        assert_eq!(
+            CTR::with_counter(&config(), counter).unwrap_err(),
+            CryptorError::Param
+        );
+    }
+}
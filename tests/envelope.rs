@@ -0,0 +1,372 @@
+use common_crypto::cryptor::{Config, Cryptor};
+use common_crypto::envelope::{
+    self, EnvelopeError, EnvelopeReader, EnvelopeWriter, Options, Secret, WorkFactor,
+};
+use common_crypto::hmac::HMAC;
+use common_crypto::kdf::{self, PRF};
+use std::io::{Read, Write};
+use std::time::Duration;
+
+const KEY: &[u8; 32] = b"0123456789abcdef0123456789abcdef";
+
+fn options(chunk_size: u32) -> Options {
+    Options {
+        chunk_size,
+        iterations: 1000,
+    }
+}
+
+fn data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+/// Decrypts an envelope as described by the module documentation, using only
+/// the underlying primitives.
+fn open_from_spec(secret: Secret<'_>, envelope: &[u8]) -> Vec<u8> {
+    let (header, rest) = envelope.split_at(48);
+    assert_eq!(&header[..8], [b'C', b'C', b'E', b'V', 1, 1, 1, header[7]]);
+
+    let chunk_size = u32::from_be_bytes(header[8..12].try_into().unwrap()) as usize;
+    let iterations = u32::from_be_bytes(header[12..16].try_into().unwrap());
+    let salt = &header[16..32];
+    let iv: &[u8; 16] = header[32..48].try_into().unwrap();
+
+    let master = match (header[7], secret) {
+        (0, Secret::Key(key)) => {
+            assert_eq!(iterations, 0);
+            key.to_vec()
+        }
+        (1, Secret::Password(password)) => {
+            kdf::pbkdf2(password, salt, PRF::SHA256, iterations, 32).unwrap()
+        }
+        _ => panic!("unexpected key derivation"),
+    };
+    let encryption_key = HMAC::sha256(&master, [&b"encryption"[..], salt].concat());
+    let mac_key = HMAC::sha256(&master, [&b"authentication"[..], salt].concat());
+
+    let (header_tag, mut rest) = rest.split_at(32);
+    assert_eq!(HMAC::sha256(mac_key, header), header_tag);
+
+    let mut ciphertext = Vec::new();
+    for index in 0u64.. {
+        let len = rest.len().min(chunk_size + 32);
+        let (chunk, tag) = rest[..len].split_at(len - 32);
+        let last = chunk.len() < chunk_size;
+
+        let message = [header_tag, &index.to_be_bytes(), &[last as u8], chunk].concat();
+        assert_eq!(HMAC::sha256(mac_key, message), tag);
+
+        ciphertext.extend_from_slice(chunk);
+        rest = &rest[len..];
+
+        if last {
+            assert!(rest.is_empty());
+            break;
+        }
+    }
+
+    Cryptor::decrypt(&Config::aes256(&encryption_key).ctr(iv), ciphertext).unwrap()
+}
+
+#[test]
+fn round_trip() {
+    for len in [0, 1, 31, 32, 33, 64, 100, 1000] {
+        let data = data(len);
+        let sealed = envelope::seal_with_options(Secret::Key(KEY), &options(32), &data).unwrap();
+
+        // Every chunk is full apart from the last, which may be empty.
+        assert_eq!(sealed.len(), 80 + len + (len / 32 + 1) * 32, "{len}");
+        assert_eq!(envelope::open(Secret::Key(KEY), &sealed).unwrap(), data);
+        assert_eq!(open_from_spec(Secret::Key(KEY), &sealed), data);
+    }
+}
+
+#[test]
+fn password() {
+    let data = data(100);
+    let secret = Secret::Password(b"correct horse battery staple");
+    let sealed = envelope::seal_with_options(secret, &options(48), &data).unwrap();
+
+    assert_eq!(sealed[7], 1);
+    assert_eq!(sealed[8..16], [0, 0, 0, 48, 0, 0, 0x03, 0xe8]);
+    assert_eq!(envelope::open(secret, &sealed).unwrap(), data);
+    assert_eq!(open_from_spec(secret, &sealed), data);
+
+    assert!(matches!(
+        envelope::open(Secret::Password(b"wrong"), &sealed),
+        Err(EnvelopeError::Authentication)
+    ));
+    assert!(matches!(
+        envelope::open(Secret::Key(KEY), &sealed),
+        Err(EnvelopeError::Param)
+    ));
+}
+
+#[test]
+fn default_options() {
+    let data = data(200_000);
+    let sealed = envelope::seal(Secret::Key(KEY), &data).unwrap();
+
+    assert_eq!(sealed[8..16], [0, 1, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(envelope::open(Secret::Key(KEY), &sealed).unwrap(), data);
+}
+
+#[test]
+fn randomised() {
+    let a = envelope::seal(Secret::Key(KEY), b"data").unwrap();
+    let b = envelope::seal(Secret::Key(KEY), b"data").unwrap();
+
+    assert_eq!(a.len(), b.len());
+    assert_ne!(a[16..48], b[16..48]);
+    assert_ne!(a[80..], b[80..]);
+}
+
+#[test]
+fn streaming() {
+    let data = data(10_000);
+    let mut writer = EnvelopeWriter::new(Vec::new(), Secret::Key(KEY), &options(512)).unwrap();
+    for chunk in data.chunks(100) {
+        writer.write_all(chunk).unwrap();
+    }
+    let sealed = writer.finish().unwrap();
+
+    let mut reader = EnvelopeReader::new(&sealed[..], Secret::Key(KEY)).unwrap();
+    let mut opened = Vec::new();
+    let mut buf = [0u8; 333];
+    loop {
+        let read = reader.read(&mut buf).unwrap();
+        if read == 0 {
+            break;
+        }
+        opened.extend_from_slice(&buf[..read]);
+    }
+
+    assert_eq!(opened, data);
+}
+
+#[test]
+fn streaming_stops_at_tampering() {
+    let data = data(100);
+    let mut sealed = envelope::seal_with_options(Secret::Key(KEY), &options(32), &data).unwrap();
+    // The third chunk's ciphertext.
+    sealed[80 + 2 * 64] ^= 1;
+
+    let mut reader = EnvelopeReader::new(&sealed[..], Secret::Key(KEY)).unwrap();
+    let mut buf = [0u8; 64];
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(buf[..], data[..64]);
+
+    let error = reader.read(&mut buf).unwrap_err();
+    assert!(matches!(
+        EnvelopeError::from(error),
+        EnvelopeError::Authentication
+    ));
+}
+
+#[test]
+fn any_modification_is_detected() {
+    let sealed = envelope::seal_with_options(Secret::Key(KEY), &options(16), data(40)).unwrap();
+
+    for i in 0..sealed.len() {
+        for bit in [0x01, 0x80] {
+            let mut modified = sealed.clone();
+            modified[i] ^= bit;
+            assert!(envelope::open(Secret::Key(KEY), &modified).is_err(), "{i}");
+        }
+    }
+}
+
+#[test]
+fn truncation_is_detected() {
+    let sealed = envelope::seal_with_options(Secret::Key(KEY), &options(16), data(40)).unwrap();
+
+    for len in 0..sealed.len() {
+        assert!(
+            envelope::open(Secret::Key(KEY), &sealed[..len]).is_err(),
+            "{len}"
+        );
+    }
+
+    // Removing whole chunks.
+    for len in [80, 128, 176] {
+        assert!(matches!(
+            envelope::open(Secret::Key(KEY), &sealed[..len]),
+            Err(EnvelopeError::Truncated)
+        ));
+    }
+
+    assert!(matches!(
+        envelope::open(Secret::Key(KEY), &sealed[..40]),
+        Err(EnvelopeError::Format)
+    ));
+}
+
+#[test]
+fn extension_is_detected() {
+    let sealed = envelope::seal_with_options(Secret::Key(KEY), &options(16), data(40)).unwrap();
+
+    for extra in [&b"\0"[..], &[0; 48], &sealed[80..128]] {
+        let extended = [&sealed[..], extra].concat();
+        assert!(envelope::open(Secret::Key(KEY), &extended).is_err());
+    }
+}
+
+#[test]
+fn reordering_is_detected() {
+    let sealed = envelope::seal_with_options(Secret::Key(KEY), &options(16), data(40)).unwrap();
+    let (header, chunks) = sealed.split_at(80);
+    let chunks: Vec<&[u8]> = chunks.chunks(48).collect();
+
+    let reordered = [header, chunks[1], chunks[0], chunks[2]].concat();
+    assert!(matches!(
+        envelope::open(Secret::Key(KEY), &reordered),
+        Err(EnvelopeError::Authentication)
+    ));
+
+    // Chunks can't be moved between envelopes with the same key.
+    let other = envelope::seal_with_options(Secret::Key(KEY), &options(16), data(40)).unwrap();
+    let spliced = [header, &other[80..128], chunks[1], chunks[2]].concat();
+    assert!(matches!(
+        envelope::open(Secret::Key(KEY), &spliced),
+        Err(EnvelopeError::Authentication)
+    ));
+}
+
+#[test]
+fn header_errors() {
+    let sealed = envelope::seal(Secret::Key(KEY), b"data").unwrap();
+
+    let mut modified = sealed.clone();
+    modified[0] = b'X';
+    assert!(matches!(
+        envelope::open(Secret::Key(KEY), &modified),
+        Err(EnvelopeError::Format)
+    ));
+
+    let mut modified = sealed.clone();
+    modified[4] = 2;
+    assert!(matches!(
+        envelope::open(Secret::Key(KEY), &modified),
+        Err(EnvelopeError::Version(2))
+    ));
+
+    for i in [5, 6] {
+        let mut modified = sealed.clone();
+        modified[i] = 2;
+        assert!(matches!(
+            envelope::open(Secret::Key(KEY), &modified),
+            Err(EnvelopeError::Unsupported)
+        ));
+    }
+
+    let mut modified = sealed.clone();
+    modified[8..12].copy_from_slice(&17u32.to_be_bytes());
+    assert!(matches!(
+        envelope::open(Secret::Key(KEY), &modified),
+        Err(EnvelopeError::Format)
+    ));
+
+    assert!(matches!(
+        envelope::open(Secret::Key(&[0; 32]), &sealed),
+        Err(EnvelopeError::Authentication)
+    ));
+}
+
+#[test]
+fn invalid_options() {
+    for chunk_size in [0, 17, 32 * 1024 * 1024] {
+        assert!(matches!(
+            envelope::seal_with_options(Secret::Key(KEY), &options(chunk_size), b"data"),
+            Err(EnvelopeError::Param)
+        ));
+    }
+
+    for iterations in [0, envelope::MAX_ITERATIONS + 1] {
+        let options = Options {
+            chunk_size: 16,
+            iterations,
+        };
+        assert!(matches!(
+            envelope::seal_with_options(Secret::Password(b"password"), &options, b"data"),
+            Err(EnvelopeError::Param)
+        ));
+    }
+}
+
+#[test]
+fn too_many_iterations() {
+    let options = Options {
+        chunk_size: 16,
+        iterations: 1,
+    };
+    let mut sealed =
+        envelope::seal_with_options(Secret::Password(b"password"), &options, b"data").unwrap();
+
+    // Rejected before running PBKDF2, rather than after hours of it.
+    sealed[12..16].copy_from_slice(&u32::MAX.to_be_bytes());
+    assert!(matches!(
+        envelope::open(Secret::Password(b"password"), &sealed),
+        Err(EnvelopeError::Format)
+    ));
+}
+
+#[test]
+fn password_helpers() {
+    let sealed = envelope::seal_with_password("hunter2", b"secret").unwrap();
+    assert_eq!(sealed[7], 1);
+    assert_eq!(
+        u32::from_be_bytes(sealed[12..16].try_into().unwrap()),
+        600_000
+    );
+    assert_eq!(
+        envelope::open_with_password("hunter2", &sealed).unwrap(),
+        b"secret"
+    );
+    assert!(matches!(
+        envelope::open_with_password("hunter3", &sealed),
+        Err(EnvelopeError::Authentication)
+    ));
+
+    // Keys aren't accepted as passwords.
+    let sealed = envelope::seal(Secret::Key(KEY), b"secret").unwrap();
+    assert!(matches!(
+        envelope::open_with_password(KEY, &sealed),
+        Err(EnvelopeError::Param)
+    ));
+}
+
+#[test]
+fn work_factor() {
+    let sealed = envelope::seal_with_password_and_work_factor(
+        "hunter2",
+        b"secret",
+        WorkFactor::Iterations(1234),
+    )
+    .unwrap();
+    assert_eq!(u32::from_be_bytes(sealed[12..16].try_into().unwrap()), 1234);
+    assert_eq!(
+        envelope::open_with_password("hunter2", &sealed).unwrap(),
+        b"secret"
+    );
+
+    let sealed = envelope::seal_with_password_and_work_factor(
+        "hunter2",
+        b"secret",
+        WorkFactor::Duration(Duration::from_millis(50)),
+    )
+    .unwrap();
+    assert!(u32::from_be_bytes(sealed[12..16].try_into().unwrap()) > 0);
+    assert_eq!(
+        envelope::open_with_password("hunter2", &sealed).unwrap(),
+        b"secret"
+    );
+
+    assert!(matches!(
+        envelope::seal_with_password_and_work_factor(
+            "hunter2",
+            b"secret",
+            WorkFactor::Iterations(0)
+        ),
+        Err(EnvelopeError::Param)
+    ));
+}
@@ -1,4 +1,7 @@
-use common_crypto::hash::Hash;
+use common_crypto::encoding::hex_encode;
+use common_crypto::hash::{self, Algorithm, Hash, Hasher};
+use std::io::{self, Cursor, Write};
+use std::path::PathBuf;
 
 #[test]
 fn sha1() {
@@ -63,3 +66,143 @@ fn md5() {
         hex::decode("8d777f385d3dfec8815d20f7496026dc").unwrap()
     );
 }
+
+macro_rules! implement_clone_test {
+    ($test_name:ident, $func:ident, $struct:ident) => {
+        #[test]
+        fn $test_name() {
+            let prefix = [0x5a; 1000];
+            let mut hasher = hash::$struct::new();
+            hasher.update(prefix);
+
+            for suffix in [&b"a"[..], b"b", b"a longer suffix"] {
+                let mut clone = hasher.clone();
+                clone.update(suffix);
+                assert_eq!(clone.finish(), Hash::$func([&prefix[..], suffix].concat()));
+            }
+
+            assert_eq!(hasher.finish(), Hash::$func(prefix));
+        }
+    };
+}
+
+implement_clone_test!(clone_sha1, sha1, SHA1);
+implement_clone_test!(clone_sha224, sha224, SHA224);
+implement_clone_test!(clone_sha256, sha256, SHA256);
+implement_clone_test!(clone_sha384, sha384, SHA384);
+implement_clone_test!(clone_sha512, sha512, SHA512);
+implement_clone_test!(clone_md2, md2, MD2);
+implement_clone_test!(clone_md4, md4, MD4);
+implement_clone_test!(clone_md5, md5, MD5);
+
+// Allocates over 4 GiB, so only runs with `cargo test -- --ignored`.
+#[test]
+#[ignore]
+fn larger_than_chunk() {
+    // Past both the internal chunk size and the range of a 32-bit length.
+    // python3 -c 'import hashlib; print(hashlib.sha256(bytes(2**32 + 3)).hexdigest())'
+    let expected = "930fa067940ff8d9f427e3a116b7598503c70ce7380d66ff65f8de33d558f7f3";
+    let data = vec![0; (1 << 32) + 3];
+
+    assert_eq!(hex_encode(&Hash::sha256(&data)), expected);
+
+    let mut hasher = hash::SHA256::new();
+    for chunk in data.chunks(1 << 20) {
+        hasher.update(chunk);
+    }
+
+    assert_eq!(hex_encode(&hasher.finish()), expected);
+}
+
+fn test_file(name: &str, data: &[u8]) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::write(&path, data).unwrap();
+    path
+}
+
+#[test]
+fn reader() {
+    // Spans several reads of the internal buffer.
+    let data: Vec<u8> = (0..1_000_000u32).map(|i| i as u8).collect();
+
+    assert_eq!(
+        Hash::sha256_reader(Cursor::new(&data)).unwrap(),
+        Hash::sha256(&data)
+    );
+    assert_eq!(Hash::md5_reader(&data[..]).unwrap(), Hash::md5(&data));
+    assert_eq!(Hash::sha1_reader(io::empty()).unwrap(), Hash::sha1([]));
+}
+
+#[test]
+fn write() {
+    let mut hasher = hash::SHA512::new();
+    write!(hasher, "da").unwrap();
+    io::copy(&mut &b"ta"[..], &mut hasher).unwrap();
+    assert_eq!(hasher.finish(), Hash::sha512(b"data"));
+}
+
+#[test]
+fn hasher() {
+    let algorithms = [
+        (Algorithm::MD2, Hash::md2(b"data").to_vec()),
+        (Algorithm::MD4, Hash::md4(b"data").to_vec()),
+        (Algorithm::MD5, Hash::md5(b"data").to_vec()),
+        (Algorithm::SHA1, Hash::sha1(b"data").to_vec()),
+        (Algorithm::SHA224, Hash::sha224(b"data").to_vec()),
+        (Algorithm::SHA256, Hash::sha256(b"data").to_vec()),
+        (Algorithm::SHA384, Hash::sha384(b"data").to_vec()),
+        (Algorithm::SHA512, Hash::sha512(b"data").to_vec()),
+    ];
+
+    for (algorithm, expected) in algorithms {
+        let mut hasher = Hasher::new(algorithm);
+        assert_eq!(hasher.algorithm(), algorithm);
+        hasher.update(b"da");
+        hasher.write_all(b"ta").unwrap();
+
+        let digest = hasher.finish();
+        assert_eq!(digest.len(), algorithm.digest_len());
+        assert_eq!(digest, expected);
+    }
+}
+
+#[test]
+fn hash_file() {
+    let path = test_file("hash_file", b"data");
+    assert_eq!(
+        hash::hash_file(&path, Algorithm::SHA256).unwrap(),
+        Hash::sha256(b"data")
+    );
+
+    // Spans several reads of the internal buffer.
+    let data = vec![0x5a; 5 * 1024 * 1024 + 1];
+    let path = test_file("hash_file_large", &data);
+    assert_eq!(
+        hash::hash_file(&path, Algorithm::SHA384).unwrap(),
+        Hash::sha384(&data)
+    );
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn hash_file_mapped() {
+    let path = test_file("hash_file_mapped", b"data");
+    assert_eq!(
+        unsafe { hash::hash_file_mapped(&path, Algorithm::SHA256) }.unwrap(),
+        Hash::sha256(b"data")
+    );
+
+    // Large enough to be memory mapped.
+    let data = vec![0xa5; 5 * 1024 * 1024 + 1];
+    let path = test_file("hash_file_mapped_large", &data);
+    assert_eq!(
+        unsafe { hash::hash_file_mapped(&path, Algorithm::SHA384) }.unwrap(),
+        Hash::sha384(&data)
+    );
+}
+
+#[test]
+fn hash_missing_file() {
+    let error = hash::hash_file("does not exist", Algorithm::MD5).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+}
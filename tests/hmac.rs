@@ -11,9 +11,18 @@ macro_rules! implement_test {
 
             let mut hmac = $algorithm::new(b"123");
             hmac.update(b"a");
+            let snapshot = hmac.clone();
             hmac.update(b"b");
             hmac.update(b"c");
             assert_eq!(&hmac.finish()[..], &hex::decode($result).unwrap());
+
+            let mut hmac = snapshot.clone();
+            hmac.update(b"bc");
+            assert_eq!(&hmac.finish()[..], &hex::decode($result).unwrap());
+
+            let mut hmac = snapshot;
+            hmac.update(b"bd");
+            assert_eq!(hmac.finish(), HMAC::$func_name(b"123", b"abd"));
         }
     };
 }
@@ -45,3 +54,11 @@ implement_test!(
     SHA512,
     "58585acd673067f96bea32a1c57bf3fc3fd5a42678567e72d5cb0ab7f08ea41dcf3a41af96c53948e13184ae6fe6cd0b8b4193fc593dfb2693b00c2b0ee7a316"
 );
+
+#[test]
+fn constant_time_eq() {
+    assert!(common_crypto::hmac::constant_time_eq(b"", b""));
+    assert!(common_crypto::hmac::constant_time_eq(b"abc", b"abc"));
+    assert!(!common_crypto::hmac::constant_time_eq(b"abc", b"abd"));
+    assert!(!common_crypto::hmac::constant_time_eq(b"abc", b"ab"));
+}
@@ -0,0 +1,235 @@
+use common_crypto::cryptor::CryptorError;
+use common_crypto::encoding::{base64url_decode, base64url_encode, hex_decode, hex_encode};
+use common_crypto::jwe::{self, ContentEncryption, Header, JWEError, KeyManagement};
+
+// RFC 7516 appendix A.3, A128KW and A128CBC-HS256.
+const TOKEN: &str = "\
+eyJhbGciOiJBMTI4S1ciLCJlbmMiOiJBMTI4Q0JDLUhTMjU2In0.\
+6KB707dM9YTIgHtLvtgWQ8mKwboJW3of9locizkDTHzBC2IlrT1oOQ.\
+AxY8DCtDaGlsbGljb3RoZQ.\
+KDlTtXchhZTGufMYmOYGS4HffxPSUrfmqCHXaI9wOGY.\
+U0m_YmjN04DJvceFICbCVQ";
+const KEK: &str = "GawgguFyGrWKav7AX4VKUg";
+const CEK: [u8; 32] = [
+    4, 211, 31, 197, 84, 157, 252, 254, 11, 100, 157, 250, 63, 170, 106, 206, 107, 124, 212, 45,
+    111, 107, 9, 219, 200, 177, 0, 240, 143, 156, 44, 207,
+];
+const PLAINTEXT: &[u8] = b"Live long and prosper.";
+
+#[test]
+fn rfc_7516_decrypt() {
+    let kek = base64url_decode(KEK).unwrap();
+
+    let header = Header::from_token(TOKEN).unwrap();
+    assert_eq!(header.alg, KeyManagement::A128KW);
+    assert_eq!(header.enc, ContentEncryption::A128CBCHS256);
+    assert_eq!(header.kid, None);
+
+    assert_eq!(jwe::decrypt(&kek, TOKEN).unwrap(), PLAINTEXT);
+}
+
+#[test]
+fn rfc_7516_encrypt() {
+    let kek = base64url_decode(KEK).unwrap();
+    let parts: Vec<&str> = TOKEN.split('.').collect();
+    let iv: [u8; 16] = base64url_decode(parts[2]).unwrap().try_into().unwrap();
+
+    let encrypted_key = KeyManagement::A128KW.wrap(&kek, &CEK).unwrap();
+    assert_eq!(base64url_encode(&encrypted_key), parts[1]);
+
+    let (ciphertext, tag) = ContentEncryption::A128CBCHS256
+        .encrypt(&CEK, &iv, parts[0].as_bytes(), PLAINTEXT)
+        .unwrap();
+    assert_eq!(base64url_encode(&ciphertext), parts[3]);
+    assert_eq!(base64url_encode(&tag), parts[4]);
+}
+
+#[test]
+fn rfc_7518_content_encryption() {
+    // Appendix B, with the keys being 0, 1, 2 and so on.
+    let plaintext = b"A cipher system must not be required to be secret, and it must be able \
+to fall into the hands of the enemy without inconvenience";
+    let aad = b"The second principle of Auguste Kerckhoffs";
+    let iv = hex_decode("1af38c2dc2b96ffdd86694092341bc04").unwrap();
+    let iv: [u8; 16] = iv.try_into().unwrap();
+
+    let vectors = [
+        (
+            ContentEncryption::A128CBCHS256,
+            "c80edfa32ddf39d5ef00c0b468834279a2e46a1b8049f792f76bfe54b903a9c9\
+a94ac9b47ad2655c5f10f9aef71427e2fc6f9b3f399a221489f16362c7032336\
+09d45ac69864e3321cf82935ac4096c86e133314c54019e8ca7980dfa4b9cf1b\
+384c486f3a54c51078158ee5d79de59fbd34d848b3d69550a67646344427ade5\
+4b8851ffb598f7f80074b9473c82e2db",
+            "652c3fa36b0a7c5b3219fab3a30bc1c4",
+        ),
+        (
+            ContentEncryption::A192CBCHS384,
+            "ea65da6b59e61edb419be62d19712ae5d303eeb50052d0dfd6697f77224c8edb\
+000d279bdc14c1072654bd30944230c657bed4ca0c9f4a8466f22b226d174621\
+4bf8cfc2400add9f5126e479663fc90b3bed787a2f0ffcbf3904be2a641d5c21\
+05bfe591bae23b1d7449e532eef60a9ac8bb6c6b01d35d49787bcd57ef484927\
+f280adc91ac0c4e79c7b11efc60054e3",
+            "8490ac0e58949bfe51875d733f93ac2075168039ccc733d7",
+        ),
+        (
+            ContentEncryption::A256CBCHS512,
+            "4affaaadb78c31c5da4b1b590d10ffbd3dd8d5d302423526912da037ecbcc7bd\
+822c301dd67c373bccb584ad3e9279c2e6d12a1374b77f077553df829410446b\
+36ebd97066296ae6427ea75c2e0846a11a09ccf5370dc80bfecbad28c73f09b3\
+a3b75e662a2594410ae496b2e2e6609e31e6e02cc837f053d21f37ff4f51950b\
+be2638d09dd7a4930930806d0703b1f6",
+            "4dd3b4c088a7f45c216839645b2012bf2e6269a8c56a816dbc1b267761955bc5",
+        ),
+    ];
+
+    for (enc, ciphertext, tag) in vectors {
+        let key: Vec<u8> = (0..enc.key_len() as u8).collect();
+
+        let (encrypted, auth_tag) = enc.encrypt(&key, &iv, aad, plaintext).unwrap();
+        assert_eq!(hex_encode(&encrypted), ciphertext);
+        assert_eq!(hex_encode(&auth_tag), tag);
+
+        assert_eq!(
+            enc.decrypt(&key, &iv, aad, &encrypted, &auth_tag).unwrap(),
+            plaintext
+        );
+        assert_eq!(
+            enc.decrypt(&key, &iv, b"", &encrypted, &auth_tag),
+            Err(JWEError::Integrity)
+        );
+    }
+}
+
+#[test]
+fn round_trip() {
+    for (alg, enc) in [
+        (KeyManagement::A128KW, ContentEncryption::A128CBCHS256),
+        (KeyManagement::A192KW, ContentEncryption::A192CBCHS384),
+        (KeyManagement::A256KW, ContentEncryption::A256CBCHS512),
+        (KeyManagement::A128KW, ContentEncryption::A256CBCHS512),
+    ] {
+        let kek = vec![7; alg.key_len()];
+        let token = jwe::encrypt(alg, &kek, enc, PLAINTEXT).unwrap();
+
+        let header = Header::from_token(&token).unwrap();
+        assert_eq!((header.alg, header.enc), (alg, enc));
+        assert_eq!(jwe::decrypt(&kek, &token).unwrap(), PLAINTEXT);
+        assert_ne!(token, jwe::encrypt(alg, &kek, enc, PLAINTEXT).unwrap());
+    }
+
+    let header = Header {
+        alg: KeyManagement::A256KW,
+        enc: ContentEncryption::A256CBCHS512,
+        kid: Some("2024-\"01\"".to_string()),
+    };
+    let token = jwe::encrypt_with_header(&header, &[7; 32], b"").unwrap();
+    assert_eq!(Header::from_token(&token).unwrap(), header);
+    assert_eq!(jwe::decrypt(&[7; 32], &token).unwrap(), b"");
+}
+
+#[test]
+fn wrong_key() {
+    assert_eq!(jwe::decrypt(&[0; 16], TOKEN), Err(JWEError::Integrity));
+    assert_eq!(
+        jwe::decrypt(&[0; 32], TOKEN),
+        Err(JWEError::Cryptor(CryptorError::KeySize))
+    );
+}
+
+#[test]
+fn modified() {
+    let kek = base64url_decode(KEK).unwrap();
+    let parts: Vec<&str> = TOKEN.split('.').collect();
+
+    for i in 1..5 {
+        let mut data = base64url_decode(parts[i]).unwrap();
+        data[0] ^= 1;
+
+        let mut modified = parts.clone();
+        let encoded = base64url_encode(&data);
+        modified[i] = &encoded;
+
+        assert_eq!(
+            jwe::decrypt(&kek, &modified.join(".")),
+            Err(JWEError::Integrity)
+        );
+    }
+
+    // The header is authenticated as it was encoded.
+    let header = base64url_encode(br#"{"enc":"A128CBC-HS256","alg":"A128KW"}"#);
+    let modified = [header.as_str()]
+        .into_iter()
+        .chain(parts[1..].iter().copied())
+        .collect::<Vec<_>>()
+        .join(".");
+    assert_eq!(jwe::decrypt(&kek, &modified), Err(JWEError::Integrity));
+}
+
+#[test]
+fn invalid() {
+    let kek = base64url_decode(KEK).unwrap();
+    let rest = &TOKEN[TOKEN.find('.').unwrap()..];
+    let with_header = |json: &str| format!("{}{rest}", base64url_encode(json.as_bytes()));
+
+    for json in [
+        r#"{"alg":"A128KW"}"#,
+        r#"{"alg":"A128KW","enc":1}"#,
+        r#"{"alg":"A128KW","alg":"A128KW","enc":"A128CBC-HS256"}"#,
+        r#"{"alg":"A128KW","enc":"A128CBC-HS256""#,
+        r#"["A128KW"]"#,
+    ] {
+        assert_eq!(
+            jwe::decrypt(&kek, &with_header(json)),
+            Err(JWEError::Format)
+        );
+    }
+
+    for json in [
+        r#"{"alg":"dir","enc":"A128CBC-HS256"}"#,
+        r#"{"alg":"A128KW","enc":"A128GCM"}"#,
+        r#"{"alg":"A128KW","enc":"A128CBC-HS256","zip":"DEF"}"#,
+        r#"{"alg":"A128KW","enc":"A128CBC-HS256","crit":["exp"],"exp":1}"#,
+    ] {
+        assert_eq!(
+            jwe::decrypt(&kek, &with_header(json)),
+            Err(JWEError::Unsupported)
+        );
+    }
+
+    // Other members, including nested values and escapes, are allowed.
+    let json = r#" { "alg" : "A128KW", "enc":"A128CBC-HS256", "kid":"café",
+        "epk": {"x": [1, -2.5e3, true, null, {}], "y": "\"}"} } "#;
+    assert_eq!(
+        Header::from_token(&with_header(json)).unwrap().kid.unwrap(),
+        "café"
+    );
+
+    assert_eq!(jwe::decrypt(&kek, ""), Err(JWEError::Format));
+    assert_eq!(
+        jwe::decrypt(&kek, &TOKEN[..TOKEN.rfind('.').unwrap()]),
+        Err(JWEError::Format)
+    );
+    assert_eq!(
+        jwe::decrypt(&kek, &format!("{TOKEN}.")),
+        Err(JWEError::Format)
+    );
+    assert_eq!(
+        jwe::decrypt(&kek, &format!("{TOKEN}=")),
+        Err(JWEError::Format)
+    );
+
+    // An encrypted key which is too short to unwrap isn't an integrity
+    // failure.
+    let mut parts: Vec<&str> = TOKEN.split('.').collect();
+    let short = base64url_encode(&[0; 16]);
+    parts[1] = &short;
+    assert_eq!(
+        jwe::decrypt(&kek, &parts.join(".")),
+        Err(JWEError::Cryptor(CryptorError::Param))
+    );
+    assert_eq!(
+        KeyManagement::A128KW.unwrap(&kek, &[0; 16]),
+        Err(JWEError::Cryptor(CryptorError::Param))
+    );
+}
@@ -0,0 +1,175 @@
+use common_crypto::encoding::{base64url_decode, base64url_encode};
+use common_crypto::jws::{self, Algorithm, Header, JWSError};
+
+// RFC 7515 appendix A.1.
+const TOKEN: &str = "\
+eyJ0eXAiOiJKV1QiLA0KICJhbGciOiJIUzI1NiJ9.\
+eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFtcGxlLmNvbS9pc19yb290Ijp0cnVlfQ.\
+dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+const KEY: &str =
+    "AyM1SysPpbyDfgZld3umj1qzKObwVMkoqQ-EstJQLr_T-1qS0gZH75aKtMN3Yj0iPS4hcgUuTwjAzZr1Z9CAow";
+const PAYLOAD: &[u8] =
+    b"{\"iss\":\"joe\",\r\n \"exp\":1300819380,\r\n \"http://example.com/is_root\":true}";
+
+#[test]
+fn rfc_7515() {
+    let key = base64url_decode(KEY).unwrap();
+
+    let header = Header::from_token(TOKEN).unwrap();
+    assert_eq!(header.alg, Algorithm::HS256);
+    assert_eq!(header.typ.as_deref(), Some("JWT"));
+    assert_eq!(header.kid, None);
+
+    assert_eq!(jws::verify(Algorithm::HS256, &key, TOKEN).unwrap(), PAYLOAD);
+}
+
+#[test]
+fn sign() {
+    // Signed with Python's hmac module, with the keys being 0, 1, 2 and so
+    // on.
+    let vectors = [
+        (
+            Algorithm::HS384,
+            "eyJhbGciOiJIUzM4NCJ9.eyJzdWIiOiIxMjM0NTY3ODkwIn0.\
+BRuWVq_TDfr8DTK1wRtcKWkXeTY3p8LC35SOK75KbLtvAMg6e75uSN4ngW8Uvrpa",
+        ),
+        (
+            Algorithm::HS512,
+            "eyJhbGciOiJIUzUxMiJ9.eyJzdWIiOiIxMjM0NTY3ODkwIn0.\
+c6EeBbPrLU_ZQTfjML9bvyROKZ4ZW5Ro9XoD6axJit3LwrV7PqWK--DajfvcMxX0SMX4uZ9JMPuA1IfXhdyJDg",
+        ),
+    ];
+
+    for (alg, token) in vectors {
+        let key: Vec<u8> = (0..alg.signature_len() as u8).collect();
+        let payload = br#"{"sub":"1234567890"}"#;
+
+        assert_eq!(jws::sign(alg, &key, payload).unwrap(), token);
+        assert_eq!(jws::verify(alg, &key, token).unwrap(), payload);
+    }
+}
+
+#[test]
+fn round_trip() {
+    let header = Header {
+        alg: Algorithm::HS512,
+        typ: Some("JWT".to_string()),
+        kid: Some("2024-\"01\"".to_string()),
+    };
+    let key = [7; 64];
+
+    let token = jws::sign_with_header(&header, &key, b"").unwrap();
+    assert_eq!(Header::from_token(&token).unwrap(), header);
+    assert_eq!(jws::verify(Algorithm::HS512, &key, &token).unwrap(), b"");
+}
+
+#[test]
+fn wrong_key() {
+    let mut key = base64url_decode(KEY).unwrap();
+    key[0] ^= 1;
+
+    assert_eq!(
+        jws::verify(Algorithm::HS256, &key, TOKEN),
+        Err(JWSError::Signature)
+    );
+    assert_eq!(
+        jws::verify(Algorithm::HS256, &key[..31], TOKEN),
+        Err(JWSError::KeySize)
+    );
+    assert_eq!(
+        jws::sign(Algorithm::HS512, &[0; 63], b""),
+        Err(JWSError::KeySize)
+    );
+}
+
+#[test]
+fn wrong_algorithm() {
+    let key = base64url_decode(KEY).unwrap();
+
+    assert_eq!(
+        jws::verify(Algorithm::HS512, &key, TOKEN),
+        Err(JWSError::Algorithm)
+    );
+
+    // An unsecured token, reusing the payload of a signed one.
+    let parts: Vec<&str> = TOKEN.split('.').collect();
+    let header = base64url_encode(br#"{"alg":"none"}"#);
+    let token = format!("{header}.{}.", parts[1]);
+    assert_eq!(
+        jws::verify(Algorithm::HS256, &key, &token),
+        Err(JWSError::Algorithm)
+    );
+    assert_eq!(Header::from_token(&token), Err(JWSError::Algorithm));
+}
+
+#[test]
+fn modified() {
+    let key = base64url_decode(KEY).unwrap();
+    let parts: Vec<&str> = TOKEN.split('.').collect();
+
+    for i in 0..3 {
+        let mut data = base64url_decode(parts[i]).unwrap();
+        // Keep the header readable, changing the type to "JVT".
+        let index = if i == 0 { 9 } else { 0 };
+        data[index] ^= 1;
+
+        let mut modified = parts.clone();
+        let encoded = base64url_encode(&data);
+        modified[i] = &encoded;
+
+        assert_eq!(
+            jws::verify(Algorithm::HS256, &key, &modified.join(".")),
+            Err(JWSError::Signature)
+        );
+    }
+
+    // The last digit of the signature only carries two bits, so these
+    // decoded to the same signature before non-zero unused bits were
+    // rejected.
+    let prefix = &TOKEN[..TOKEN.len() - 1];
+    assert!(TOKEN.ends_with('k'));
+    for last in ['l', 'm', 'n'] {
+        assert_eq!(
+            jws::verify(Algorithm::HS256, &key, &format!("{prefix}{last}")),
+            Err(JWSError::Format)
+        );
+    }
+}
+
+#[test]
+fn invalid() {
+    let key = base64url_decode(KEY).unwrap();
+    let rest = &TOKEN[TOKEN.find('.').unwrap()..];
+    let with_header = |json: &str| format!("{}{rest}", base64url_encode(json.as_bytes()));
+
+    for json in [r#"{"typ":"JWT"}"#, r#"{"alg":256}"#, r#"{"alg":"HS256""#] {
+        assert_eq!(
+            jws::verify(Algorithm::HS256, &key, &with_header(json)),
+            Err(JWSError::Format)
+        );
+    }
+
+    assert_eq!(
+        jws::verify(
+            Algorithm::HS256,
+            &key,
+            &with_header(r#"{"alg":"HS256","b64":false,"crit":["b64"]}"#)
+        ),
+        Err(JWSError::Unsupported)
+    );
+
+    let parts: Vec<&str> = TOKEN.split('.').collect();
+    for token in [
+        "",
+        parts[0],
+        &parts[..2].join("."),
+        &format!("{TOKEN}."),
+        &format!("{TOKEN}="),
+        &format!("{}.{}+.{}", parts[0], parts[1], parts[2]),
+    ] {
+        assert_eq!(
+            jws::verify(Algorithm::HS256, &key, token),
+            Err(JWSError::Format)
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use common_crypto::cryptor::CryptorError;
+use common_crypto::kdf::{self, PRF};
+use std::time::Duration;
+
+#[test]
+fn pbkdf2() {
+    // RFC 6070.
+    assert_eq!(
+        hex::encode(kdf::pbkdf2(b"password", b"salt", PRF::SHA1, 4096, 20).unwrap()),
+        "4b007901b765489abead49d926f721d065a429c1"
+    );
+    assert_eq!(
+        hex::encode(
+            kdf::pbkdf2(
+                b"passwordPASSWORDpassword",
+                b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+                PRF::SHA1,
+                4096,
+                25
+            )
+            .unwrap()
+        ),
+        "3d2eec4fe41c849b80c8d83662c0e44a8b291a964cf2f07038"
+    );
+
+    // From RFC 7914 section 11.
+    assert_eq!(
+        hex::encode(kdf::pbkdf2(b"passwd", b"salt", PRF::SHA256, 1, 64).unwrap()),
+        "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc\
+         49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"
+    );
+}
+
+#[test]
+fn pbkdf2_without_rounds() {
+    assert_eq!(
+        kdf::pbkdf2(b"password", b"salt", PRF::SHA256, 0, 32),
+        Err(CryptorError::Param)
+    );
+}
+
+#[test]
+fn calibrate_pbkdf2() {
+    let rounds = kdf::calibrate_pbkdf2(8, 16, PRF::SHA256, 32, Duration::from_millis(10)).unwrap();
+    assert!(rounds > 0);
+    kdf::pbkdf2(b"password", [0; 16], PRF::SHA256, rounds, 32).unwrap();
+}
+
+#[test]
+fn calibrate_pbkdf2_invalid() {
+    let duration = Duration::from_millis(10);
+
+    for (salt_len, len) in [(16, 0), (0, 32), (129, 32)] {
+        assert_eq!(
+            kdf::calibrate_pbkdf2(8, salt_len, PRF::SHA256, len, duration),
+            Err(CryptorError::Param)
+        );
+    }
+}
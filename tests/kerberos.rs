@@ -0,0 +1,341 @@
+use common_crypto::kerberos::{self, EncryptionType, KerberosError, Key};
+
+const RAEBURN: &str = "ATHENA.MIT.EDUraeburn";
+
+#[test]
+fn n_fold() {
+    // RFC 3961 appendix A.1.
+    let tests: &[(&str, usize, &str)] = &[
+        ("012345", 8, "be072631276b1955"),
+        ("password", 7, "78a07b6caf85fa"),
+        ("Rough Consensus, and Running Code", 8, "bb6ed30870b7f0e0"),
+        ("password", 21, "59e4a8ca7c0385c3c37b3f6d2000247cb6e6bd5b3e"),
+        (
+            "MASSACHVSETTS INSTITVTE OF TECHNOLOGY",
+            24,
+            "db3b0d8f0b061e603282b308a50841229ad798fab9540c1b",
+        ),
+        ("Q", 21, "518a54a215a8452a518a54a215a8452a518a54a215"),
+        ("ba", 21, "fb25d531ae8974499f52fd92ea9857c4ba24cf297e"),
+        ("kerberos", 8, "6b65726265726f73"),
+        ("kerberos", 16, "6b65726265726f737b9b5b2b93132b93"),
+        ("kerberos", 21, "8372c236344e5f1550cd0747e15d62ca7a5a3bcea4"),
+        (
+            "kerberos",
+            32,
+            "6b65726265726f737b9b5b2b93132b935c9bdcdad95c9899c4cae4dee6d6cae4",
+        ),
+    ];
+
+    for (input, len, expected) in tests {
+        assert_eq!(
+            hex::encode(kerberos::n_fold(input.as_bytes(), *len)),
+            *expected,
+            "{input} {len}"
+        );
+    }
+}
+
+#[test]
+fn des3_derive_key() {
+    // RFC 3961 appendix A.3.
+    let tests = [
+        (
+            "dce06b1f64c857a11c3db57c51899b2cc1791008ce973b92",
+            &hex::decode("0000000155").unwrap()[..],
+            "935079d14490a75c3093c4a6e8c3b049c71e6ee705",
+            "925179d04591a79b5d3192c4a7e9c289b049c71f6ee604cd",
+        ),
+        (
+            "5e13d31c70ef765746578531cb51c15bf11ca82c97cee9f2",
+            &hex::decode("00000001aa").unwrap(),
+            "9f58e5a047d894101c469845d67ae3c5249ed812f2",
+            "9e58e5a146d9942a101c469845d67a20e3c4259ed913f207",
+        ),
+        (
+            "d3f8298ccb166438dcb9b93ee5a7629286a491f838f802fb",
+            b"kerberos",
+            "2270db565d2a3d64cfbfdc5305d4f778a6de42d9da",
+            "2370da575d2a3da864cebfdc5204d56df779a7df43d9da43",
+        ),
+    ];
+
+    for (key, constant, random, derived) in tests {
+        let key = Key::new(EncryptionType::DES3CBCSHA1, hex::decode(key).unwrap()).unwrap();
+
+        assert_eq!(hex::encode(key.derive_random(constant).unwrap()), random);
+        assert_eq!(
+            hex::encode(key.derive_key(constant).unwrap().as_bytes()),
+            derived
+        );
+    }
+}
+
+#[test]
+fn string_to_key() {
+    // RFC 3962 appendix B and RFC 3961 appendix A.4.
+    let tests = [
+        (
+            EncryptionType::AES128CTS,
+            "password",
+            RAEBURN,
+            Some(1),
+            "42263c6e89f4fc28b8df68ee09799f15",
+        ),
+        (
+            EncryptionType::AES256CTS,
+            "password",
+            RAEBURN,
+            Some(1),
+            "fe697b52bc0d3ce14432ba036a92e65bbb52280990a2fa27883998d72af30161",
+        ),
+        (
+            EncryptionType::AES128CTS,
+            "password",
+            RAEBURN,
+            Some(2),
+            "c651bf29e2300ac27fa469d693bdda13",
+        ),
+        (
+            EncryptionType::AES256CTS,
+            "password",
+            RAEBURN,
+            Some(2),
+            "a2e16d16b36069c135d5e9d2e25f896102685618b95914b467c67622225824ff",
+        ),
+        (
+            EncryptionType::AES128CTS,
+            "password",
+            RAEBURN,
+            Some(1200),
+            "4c01cd46d632d01e6dbe230a01ed642a",
+        ),
+        (
+            EncryptionType::AES256CTS,
+            "password",
+            RAEBURN,
+            Some(1200),
+            "55a6ac740ad17b4846941051e1e8b0a7548d93b0ab30a8bc3ff16280382b8c2a",
+        ),
+        (
+            EncryptionType::DES3CBCSHA1,
+            "password",
+            RAEBURN,
+            None,
+            "850bb51358548cd05e86768c313e3bfef7511937dcf72c3e",
+        ),
+        (
+            EncryptionType::DES3CBCSHA1,
+            "potatoe",
+            "WHITEHOUSE.GOVdanny",
+            None,
+            "dfcd233dd0a43204ea6dc437fb15e061b02979c1f74f377a",
+        ),
+        // The NT hash, ignoring the salt.
+        (
+            EncryptionType::RC4HMAC,
+            "password",
+            RAEBURN,
+            None,
+            "8846f7eaee8fb117ad06bdd830b7586c",
+        ),
+    ];
+
+    for (etype, password, salt, iterations, expected) in tests {
+        let key = Key::from_password(etype, password, salt, iterations).unwrap();
+        assert_eq!(key.etype(), etype);
+        assert_eq!(
+            hex::encode(key.as_bytes()),
+            expected,
+            "{etype:?} {iterations:?}"
+        );
+    }
+}
+
+#[test]
+fn default_iterations() {
+    let key = Key::from_password(
+        EncryptionType::AES128CTS,
+        "password",
+        "EXAMPLE.COMuser",
+        None,
+    );
+    assert_eq!(
+        hex::encode(key.unwrap().as_bytes()),
+        "3892facdf64b383e0f9e8607b8de96e3"
+    );
+}
+
+// Encrypted with key usage 3 and a confounder of 0, 1, 2, ... by an independent
+// Python implementation, checked against the RFC 3961 and 3962 vectors. The
+// AES plaintexts cover ciphertext stealing with one block, a partial second
+// block, two full blocks and a partial fourth block.
+const PLAINTEXTS: [&[u8]; 4] = [
+    b"",
+    b"1",
+    b"0123456789abcdef",
+    b"The quick brown fox jumps over the lazy dog",
+];
+
+const ENCRYPTED: &[(EncryptionType, &str, [&str; 4], &str)] = &[
+    (
+        EncryptionType::AES128CTS,
+        "3892facdf64b383e0f9e8607b8de96e3",
+        [
+            "9fbf6bee73ef96c468ff0bf999ee71aa718887419e28a78fd0d8b526",
+            "3ee2bb61c5c903cd5cab636fefae41bb9f81e244f357a2f17805f1d3a6",
+            "32737e7d5d9a12bd8de4cef99ea55dda9fbf6bee73ef96c468ff0bf999ee71aad03a5da6f7108242bbab455c",
+            "9fbf6bee73ef96c468ff0bf999ee71aa98d7f691862a6cc0be6521ab0119bbe18f284ac60ae7b6cb81a17abd\
+             93b0dc06313ffebc0a03e8e1d2c94d735e0ae83c24a4260ebbd6c1",
+        ],
+        "2093e51c434e301d07d64baa",
+    ),
+    (
+        EncryptionType::AES256CTS,
+        "511ccb9e22d7d454919b07228c4cee55c6f8137d6c221534ed446aa2d7a4e864",
+        [
+            "e579932c9faf3399c225278c2de32ce97b3f2ce7b96ef8b69e6370ee",
+            "62e65e8a163735a0031aa0ff1c065966e59dad0c44ebdd11ddc88a99ec",
+            "b3edd3526940ead597b0b042e56667efe579932c9faf3399c225278c2de32ce937048201e15aae8c28552183",
+            "e579932c9faf3399c225278c2de32ce9d2882decc6e6d88f022f390d81dfd6617375e89807a3f5f61d82e88d\
+             1df01f066564be0c0d22e57dbbfa7d82623275210c36d6e19a6d4c",
+        ],
+        "42d9427e702016395d644a99",
+    ),
+    (
+        EncryptionType::DES3CBCSHA1,
+        "3e79704f62a29b4c5bce2f07982501f75d5832a7f79dad51",
+        [
+            "ec77c0183fa96dad9a1afc6e3850387a906169d76b076f91c94d3519",
+            "ec77c0183fa96dad2b66056d6c911a227543dbbdb2c0756ad0eb3a085e8ada8d5180b4b1",
+            "ec77c0183fa96dad1b479682f0daf03f305eb3df55af24a1aa11d1885885c8c000790dc04098e39a1c377a39",
+            "ec77c0183fa96dada78d1dbdc197bff50d965863dd337fbc7eeadd74ed0f9986238e291b55e876854ebc28aa\
+             6cc739881d71b4a888506e319355306241e90192fe9e1a9d0247f7ec40687de7",
+        ],
+        "694bf079a237871544c59d34651b7e2041734da4",
+    ),
+    (
+        EncryptionType::RC4HMAC,
+        "8846f7eaee8fb117ad06bdd830b7586c",
+        [
+            "8ae68218c7d8418341711541aade7e2e52caf01ad5ea40cb",
+            "724eacd8d07615edb55ec57a5da11ddd3788a42bb8c55878d0",
+            "c6b53d44a31cc8912a02338e5e2451d5348941cea9da92ecec0ef6469e392cb0ba2950a06439c835",
+            "78e585b4518c59a35dbd2900fd26543ad5e2e2055d893bcfefac30f4b98a043c44035e10f0e55359d5db760d\
+             7d2c0e750862987dec452f04e99599981a984e8a96818c",
+        ],
+        "e6abaac925df6c44bde7d9bc8c577ec8",
+    ),
+];
+
+fn key(etype: EncryptionType, key: &str) -> Key {
+    Key::new(etype, hex::decode(key).unwrap()).unwrap()
+}
+
+#[test]
+fn decrypt() {
+    for (etype, hex_key, ciphertexts, _) in ENCRYPTED {
+        let key = key(*etype, hex_key);
+
+        for (plaintext, ciphertext) in PLAINTEXTS.iter().zip(ciphertexts) {
+            let mut expected = plaintext.to_vec();
+            if *etype == EncryptionType::DES3CBCSHA1 {
+                expected.resize(plaintext.len().div_ceil(8) * 8, 0);
+            }
+
+            let decrypted = key.decrypt(3, hex::decode(ciphertext).unwrap()).unwrap();
+            assert_eq!(decrypted, expected, "{etype:?} {plaintext:?}");
+        }
+    }
+}
+
+#[test]
+fn round_trip() {
+    for (etype, hex_key, _, _) in ENCRYPTED {
+        let key = key(*etype, hex_key);
+
+        for plaintext in PLAINTEXTS {
+            let encrypted = key.encrypt(7, plaintext).unwrap();
+            assert_ne!(encrypted, key.encrypt(7, plaintext).unwrap());
+
+            let decrypted = key.decrypt(7, &encrypted).unwrap();
+            assert_eq!(&decrypted[..plaintext.len()], plaintext, "{etype:?}");
+        }
+    }
+}
+
+#[test]
+fn checksum() {
+    for (etype, hex_key, _, checksum) in ENCRYPTED {
+        let key = key(*etype, hex_key);
+        let data = PLAINTEXTS[3];
+
+        assert_eq!(hex::encode(key.checksum(3, data).unwrap()), *checksum);
+        assert_eq!(
+            key.verify_checksum(3, data, hex::decode(checksum).unwrap()),
+            Ok(())
+        );
+        assert_eq!(
+            key.verify_checksum(4, data, hex::decode(checksum).unwrap()),
+            Err(KerberosError::Integrity)
+        );
+    }
+}
+
+#[test]
+fn wrong_usage_or_key() {
+    for (etype, hex_key, ciphertexts, _) in ENCRYPTED {
+        let key = key(*etype, hex_key);
+        let other = Key::from_password(*etype, "other", "EXAMPLE.COMuser", None).unwrap();
+
+        for ciphertext in ciphertexts {
+            let ciphertext = hex::decode(ciphertext).unwrap();
+            assert_eq!(key.decrypt(4, &ciphertext), Err(KerberosError::Integrity));
+            assert_eq!(other.decrypt(3, &ciphertext), Err(KerberosError::Integrity));
+        }
+    }
+}
+
+#[test]
+fn modified() {
+    for (etype, hex_key, ciphertexts, _) in ENCRYPTED {
+        let key = key(*etype, hex_key);
+        let mut ciphertext = hex::decode(ciphertexts[3]).unwrap();
+        ciphertext[20] ^= 1;
+
+        assert_eq!(
+            key.decrypt(3, &ciphertext),
+            Err(KerberosError::Integrity),
+            "{etype:?}"
+        );
+        assert_eq!(
+            key.decrypt(3, &ciphertext[..10]),
+            Err(KerberosError::Format)
+        );
+    }
+}
+
+#[test]
+fn invalid_key() {
+    assert!(matches!(
+        Key::new(EncryptionType::AES256CTS, [0; 16]),
+        Err(KerberosError::Cryptor(_))
+    ));
+
+    let rc4 = Key::new(EncryptionType::RC4HMAC, [0; 16]).unwrap();
+    assert_eq!(rc4.derive_key(b"kerberos"), Err(KerberosError::Unsupported));
+}
+
+#[test]
+fn etype_ids() {
+    for etype in [
+        EncryptionType::DES3CBCSHA1,
+        EncryptionType::AES128CTS,
+        EncryptionType::AES256CTS,
+        EncryptionType::RC4HMAC,
+    ] {
+        assert_eq!(EncryptionType::from_id(etype as i32), Some(etype));
+    }
+
+    assert_eq!(EncryptionType::from_id(1), None);
+}
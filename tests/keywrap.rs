@@ -0,0 +1,55 @@
+use common_crypto::cryptor::CryptorError;
+use common_crypto::encoding::{hex_decode, hex_encode};
+use common_crypto::keywrap;
+
+#[test]
+fn rfc_3394() {
+    // Sections 4.1 and 4.6.
+    let vectors = [
+        (
+            "000102030405060708090a0b0c0d0e0f",
+            "00112233445566778899aabbccddeeff",
+            "1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5",
+        ),
+        (
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "00112233445566778899aabbccddeeff000102030405060708090a0b0c0d0e0f",
+            "28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326cbc7f0e71a99f43bfb988b9b7a02dd21",
+        ),
+    ];
+
+    for (kek, key, wrapped) in vectors {
+        let kek = hex_decode(kek).unwrap();
+        assert_eq!(
+            hex_encode(&keywrap::wrap(&kek, hex_decode(key).unwrap()).unwrap()),
+            wrapped
+        );
+        assert_eq!(
+            hex_encode(&keywrap::unwrap(&kek, hex_decode(wrapped).unwrap()).unwrap()),
+            key
+        );
+    }
+}
+
+#[test]
+fn invalid() {
+    let kek = [0; 16];
+    let mut wrapped = keywrap::wrap(&kek, [1; 16]).unwrap();
+
+    wrapped[0] ^= 1;
+    assert_eq!(keywrap::unwrap(&kek, &wrapped), Err(CryptorError::Decode));
+    assert_eq!(
+        keywrap::unwrap(&[1; 16], keywrap::wrap(&kek, [1; 16]).unwrap()),
+        Err(CryptorError::Decode)
+    );
+
+    assert_eq!(keywrap::wrap(&kek, [1; 8]), Err(CryptorError::Param));
+    assert_eq!(keywrap::wrap(&kek, [1; 17]), Err(CryptorError::Param));
+    assert_eq!(keywrap::unwrap(&kek, [1; 16]), Err(CryptorError::Param));
+
+    assert_eq!(keywrap::wrap(&[0; 20], [1; 16]), Err(CryptorError::KeySize));
+    assert_eq!(
+        keywrap::unwrap(&[0; 20], [1; 24]),
+        Err(CryptorError::KeySize)
+    );
+}
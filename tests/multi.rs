@@ -0,0 +1,103 @@
+use common_crypto::hash::multi::{self, MultiHasher};
+use common_crypto::hash::{Algorithm, Hash};
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+fn test_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn multi_hasher() {
+    let mut hasher = MultiHasher::new(&[Algorithm::SHA256, Algorithm::MD5, Algorithm::SHA256]);
+    hasher.update(b"da");
+    hasher.write_all(b"ta").unwrap();
+    let digests = hasher.finish();
+
+    assert_eq!(
+        digests.iter().map(|(a, _)| a).collect::<Vec<_>>(),
+        [Algorithm::SHA256, Algorithm::MD5]
+    );
+    assert_eq!(
+        digests.get(Algorithm::SHA256),
+        Some(&Hash::sha256(b"data")[..])
+    );
+    assert_eq!(digests.get(Algorithm::MD5), Some(&Hash::md5(b"data")[..]));
+    assert_eq!(digests.get(Algorithm::SHA1), None);
+}
+
+#[test]
+fn reader() {
+    let data = vec![7; 1_000_000];
+    let digests = multi::hash_reader(&data[..], &[Algorithm::SHA512, Algorithm::SHA1]).unwrap();
+
+    assert_eq!(
+        digests.get(Algorithm::SHA512),
+        Some(&Hash::sha512(&data)[..])
+    );
+    assert_eq!(digests.get(Algorithm::SHA1), Some(&Hash::sha1(&data)[..]));
+}
+
+#[test]
+fn files() {
+    let dir = test_dir("multi_files");
+    let mut paths = Vec::new();
+    let mut contents = Vec::new();
+
+    for i in 0..50 {
+        let path = dir.join(format!("{i}"));
+        let data = vec![i as u8; i * 1000];
+        std::fs::write(&path, &data).unwrap();
+        paths.push(path);
+        contents.push(data);
+    }
+    paths.insert(10, dir.join("missing"));
+
+    let algorithms = [Algorithm::SHA256, Algorithm::SHA512, Algorithm::MD5];
+    for threads in [1, 4, 64] {
+        let manifest = multi::hash_files_with_threads(
+            &paths,
+            &algorithms,
+            NonZeroUsize::new(threads).unwrap(),
+        );
+
+        assert_eq!(manifest.entries.len(), paths.len());
+        assert_eq!(
+            manifest
+                .errors()
+                .map(|(path, e)| (path, e.kind()))
+                .collect::<Vec<_>>(),
+            [(paths[10].as_path(), io::ErrorKind::NotFound)]
+        );
+
+        let found = manifest.entries.iter().filter(|e| e.digests.is_ok());
+        for ((entry, path), data) in found
+            .zip(paths.iter().filter(|p| p.exists()))
+            .zip(&contents)
+        {
+            assert_eq!(&entry.path, path);
+
+            let digests = entry.digests.as_ref().unwrap();
+            assert_eq!(
+                digests.get(Algorithm::SHA256),
+                Some(&Hash::sha256(data)[..])
+            );
+            assert_eq!(
+                digests.get(Algorithm::SHA512),
+                Some(&Hash::sha512(data)[..])
+            );
+            assert_eq!(digests.get(Algorithm::MD5), Some(&Hash::md5(data)[..]));
+        }
+    }
+}
+
+#[test]
+fn no_files() {
+    let paths: [&str; 0] = [];
+    assert!(multi::hash_files(&paths, &[Algorithm::SHA256])
+        .entries
+        .is_empty());
+}
@@ -0,0 +1,113 @@
+use common_crypto::ntlm;
+
+// The examples from MS-NLMP section 4.2.
+const USER: &str = "User";
+const DOMAIN: &str = "Domain";
+const PASSWORD: &str = "Password";
+const SERVER_CHALLENGE: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+const CLIENT_CHALLENGE: [u8; 8] = [0xaa; 8];
+
+// MsvAvNbDomainName "Domain", MsvAvNbComputerName "Server" and MsvAvEOL.
+const TARGET_INFO: &str =
+    "02000c0044006f006d00610069006e0001000c0053006500720076006500720000000000";
+
+#[test]
+fn hashes() {
+    assert_eq!(
+        hex::encode(ntlm::nt_hash(PASSWORD)),
+        "a4f49c406510bdcab6824ee7c30fd852"
+    );
+    assert_eq!(
+        hex::encode(ntlm::lm_hash(PASSWORD).unwrap()),
+        "e52cac67419a9a224a3b108f3fa6cb6d"
+    );
+    assert_eq!(
+        hex::encode(ntlm::nt_hash_v2(PASSWORD, USER, DOMAIN)),
+        "0c868a403bfd7a93a3001ef22ef02e3f"
+    );
+}
+
+#[test]
+fn lm_hash_empty() {
+    assert_eq!(
+        hex::encode(ntlm::lm_hash("").unwrap()),
+        "aad3b435b51404eeaad3b435b51404ee"
+    );
+}
+
+#[test]
+fn expand_des_key() {
+    assert_eq!(ntlm::expand_des_key(&[0; 7]), [1; 8]);
+    assert_eq!(ntlm::expand_des_key(&[0xff; 7]), [0xfe; 8]);
+    assert_eq!(
+        hex::encode(ntlm::expand_des_key(b"KGS!@#$")),
+        "4aa2d56415018c49"
+    );
+}
+
+#[test]
+fn v1() {
+    let nt_hash = ntlm::nt_hash(PASSWORD);
+    let lm_hash = ntlm::lm_hash(PASSWORD).unwrap();
+
+    assert_eq!(
+        hex::encode(ntlm::v1_response(&nt_hash, &SERVER_CHALLENGE).unwrap()),
+        "67c43011f30298a2ad35ece64f16331c44bdbed927841f94"
+    );
+    assert_eq!(
+        hex::encode(ntlm::v1_response(&lm_hash, &SERVER_CHALLENGE).unwrap()),
+        "98def7b87f88aa5dafe2df779688a172def11c7d5ccdef13"
+    );
+    assert_eq!(
+        hex::encode(ntlm::v1_session_base_key(&nt_hash)),
+        "d87262b0cde4b1cb7499becccdf10784"
+    );
+}
+
+#[test]
+fn v1_ess() {
+    let nt_hash = ntlm::nt_hash(PASSWORD);
+    let response = ntlm::v1_ess_response(&nt_hash, &SERVER_CHALLENGE, &CLIENT_CHALLENGE).unwrap();
+
+    assert_eq!(
+        hex::encode(response),
+        "7537f803ae367128ca458204bde7caf81e97ed2683267232"
+    );
+}
+
+#[test]
+fn v2() {
+    let hash = ntlm::nt_hash_v2(PASSWORD, USER, DOMAIN);
+    let target_info = hex::decode(TARGET_INFO).unwrap();
+    let response = ntlm::v2_response(&hash, &SERVER_CHALLENGE, &CLIENT_CHALLENGE, 0, &target_info);
+
+    assert_eq!(
+        hex::encode(response.lm_response),
+        "86c35097ac9cec102554764a57cccc19aaaaaaaaaaaaaaaa"
+    );
+    assert_eq!(
+        hex::encode(response.nt_proof()),
+        "68cd0ab851e51c96aabc927bebef6a1c"
+    );
+    assert_eq!(
+        hex::encode(response.session_base_key),
+        "8de40ccadbc14a82f15cb0ad0de95ca3"
+    );
+
+    // The blob follows the proof.
+    assert_eq!(
+        hex::encode(&response.nt_response[16..]),
+        format!("01010000000000000000000000000000aaaaaaaaaaaaaaaa00000000{TARGET_INFO}00000000")
+    );
+}
+
+#[test]
+fn timestamp() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    assert_eq!(ntlm::timestamp(UNIX_EPOCH), 116444736000000000);
+    assert_eq!(
+        ntlm::timestamp(UNIX_EPOCH + Duration::from_nanos(1_500)),
+        116444736000000015
+    );
+}
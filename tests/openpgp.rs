@@ -0,0 +1,320 @@
+use common_crypto::encoding::{base64_decode, base64_encode};
+use common_crypto::hash::{Algorithm, Hash};
+use common_crypto::openpgp::{self, Literal, Message, OpenPGPError, S2K};
+use common_crypto::spec::CipherSpec;
+
+const PLAINTEXT: &[u8] = b"The quick brown fox jumps over the lazy dog\n";
+
+// The fixtures are plain.txt, containing PLAINTEXT, encrypted with:
+// gpg --symmetric --passphrase password --compress-algo none --cipher-algo CAST5 plain.txt
+const CAST5: &str = "
+jA0EAwMCn94F+a2eX7P/0l4Bj4umcWMxg9uDGsK4IOQyPsDY+hjgtn/0SEZCALkACAZpy8qCE4+j
+9tRYXUHAz8Y8GD4+5HmWYbafn7i8bxM2vkwTXEyQHWRmYUAGCrs2XFDQb+j5oKm1pht7hP8l
+";
+
+// gpg --symmetric --armor ... --cipher-algo AES256 --s2k-digest-algo SHA256 --s2k-count 65536
+const AES256: &str = "\
+-----BEGIN PGP MESSAGE-----
+
+jA0ECQMIN4ABJ39hvZlg0mYBSmnUsNLgdCp/xuYUH3skfaJeuAEnbRWNZXzdr50C
+d4NS7D2L5gUnlZZ0B5K/mcTJb5JxZWUQIsJRtsLTJP8ZR5HAG1rTeJME9lYkEZC6
+idlMiInx49IF4rD5eKCUWTVr213oaJM=
+=dzeI
+-----END PGP MESSAGE-----
+";
+
+// gpg --symmetric ... --cipher-algo AES128 --s2k-mode 1
+const SALTED: &str = "
+jAwEBwECi3z/9FBnVjTSZgEYo9RvCurgi1DSXyyw7/U+NLAc146VzR/plDNkXEOVOKVNQKwtdCFS
+KY1/rucwZqQKStt2tL3VF495W23++THmq2yL7RelrkOCZEAOwvWZzbWL1wQw10aPzO4qk/j6GHEB
+9Opv1g==
+";
+
+// gpg --symmetric ... --cipher-algo AES128 --s2k-mode 0
+const SIMPLE: &str = "
+jAQEBwAC0mYBKQhdWfVh0mWKXX5LYv4uard/gByPw5m7L0c84j4Fd1WXSjZZZlrTxY0uqu2HvMEn
+rwKQN0g4eWHEaQ5SpSAlDFwpVNvI9FIH7/9soQfqm8VP7a2OaVRgFpqI3oAf1R3UvojiOwM=
+";
+
+// Without a modification detection code.
+// gpg --symmetric ... --rfc2440 --cipher-algo CAST5 --s2k-count 65536
+const SED: &str = "
+jA0EAwMCYN2vl9wYStNgpEeb6I9M/PA8UzYsQdF1QzpwIaeAlTp8DMl/7wVUwIxhA41zDaZtbpZc
+8fb6uipQrF7PFpca6Xcp4NMSNFJXTQHJxggBpeo05w==
+";
+
+// Compressed with raw DEFLATE.
+// gpg --symmetric --passphrase password --cipher-algo AES256 plain.txt
+const COMPRESSED: &str = "
+jA0ECQMC6GrjTLOOxglg0mkBT+vVPjQiDxh5GV7IZnmyAnwiDEmJT6H4oaUpiW3iTlJlup0uSKiu
+qjIMOR5I9krOW/GNlxMbeBePKnVTGlVDOEPk81+FxFyz19wq26nO6xEXZWGBDSkFh2MDJJRaIh7Z
+Wt76jzMg0u4=
+";
+
+// Streamed from stdin, so written with partial body lengths.
+// python3 -c "print(''.join('line %d\n' % i for i in range(100)), end='')" |
+//     gpg --symmetric ... --cipher-algo AES192 --s2k-digest-algo SHA512 --s2k-count 65536
+const PARTIAL: &str = "
+jA0ECAMKgr8cW5CGo6Jg0ukBvXRxWFu6oo5wfUJw1lgZt+PG9ZuZxhi9EjIt/xNjX00iSagyhPUv
+z0p6cumxW/b0z8qPumCtpVQrDQXsOH8nv3SukCEjAcIM6vnfky5UQllkuBoInVl0aPGLu2RqQYYv
+k/8RzFPw8B6dtCboq3Vdq4v/5wSsSK7JV/HlWEPMszqqXzW2OPm5Aw1UBE0h0RciCOCFQe6ZtkOL
+lT5dxW7sEBW3XAHnWDYrUPG1VhMFnCPcBzAHJed06C6E6+5eaTguySXtHM+WsTas/ymillkMUNnk
+HW1V2bWf/2NTG1MsrKRvt5DfG+IFzO6Arxv8Q+1Yncxa8ddrf79T11w35rDjl4uvHfXX59K9vEaK
+cBWZ3mgLF7oicX2dXSqShEphMrSM1tZ/0WByngH3X6hRIkTjJGhmpl6fZSND7BnXATXfTsCdoeAK
+ZRGwJtG01cHoFnjHRXtqyAH/IsBJDW4Y8rPIlocoaztsa498HaW5QtLhqipQ/xzkUlWl3XBZWyoO
+AotbpiSIdNmK5OX9LeA629f2Sx/z1Df+0WqZcD+Z+2OnN1lTFIRety4liZDfzj7oaaauwjhpQA56
+rrynsXSeWnxwpLd07DxREtteWaTYcRHJqtH/tyS/6fKauh8sVZDyKCcwOokIWwMKmP+p0H0sqyuo
+r1GvruDd3SUll5oGh4DByMCJMRuRso/up6x8x53GgVs59p+nZ78fjU4lWch1IsEvHbfsvGRyd60D
+Yj3kr3jKjONxoeASy55WcMKVNHyX+srvsvJsU10sW9oCmaDINGTW4FOlfJBELE4gI3gePwGBuukw
+8wzbIIVCwOt+nCQYcgOnU7+Btc/50y7Omq5/AJtfdmJBNaUr4suYge5A9RFuMEhioOhfkLZfDCuV
+dSNqPLX9GDZXaSuPJx1po2qBGllWrSHRGwp50WU+/thXUipfo4B7d17bJPWO+7ZS7DQc52bEu72P
+UKseZ+u1LGM1bCADMGk8gOYlwUHNFp4YSkJlc+TbqyXO+VRfDHzjmlCEDB8iT7xkc2E8Hzms/kXC
+ZMbjVSKfKDkqcVFe66ARTG38jwDn8y4ZquWBGh2NsbuMIc9Nu7BtXzt2KQMtJEmE4bWGk+jDro+j
+i8BzjP0=
+";
+
+fn literal(message: Message) -> Literal {
+    match message {
+        Message::Literal(literal) => literal,
+        message => panic!("{:?}", message),
+    }
+}
+
+#[test]
+fn s2k() {
+    // The session keys shown by gpg --show-session-key.
+    let tests = [
+        (
+            S2K::Simple {
+                hash: Algorithm::SHA1,
+            },
+            "5baa61e4c9b93f3f0682250b6cf8331b",
+        ),
+        (
+            S2K::Salted {
+                hash: Algorithm::SHA1,
+                salt: hex::decode("8b7cfff450675634").unwrap().try_into().unwrap(),
+            },
+            "7133ef20761b8ec0d733e5ad93d93c28",
+        ),
+        (
+            S2K::Iterated {
+                hash: Algorithm::SHA1,
+                salt: hex::decode("9fde05f9ad9e5fb3").unwrap().try_into().unwrap(),
+                count: 65011712,
+            },
+            "75b8646cac28e14e27fe589aed557837",
+        ),
+        (
+            S2K::Iterated {
+                hash: Algorithm::SHA256,
+                salt: hex::decode("378001277f61bd99").unwrap().try_into().unwrap(),
+                count: 65536,
+            },
+            "0e96600edd0d6e4b200643b5be2140d72baf3152e784bd148ae3751cfd1146b0",
+        ),
+        (
+            S2K::Iterated {
+                hash: Algorithm::SHA512,
+                salt: hex::decode("82bf1c5b9086a3a2").unwrap().try_into().unwrap(),
+                count: 65536,
+            },
+            "15fece651e574d5e2597e67164e066c0467876cf22ab1ef8",
+        ),
+    ];
+
+    for (s2k, key) in tests {
+        let len = key.len() / 2;
+        assert_eq!(
+            hex::encode(s2k.derive_key("password", len)),
+            key,
+            "{:?}",
+            s2k
+        );
+    }
+}
+
+#[test]
+fn s2k_longer_than_digest() {
+    // Further hashes are preloaded with zeroes.
+    let s2k = S2K::Simple {
+        hash: Algorithm::MD5,
+    };
+
+    let key = s2k.derive_key("password", 24);
+    assert_eq!(
+        key,
+        [&Hash::md5("password")[..], &Hash::md5(b"\0password")[..8],].concat()
+    );
+}
+
+#[test]
+fn decrypt() {
+    for input in [CAST5, SALTED, SIMPLE] {
+        let literal = literal(openpgp::decrypt(base64_decode(input).unwrap(), "password").unwrap());
+
+        assert_eq!(literal.format, b'b');
+        assert_eq!(literal.filename, b"plain.txt");
+        assert_eq!(literal.data, PLAINTEXT);
+    }
+}
+
+#[test]
+fn decrypt_armored() {
+    let literal = literal(openpgp::decrypt(AES256, "password").unwrap());
+    assert_eq!(literal.data, PLAINTEXT);
+}
+
+#[test]
+fn decrypt_partial() {
+    let literal = literal(openpgp::decrypt(base64_decode(PARTIAL).unwrap(), "password").unwrap());
+    let expected: String = (0..100).map(|i| format!("line {i}\n")).collect();
+
+    assert_eq!(literal.filename, b"");
+    assert_eq!(literal.date, 1792346213);
+    assert_eq!(literal.data, expected.as_bytes());
+}
+
+#[test]
+fn decrypt_compressed() {
+    match openpgp::decrypt(base64_decode(COMPRESSED).unwrap(), "password").unwrap() {
+        Message::Compressed { algorithm, data } => {
+            assert_eq!(algorithm, 1);
+            assert!(!data.is_empty());
+        }
+        message => panic!("{:?}", message),
+    }
+}
+
+#[test]
+fn decrypt_encrypted_session_key() {
+    // SIMPLE's session key encrypted with a key derived from another password,
+    // which gpg only writes when encrypting to several passwords.
+    let input = base64_decode(SIMPLE).unwrap();
+    let s2k = S2K::Simple {
+        hash: Algorithm::SHA256,
+    };
+    let key = s2k.derive_key("other", 32);
+    let session_key = hex::decode("075baa61e4c9b93f3f0682250b6cf8331b").unwrap();
+    let cfb: CipherSpec = "aes-256-cfb".parse().unwrap();
+    let encrypted_key = cfb.encrypt(key, Some(&[0; 16]), session_key).unwrap();
+
+    let mut packet = vec![0xc3, 4 + encrypted_key.len() as u8, 4, 9, 0, 8];
+    packet.extend_from_slice(&encrypted_key);
+
+    // Placed before the packet for "password", which isn't used.
+    let message = [&packet[..], &input].concat();
+    for password in ["other", "password"] {
+        let literal = literal(openpgp::decrypt(&message, password).unwrap());
+        assert_eq!(literal.data, PLAINTEXT);
+    }
+
+    // The last packet's error.
+    assert_eq!(
+        openpgp::decrypt(&message, "wrong"),
+        Err(OpenPGPError::Decryption)
+    );
+}
+
+#[test]
+fn wrong_password() {
+    for input in [CAST5, SALTED, SIMPLE, SED, PARTIAL] {
+        let input = base64_decode(input).unwrap();
+        assert_eq!(
+            openpgp::decrypt_unprotected(input, "wrong"),
+            Err(OpenPGPError::Decryption)
+        );
+    }
+}
+
+#[test]
+fn modified() {
+    let mut input = base64_decode(CAST5).unwrap();
+    let len = input.len();
+    input[len - 30] ^= 1;
+
+    assert_eq!(
+        openpgp::decrypt(input, "password"),
+        Err(OpenPGPError::Integrity)
+    );
+}
+
+#[test]
+fn unprotected() {
+    let input = base64_decode(SED).unwrap();
+    assert_eq!(
+        openpgp::decrypt(&input, "password"),
+        Err(OpenPGPError::Unprotected)
+    );
+
+    let literal = literal(openpgp::decrypt_unprotected(&input, "password").unwrap());
+    assert_eq!(literal.filename, b"plain.txt");
+    assert_eq!(literal.data, PLAINTEXT);
+}
+
+/// Wraps the packet in uncompressed compressed data packets, each with a
+/// five byte length.
+fn nest(packet: &[u8], depth: usize) -> Vec<u8> {
+    let mut nested = Vec::with_capacity(packet.len() + depth * 7);
+
+    for level in (1..=depth).rev() {
+        let len = (packet.len() + level * 7 - 6) as u32;
+        nested.extend_from_slice(&[0xc8, 0xff]);
+        nested.extend_from_slice(&len.to_be_bytes());
+        nested.push(0);
+    }
+
+    nested.extend_from_slice(packet);
+    nested
+}
+
+#[test]
+fn invalid() {
+    assert_eq!(
+        openpgp::decrypt(PLAINTEXT, "password"),
+        Err(OpenPGPError::Format)
+    );
+    assert_eq!(openpgp::decrypt(b"", "password"), Err(OpenPGPError::Format));
+
+    // Truncated.
+    let input = base64_decode(CAST5).unwrap();
+    assert_eq!(
+        openpgp::decrypt(&input[..input.len() - 1], "password"),
+        Err(OpenPGPError::Format)
+    );
+
+    // A literal data packet, which isn't encrypted.
+    let packet = [&[0xcb, 6 + 3, b'b', 0, 0, 0, 0, 0][..], b"abc"].concat();
+    assert_eq!(
+        openpgp::decrypt(&packet, "password"),
+        Err(OpenPGPError::Format)
+    );
+    assert_eq!(
+        literal(openpgp::read_message(&packet).unwrap()).data,
+        b"abc"
+    );
+
+    // Nested uncompressed compressed data packets are limited, rather than
+    // overflowing the stack.
+    assert_eq!(
+        literal(openpgp::read_message(&nest(&packet, 8)).unwrap()).data,
+        b"abc"
+    );
+    assert_eq!(
+        openpgp::read_message(&nest(&packet, 9)),
+        Err(OpenPGPError::Format)
+    );
+    assert_eq!(
+        openpgp::read_message(&nest(&packet, 100_000)),
+        Err(OpenPGPError::Format)
+    );
+
+    let armored = format!(
+        "-----BEGIN PGP MESSAGE-----\n\n{}\n-----END PGP SIGNATURE-----\n",
+        base64_encode(&input)
+    );
+    assert_eq!(
+        openpgp::decrypt(armored, "password"),
+        Err(OpenPGPError::Format)
+    );
+}
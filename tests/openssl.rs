@@ -0,0 +1,156 @@
+use common_crypto::cryptor::CryptorError;
+use common_crypto::hash::Algorithm;
+use common_crypto::kdf::PRF;
+use common_crypto::openssl::{Enc, EncError, EncReader, EncWriter, KeyDerivation};
+use std::io::{Read, Write};
+
+fn enc(cipher: &str, key_derivation: KeyDerivation) -> Enc {
+    Enc::new(cipher.parse().unwrap(), key_derivation)
+}
+
+#[test]
+fn matches_openssl() {
+    // printf 'The quick brown fox' | openssl enc -<cipher> <options> -pass pass:password
+    let pbkdf2 = KeyDerivation::pbkdf2();
+    for (cipher, key_derivation, expected) in [
+        (
+            "aes-256-cbc",
+            pbkdf2,
+            "53616c7465645f5f8f5e0ce191f3b9c703491e83cc03a3dda161b454bb853c4b6a7457e0a648078959d0242601f4ca37",
+        ),
+        (
+            // -iter 1000 -md sha512
+            "aes-128-cbc",
+            KeyDerivation::PBKDF2 {
+                prf: PRF::SHA512,
+                iterations: 1000,
+            },
+            "53616c7465645f5f13ed9194d5f65d9169133d09ba5831039337936f1a9950adc27ef8f22160f0dfa79c4450e8ba66ad",
+        ),
+        (
+            // -md md5
+            "aes-256-cbc",
+            KeyDerivation::BytesToKey(Algorithm::MD5),
+            "53616c7465645f5f25710de0afd1490d8f3ad1cf1f2b037a56f1386853b96c2012314d766755e9c94c0f0d11e6c50bca",
+        ),
+        (
+            // -md sha256
+            "aes-256-cbc",
+            KeyDerivation::BytesToKey(Algorithm::SHA256),
+            "53616c7465645f5fcd7ff3d9f4cd2aab576056a3314d9e64ae24fe15832e391e839b6801836318deffb17ca2f49d9f75",
+        ),
+        (
+            // -md md5
+            "des-ede3-cbc",
+            KeyDerivation::BytesToKey(Algorithm::MD5),
+            "53616c7465645f5f62732549f9ce034d6455446ca35a32cd4e8be43045fb3520cab8584ab54eb30a",
+        ),
+        (
+            "aes-128-ctr",
+            pbkdf2,
+            "53616c7465645f5fb1bbf3ce335b6e96875a43c5e1af707e2d53609803ff533322bc92",
+        ),
+        (
+            // -iter 1 -md sha1
+            "aes-192-cfb",
+            KeyDerivation::PBKDF2 {
+                prf: PRF::SHA1,
+                iterations: 1,
+            },
+            "53616c7465645f5fc4661e6b8552f357c6254c65e398e8110341e04dcbb9aa7b33462c",
+        ),
+    ] {
+        let enc = enc(cipher, key_derivation);
+        let expected = hex::decode(expected).unwrap();
+
+        assert_eq!(
+            enc.decrypt(b"password", &expected).unwrap(),
+            b"The quick brown fox",
+            "{cipher}"
+        );
+
+        let salt = expected[8..16].try_into().unwrap();
+        assert_eq!(
+            enc.encrypt_with_salt(b"password", salt, b"The quick brown fox")
+                .unwrap(),
+            expected,
+            "{cipher}"
+        );
+    }
+}
+
+#[test]
+fn empty() {
+    // printf '' | openssl enc -aes-256-cbc -pbkdf2 -pass pass:password
+    let expected =
+        hex::decode("53616c7465645f5f31d112818251571acd5c3338e1bef92c005cc22949bc11c4").unwrap();
+    let enc = enc("aes-256-cbc", KeyDerivation::pbkdf2());
+
+    assert_eq!(enc.decrypt(b"password", &expected).unwrap(), b"");
+}
+
+#[test]
+fn salt_is_random() {
+    let enc = enc("aes-128-cbc", KeyDerivation::pbkdf2());
+    let a = enc.encrypt(b"password", b"data").unwrap();
+    let b = enc.encrypt(b"password", b"data").unwrap();
+
+    assert_eq!(&a[..8], b"Salted__");
+    assert_ne!(a[8..16], b[8..16]);
+    assert_eq!(enc.decrypt(b"password", &a).unwrap(), b"data");
+    assert_eq!(enc.decrypt(b"password", &b).unwrap(), b"data");
+}
+
+#[test]
+fn streaming() {
+    let enc = enc("aes-256-cbc", KeyDerivation::pbkdf2());
+    let plaintext: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+
+    let mut writer = EncWriter::new(&enc, b"password", Vec::new()).unwrap();
+    for chunk in plaintext.chunks(1000) {
+        writer.write_all(chunk).unwrap();
+    }
+    let encrypted = writer.finish().unwrap();
+    assert_eq!(
+        encrypted.len(),
+        16 + plaintext.len() + 16 - plaintext.len() % 16
+    );
+
+    let mut reader = EncReader::new(&enc, b"password", &encrypted[..]).unwrap();
+    let mut decrypted = Vec::new();
+    let mut buf = [0u8; 777];
+    loop {
+        let read = reader.read(&mut buf).unwrap();
+        if read == 0 {
+            break;
+        }
+        decrypted.extend_from_slice(&buf[..read]);
+    }
+
+    assert_eq!(decrypted, plaintext);
+    assert_eq!(enc.decrypt(b"password", &encrypted).unwrap(), plaintext);
+}
+
+#[test]
+fn errors() {
+    let enc = enc("aes-256-cbc", KeyDerivation::pbkdf2());
+
+    assert!(matches!(
+        enc.decrypt(b"password", b"Salted__1234"),
+        Err(EncError::Format)
+    ));
+    assert!(matches!(
+        enc.decrypt(b"password", b"Unsalted12345678ciphertext"),
+        Err(EncError::Format)
+    ));
+
+    // Decrypting with the wrong password is only detected by the padding, so a
+    // fixed salt is used to avoid valid padding by chance.
+    let encrypted = enc
+        .encrypt_with_salt(b"password", [0u8; 8], [0u8; 100])
+        .unwrap();
+    assert!(matches!(
+        enc.decrypt(b"wrong password", &encrypted),
+        Err(EncError::Cryptor(CryptorError::Decode))
+    ));
+}
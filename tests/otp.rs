@@ -0,0 +1,235 @@
+use common_crypto::otp::{Algorithm, Generator, KeyURI, OTPError, HOTP, TOTP};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SHA1_SECRET: &[u8] = b"12345678901234567890";
+const SHA256_SECRET: &[u8] = b"12345678901234567890123456789012";
+const SHA512_SECRET: &[u8] = b"1234567890123456789012345678901234567890123456789012345678901234";
+
+fn at(seconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+#[test]
+fn rfc_4226() {
+    // Appendix D.
+    let codes = [
+        "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583", "399871",
+        "520489",
+    ];
+
+    let hotp = HOTP::new(SHA1_SECRET);
+    for (counter, code) in codes.iter().enumerate() {
+        assert_eq!(hotp.generate(counter as u64), *code);
+    }
+}
+
+#[test]
+fn rfc_6238() {
+    // Appendix B.
+    let vectors = [
+        (59, "94287082", "46119246", "90693936"),
+        (1111111109, "07081804", "68084774", "25091201"),
+        (1111111111, "14050471", "67062674", "99943326"),
+        (1234567890, "89005924", "91819424", "93441116"),
+        (2000000000, "69279037", "90698825", "38618901"),
+        (20000000000, "65353130", "77737706", "47863826"),
+    ];
+
+    let sha1 = TOTP::new(SHA1_SECRET).digits(8);
+    let sha256 = TOTP::new(SHA256_SECRET)
+        .algorithm(Algorithm::SHA256)
+        .digits(8);
+    let sha512 = TOTP::new(SHA512_SECRET)
+        .algorithm(Algorithm::SHA512)
+        .digits(8);
+
+    for (seconds, sha1_code, sha256_code, sha512_code) in vectors {
+        assert_eq!(sha1.generate(at(seconds)).unwrap(), sha1_code);
+        assert_eq!(sha256.generate(at(seconds)).unwrap(), sha256_code);
+        assert_eq!(sha512.generate(at(seconds)).unwrap(), sha512_code);
+    }
+}
+
+#[test]
+fn verify_hotp() {
+    let hotp = HOTP::new(SHA1_SECRET);
+
+    assert_eq!(hotp.verify("755224", 0, 0), Some(0));
+    assert_eq!(hotp.verify("338314", 0, 5), Some(4));
+    assert_eq!(hotp.verify("338314", 0, 3), None);
+    assert_eq!(hotp.verify("755224", 1, 5), None);
+    assert_eq!(hotp.verify("55224", 0, 0), None);
+    assert_eq!(hotp.verify("0755224", 0, 0), None);
+}
+
+#[test]
+fn verify_totp() {
+    // The time step for 59 seconds is 1, and 1111111109 is 37037036.
+    let totp = TOTP::new(SHA1_SECRET).digits(8);
+
+    assert_eq!(totp.verify("94287082", at(59)), Some(1));
+    assert_eq!(totp.verify("94287082", at(89)), Some(1));
+    assert_eq!(totp.verify("94287082", at(30)), Some(1));
+    assert_eq!(totp.verify("94287082", at(0)), Some(1));
+    assert_eq!(totp.verify("94287082", at(90)), None);
+    assert_eq!(totp.verify("07081804", at(1111111109)), Some(37037036));
+
+    let strict = totp.clone().skew(0);
+    assert_eq!(strict.verify("94287082", at(59)), Some(1));
+    assert_eq!(strict.verify("94287082", at(60)), None);
+
+    let lenient = totp.skew(2);
+    assert_eq!(lenient.verify("94287082", at(119)), Some(1));
+
+    // A period of 60 seconds makes the time step for 59 seconds 0.
+    let minute = TOTP::new(SHA1_SECRET).digits(8).period(60);
+    assert_eq!(minute.time_step(at(59)), Some(0));
+    assert_eq!(
+        minute.generate(at(59)).unwrap(),
+        HOTP::new(SHA1_SECRET).digits(8).generate(0)
+    );
+
+    // Times before the epoch aren't treated as the first time step.
+    let before = UNIX_EPOCH - Duration::from_secs(1);
+    assert_eq!(minute.time_step(before), None);
+    assert_eq!(minute.generate(before), None);
+    assert_eq!(minute.verify("94287082", before), None);
+}
+
+#[test]
+fn debug_hides_secret() {
+    let debug = format!("{:?}", TOTP::new(SHA1_SECRET));
+    assert!(!debug.contains("49, 50"));
+    assert!(debug.contains("SHA1"));
+}
+
+#[test]
+#[should_panic]
+fn invalid_digits() {
+    let _ = HOTP::new(SHA1_SECRET).digits(5);
+}
+
+#[test]
+fn parse_uri() {
+    let uri: KeyURI =
+        "otpauth://totp/Example:alice@example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example"
+            .parse()
+            .unwrap();
+
+    assert_eq!(uri.issuer.as_deref(), Some("Example"));
+    assert_eq!(uri.account, "alice@example.com");
+    assert_eq!(
+        uri.generator,
+        Generator::TOTP(TOTP::new(b"Hello!\xde\xad\xbe\xef"))
+    );
+
+    let uri: KeyURI = "otpauth://hotp/ACME%20Co:%20john.doe%40email.com?\
+secret=gezdgnbvgy3tqojqgezdgnbvgy3tqojq&algorithm=SHA256&digits=8&counter=42&image=x"
+        .parse()
+        .unwrap();
+
+    assert_eq!(uri.issuer.as_deref(), Some("ACME Co"));
+    assert_eq!(uri.account, "john.doe@email.com");
+    assert_eq!(
+        uri.generator,
+        Generator::HOTP {
+            hotp: HOTP::new(SHA1_SECRET)
+                .algorithm(Algorithm::SHA256)
+                .digits(8),
+            counter: 42,
+        }
+    );
+
+    // The issuer parameter is preferred, and the label needn't have one.
+    let uri: KeyURI = "otpauth://totp/alice?secret=GEZDGNBV&period=60&algorithm=sha512"
+        .parse()
+        .unwrap();
+
+    assert_eq!(uri.issuer, None);
+    assert_eq!(uri.account, "alice");
+    assert_eq!(
+        uri.generator,
+        Generator::TOTP(TOTP::new(b"12345").algorithm(Algorithm::SHA512).period(60))
+    );
+
+    // An encoded separator is recognised when the prefix is the issuer.
+    let uri: KeyURI = "otpauth://totp/ACME%3Aalice?secret=GEZDGNBV&issuer=ACME"
+        .parse()
+        .unwrap();
+    assert_eq!(uri.issuer.as_deref(), Some("ACME"));
+    assert_eq!(uri.account, "alice");
+
+    let uri: KeyURI = "otpauth://totp/Old:alice?secret=GEZDGNBV&issuer=New"
+        .parse()
+        .unwrap();
+    assert_eq!(uri.issuer.as_deref(), Some("New"));
+}
+
+#[test]
+fn generate_uri() {
+    let uri = KeyURI {
+        issuer: Some("ACME Co".to_string()),
+        account: "john.doe@email.com".to_string(),
+        generator: Generator::TOTP(TOTP::new(SHA1_SECRET).digits(8)),
+    };
+
+    let text = uri.to_string();
+    assert_eq!(
+        text,
+        "otpauth://totp/ACME%20Co:john.doe%40email.com?\
+secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=ACME%20Co&algorithm=SHA1&digits=8&period=30"
+    );
+    assert_eq!(text.parse::<KeyURI>().unwrap(), uri);
+
+    let uri = KeyURI {
+        issuer: None,
+        account: "a:b".to_string(),
+        generator: Generator::HOTP {
+            hotp: HOTP::new(b"12345").algorithm(Algorithm::SHA512),
+            counter: 7,
+        },
+    };
+
+    let text = uri.to_string();
+    assert_eq!(
+        text,
+        "otpauth://hotp/a%3Ab?secret=GEZDGNBV&algorithm=SHA512&digits=6&counter=7"
+    );
+    assert_eq!(text.parse::<KeyURI>().unwrap(), uri);
+
+    // Colons in the issuer and account are kept apart from the separator.
+    let uri = KeyURI {
+        issuer: Some("i:j".to_string()),
+        account: "a:b".to_string(),
+        ..uri
+    };
+    assert_eq!(uri.to_string().parse::<KeyURI>().unwrap(), uri);
+}
+
+#[test]
+fn invalid_uri() {
+    for uri in [
+        "",
+        "https://totp/alice?secret=GEZDGNBV",
+        "otpauth://totp?secret=GEZDGNBV",
+        "otpauth://totp/alice",
+        "otpauth://totp/alice?secret=",
+        "otpauth://totp/alice?secret=GEZDGNB1",
+        "otpauth://totp/alice?secret=GEZDGNBV&digits=5",
+        "otpauth://totp/alice?secret=GEZDGNBV&digits=six",
+        "otpauth://totp/alice?secret=GEZDGNBV&period=0",
+        "otpauth://totp/alice?secret=GEZDGNBV&issuer=%2",
+        "otpauth://totp/alice%ff?secret=GEZDGNBV",
+        "otpauth://hotp/alice?secret=GEZDGNBV",
+        "otpauth://hotp/alice?secret=GEZDGNBV&counter=-1",
+    ] {
+        assert_eq!(uri.parse::<KeyURI>(), Err(OTPError::Format), "{uri}");
+    }
+
+    for uri in [
+        "otpauth://motp/alice?secret=GEZDGNBV",
+        "otpauth://totp/alice?secret=GEZDGNBV&algorithm=MD5",
+    ] {
+        assert_eq!(uri.parse::<KeyURI>(), Err(OTPError::Unsupported), "{uri}");
+    }
+}
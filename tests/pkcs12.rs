@@ -0,0 +1,272 @@
+use common_crypto::cryptor::CryptorError;
+use common_crypto::encoding::base64_decode;
+use common_crypto::hash::Algorithm;
+use common_crypto::pkcs12::{self, Bag, PBEAlgorithm, PKCS12Error, Purpose, PBE};
+
+// openssl ecparam -name prime256v1 -genkey -noout -out key.pem
+// openssl req -x509 -new -key key.pem -subj /CN=test -days 36500 -out cert.pem
+// openssl pkcs12 -export -legacy -in cert.pem -inkey key.pem -passout pass:password
+const LEGACY: &str = "
+MIIDegIBAzCCA0AGCSqGSIb3DQEHAaCCAzEEggMtMIIDKTCCAh8GCSqGSIb3DQEHBqCCAhAwggIM
+AgEAMIICBQYJKoZIhvcNAQcBMBwGCiqGSIb3DQEMAQYwDgQI6lvni4AhuiECAggAgIIB2PYdjgy+
+epnDkk6iFLNQ8LkbiKzXrjRK5SRlmoLKGAGiRUoKnadMqKI6icgVAr3KCJPp0fnGlCwuwqmOOgsM
+1kb/5Qg/V2oed5rlt0mmcYRAIUHFmT3scrmCIJb7UVl62vcz6Lgs5aPFp4j+1r0/G03x3T5yg5Sj
+hBmmdBC+JSRWKW+eNUaAnYlIhwXrNzbG6Ub7ccw3u4QWO5p1q6vaA/2O9jaAwmVTOP4fSVzQnDHI
+TEjJlcO1SC87wTLHjE9RV63FF9j6FizoHxkNvmTphOVmSXN0NVmWh/EvM9lABj+yg5Dar5ECiw6v
+jqSy5U0i8iHWN0KLWeAbwkgG5j4ab48sXp1ijy6JL0+3jGitPjVw7bDcqjeAWmfLBzd8EY8arEq8
+tY9haEEPdJ3dat88lfDKqOBLhucksYVu2mNriudGH7fLje4Zv7MEoxqAsrVMvoc8+oOf+MnYhnk3
+kPGoLI7f8ppg3iPmO9OpuQK1yKTNNKxuDt5h5ibwgldHJLBMRR1Wu3s0qAfo9i6R/ryiLjvqWPZ1
+R0RKRmC3eC5nObHFsXaqYHD6cC88HU3rHnBes5cBRN8+rbft+Xg7IeTbav3wOyynFnTpEJIvTGTo
+YBpKqtMRNv+nTT4wggECBgkqhkiG9w0BBwGggfQEgfEwge4wgesGCyqGSIb3DQEMCgECoIG0MIGx
+MBwGCiqGSIb3DQEMAQMwDgQIGB6IEohy8fkCAggABIGQu6b4AxM2S77ALem8cDc2F6gJsfJKfEnm
+ncfBtC8b8wKR/gvTDftGzZKgBDWXHGxWHxcwr+Vd8dd1rXPQtcXs4eLDIuX3mZe6hz4kAiHDP1Nh
+7yWSdZfNMK3HuSZ9YMONphcMZXIx/9gOajo7tAi8Ftzh8pnJYn/HndSMp+2d9GUxBhOW4ptKkFk+
+QoJR62xzMSUwIwYJKoZIhvcNAQkVMRYEFA52uqDE0kymGSAZNX7/oiS48YrSMDEwITAJBgUrDgMC
+GgUABBQlu2m0pSBrWvA49H5sssnd276YFgQIpWtqyb5e3bgCAggA
+";
+
+// openssl pkcs12 -export -legacy -certpbe PBE-SHA1-2DES -keypbe PBE-SHA1-RC4-128 \
+//     -macalg sha256 -in cert.pem -inkey key.pem -passout pass:password
+const OTHER: &str = "
+MIIDgwIBAzCCAzkGCSqGSIb3DQEHAaCCAyoEggMmMIIDIjCCAh8GCSqGSIb3DQEHBqCCAhAwggIM
+AgEAMIICBQYJKoZIhvcNAQcBMBwGCiqGSIb3DQEMAQQwDgQIEz9Xrd+VW2ACAggAgIIB2BVcDvr7
+jm2sak2nkgfb4vSRkHkfogCZgeTFumEErutdcfCVufoRfJ8TxFQARI7czQ9/8T3gsWjneqnOrGk4
+vHJKWxDKuP540zDJ63Q2CFHMYSp4/3o0ljhtjOlEqA3uBuhWvOn1akXApPKyuFXrEBC9GOn8gxRW
+dvwJ91tJxtdOpXIf0Mej758vy/bO+AYILjtOGtcLM9Foa8Vp0cowPop5LVbXUW0EC/rkSWaGV7uF
+jiEjOy2lEkGkON3dppYl5C92Z0ffqCH0xteaLpIVe1++WdKEoRxDvh7bgO4f5vF8ZaB1V2HPc5w/
+bZ9YPo8dgv3fCy2/AcJDzn7LLlc4DNvcSq8B3yS26K7rvETnuApnzCq2Mi1RvdIs9ozM6NwquNJH
+FCfMxj5BhztdobCic3G61c4klYRRvrc+3lE1+ijbwNS4m5o9JAviuoiaWe8sfWJ5IsclaIKVx8vt
+RG6pOaCLgz9Pj7GtmIlr0a55PYcok4upoEKlp1av+Y8Jq59cWtLCx5L/3fU7wnAszuaA5L0yE/wO
+CpeGwqBMlDi/dGTME9JB3OmjcpODNkNc7tCwJT33Jr161MfFxSGK3f6jT02s5TQxpLidMPpI48Fq
+Ep6JdokLMwYmYMAwgfwGCSqGSIb3DQEHAaCB7gSB6zCB6DCB5QYLKoZIhvcNAQwKAQKgga4wgasw
+HAYKKoZIhvcNAQwBATAOBAjANNj0P5yLsQICCAAEgYqNZ3zg4049Q5YnYL/1pupDmeUyYtpWJIY7
++4OG6HxVfLx/2I3PYQndBO5junNafZm/UsFGW34VFVTX95uSD0SYkPKS3G1MvPPVRs7JQ1A+6O1o
+BFxckXUGf2wGmorufyH3sl8HI0wYXVY2zwMKZwb/CGIkXfdgtQg0nuLsQzWPed07J/i5ZFQAjOkx
+JTAjBgkqhkiG9w0BCRUxFgQUDna6oMTSTKYZIBk1fv+iJLjxitIwQTAxMA0GCWCGSAFlAwQCAQUA
+BCB5CKdacPiRvI2uQ2IQDp9muISM5WAOKVKro+mWKiP+ZwQINR8K5+QuHnACAggA
+";
+
+// OpenSSL 3's defaults of PBES2 with AES-256-CBC, and a SHA-256 MAC.
+// openssl pkcs12 -export -in cert.pem -inkey key.pem -passout pass:password
+const MODERN: &str = "
+MIIEDAIBAzCCA8IGCSqGSIb3DQEHAaCCA7MEggOvMIIDqzCCAmIGCSqGSIb3DQEHBqCCAlMwggJP
+AgEAMIICSAYJKoZIhvcNAQcBMFcGCSqGSIb3DQEFDTBKMCkGCSqGSIb3DQEFDDAcBAi+FYmCW8UD
+hQICCAAwDAYIKoZIhvcNAgkFADAdBglghkgBZQMEASoEEMarFdlu+iRujDs3gC2qzLGAggHg3lsc
+VROWafjTgfLttTu/gl8oFqdWJkEefZ8WNyt6wzhjw68CV1Oo/pMGBhva7iNlcWijn4chJvEBjSJ6
+6UD8O2s9t2gJLXF1HBxNcs+DggFBml9j2uWL6xDCBqOkwu+MJoXpSf6MgS33LZYsMU7UEOkzHyJy
+R1qulAB2kBjilAbA9zIufOVlNJLoXrxfhzV0Y/u1l2ujS8TUB0a4GbelvWYgWgADS4TOCVIvzpX7
+A2SYVx/o+DlDTFxYrjZ5F88rNYn8SRvWyZBLPpkdWF4h0OMawfmj9JKMxfWzX0szdMwyOkr+rPkh
+vBhG4DsUcvkKJkniFTloHKjsO08QfF6mgIYcnY2bsbOj6gQH8Ihj1RnytA9Zc2jizBmkj8bPnof4
+SJMLzIMQ45cK+LJ8JXmxwrgFj0+tceGICp+0owVGBWg+L1WHBmVuWGG9bqNxaYMNVtfR3BL4nPTU
+Xvj5PBETTuPaczQLx5HpFEvptxry+K6DT9ImjjSzL78yLzf0HNxSWewRumGLqMHy63t6xWMWJALj
+2oTQLm2PCYodY4THOliWQ9amySnp0CFGGQnWBVZjgTljA9g5eZc3p1paySMltE+9ZzNgF++Huh7u
+xFNC8DTwOQ2YqXgisZwAxw7wdle+MIIBQQYJKoZIhvcNAQcBoIIBMgSCAS4wggEqMIIBJgYLKoZI
+hvcNAQwKAQKgge8wgewwVwYJKoZIhvcNAQUNMEowKQYJKoZIhvcNAQUMMBwECJYc/JSSZHfjAgII
+ADAMBggqhkiG9w0CCQUAMB0GCWCGSAFlAwQBKgQQF36EFzOpHR1745PA94ssjASBkMxQDtOsabGW
+UsjbRULo8OgixINl0NxhVK+3dAdiH5SpV598dYvvaTkbgJMghNJd4t/XHyNlkCvZ2Zy8sYpwNrDw
+xtmSsVE6Fr+CYyCvs+X1S/HsErmL8EH5hibTmy2h1JunP04bPMavVyhx5roemEf6ay4gO+o7wMdK
++ALjjhXdYn6G2HHGEzzFbUwEgCvp8jElMCMGCSqGSIb3DQEJFTEWBBQOdrqgxNJMphkgGTV+/6Ik
+uPGK0jBBMDEwDQYJYIZIAWUDBAIBBQAEIBeQVlCiA+qU6yjegXa3JbZTvnLjyvQUUiFU5zRiQ2Rh
+BAhvWvP6LWg0igICCAA=
+";
+
+// openssl pkcs8 -topk8 -nocrypt -in key.pem -outform DER
+const KEY: &[&str] = &[
+    "308187020100301306072a8648ce3d020106082a8648ce3d030107046d306b02010104200440712d49da2f19fb040d6f",
+    "8a1bad9f0c31a94faccafb870d051fcb61b9f50ea14403420004d7a85f5702372c76d053b8b5cc662ef4f7b7984d6789",
+    "d280760f623bf3ebed13dea856210513b250304e252a01d1dc4970e9f755900223ec64918c74daf6fbbb",
+];
+
+// openssl x509 -in cert.pem -outform DER
+const CERTIFICATE: &[&str] = &[
+    "308201763082011ba00302010202144f93e4ce3081acb0071affbd176ec39e1028aa9b300a06082a8648ce3d04030230",
+    "0f310d300b06035504030c04746573743020170d3236313031383137343831315a180f32313236303932343137343831",
+    "315a300f310d300b06035504030c04746573743059301306072a8648ce3d020106082a8648ce3d03010703420004d7a8",
+    "5f5702372c76d053b8b5cc662ef4f7b7984d6789d280760f623bf3ebed13dea856210513b250304e252a01d1dc4970e9",
+    "f755900223ec64918c74daf6fbbba3533051301d0603551d0e04160414d9ba1d51a41db7165a1378acd440a84123bb47",
+    "6e301f0603551d23041830168014d9ba1d51a41db7165a1378acd440a84123bb476e300f0603551d130101ff04053003",
+    "0101ff300a06082a8648ce3d0403020349003046022100eee6ab74f6b1528b233dac9d7d4e61bc2bd081562302e8e5ab",
+    "acf048e53b61850221009d0b671112a2dd34f7975bd7627388520f3579eb8c657c9cc167d7e64dc996e7",
+];
+
+fn unhex(lines: &[&str]) -> Vec<u8> {
+    hex::decode(lines.concat()).unwrap()
+}
+
+#[test]
+fn derive_key() {
+    // openssl kdf -keylen <len> -kdfopt digest:<digest> -kdfopt hexpass:<BMPString>
+    //     -kdfopt hexsalt:<salt> -kdfopt iter:<iterations> -kdfopt id:<purpose> PKCS12KDF
+    for (algorithm, password, salt, purpose, iterations, expected) in [
+        (
+            Algorithm::SHA1,
+            "smeg",
+            "0a58cf64530d823f",
+            Purpose::Key,
+            1,
+            "8aaae6297b6cb04642ab5b077851284eb7128f1a2a7fbca3",
+        ),
+        (
+            Algorithm::SHA1,
+            "smeg",
+            "0a58cf64530d823f",
+            Purpose::IV,
+            1,
+            "79993dfe048d3b76",
+        ),
+        (
+            Algorithm::SHA1,
+            "queeg",
+            "1682c0fc5b3f7ec5",
+            Purpose::Key,
+            1000,
+            "483dd6e919d7de2e8e648ba8f862f3fbfbdc2bcb2c02957f",
+        ),
+        (
+            Algorithm::SHA1,
+            "queeg",
+            "3d83c0e4546ac140",
+            Purpose::MAC,
+            1000,
+            "17b9e78ea534fc2b6a35512d03799d9ea3c461c0",
+        ),
+        (
+            Algorithm::SHA256,
+            "password",
+            "0102030405060708",
+            Purpose::MAC,
+            2048,
+            "4f510e1b5428cb3a1b784ceee7b1a95670f60e825199c121bd1752ee9e1c83f2",
+        ),
+        (
+            Algorithm::SHA512,
+            "password",
+            "0102030405060708",
+            Purpose::Key,
+            2,
+            "fe5052ed15f245e7e4c8036c1d24c264b4537d8e68ee958b8a387831b5c1fd7c\
+             9d3d31d4418016500c67d96c6705b1d40d31172ab53bd0b34d54886a57b9f485",
+        ),
+    ] {
+        let salt = hex::decode(salt).unwrap();
+        let expected = hex::decode(expected.replace(' ', "")).unwrap();
+        let derived = pkcs12::derive_key(
+            algorithm,
+            password,
+            &salt,
+            purpose,
+            iterations,
+            expected.len(),
+        );
+        assert_eq!(derived, expected, "{password} {purpose:?}");
+    }
+}
+
+#[test]
+fn decrypt_legacy() {
+    let file = base64_decode(LEGACY).unwrap();
+
+    assert_eq!(
+        pkcs12::decrypt(&file, "password").unwrap(),
+        [Bag::Certificate(unhex(CERTIFICATE)), Bag::Key(unhex(KEY))]
+    );
+}
+
+#[test]
+fn decrypt_other_algorithms() {
+    let file = base64_decode(OTHER).unwrap();
+
+    assert_eq!(
+        pkcs12::decrypt(&file, "password").unwrap(),
+        [Bag::Certificate(unhex(CERTIFICATE)), Bag::Key(unhex(KEY))]
+    );
+}
+
+#[test]
+fn decrypt_modern() {
+    let file = base64_decode(MODERN).unwrap();
+
+    assert_eq!(
+        pkcs12::decrypt(&file, "password").unwrap(),
+        [Bag::Certificate(unhex(CERTIFICATE)), Bag::Key(unhex(KEY))]
+    );
+}
+
+#[test]
+fn wrong_password() {
+    for file in [LEGACY, OTHER, MODERN] {
+        let file = base64_decode(file).unwrap();
+        assert_eq!(pkcs12::decrypt(&file, "hunter2"), Err(PKCS12Error::MAC));
+    }
+}
+
+#[test]
+fn corrupt() {
+    let mut file = base64_decode(LEGACY).unwrap();
+    assert_eq!(
+        pkcs12::decrypt(&file[..100], "password"),
+        Err(PKCS12Error::Format)
+    );
+
+    // Part of the encrypted certificates.
+    file[200] ^= 1;
+    assert_eq!(pkcs12::decrypt(&file, "password"), Err(PKCS12Error::MAC));
+
+    assert_eq!(
+        pkcs12::decrypt(b"not a pkcs12 file", "password"),
+        Err(PKCS12Error::Format)
+    );
+}
+
+#[test]
+fn encrypt_matches_openssl() {
+    // The shrouded key's parameters, from openssl pkcs12 -info.
+    let pbe = PBE {
+        algorithm: PBEAlgorithm::SHA1And3KeyTDES,
+        salt: hex::decode("181e88128872f1f9").unwrap(),
+        iterations: 2048,
+    };
+    let encrypted = pbe.encrypt("password", unhex(KEY)).unwrap();
+
+    let file = base64_decode(LEGACY).unwrap();
+    assert!(file.windows(encrypted.len()).any(|w| w == encrypted));
+}
+
+#[test]
+fn round_trip() {
+    for algorithm in [
+        PBEAlgorithm::SHA1And128BitRC4,
+        PBEAlgorithm::SHA1And40BitRC4,
+        PBEAlgorithm::SHA1And3KeyTDES,
+        PBEAlgorithm::SHA1And2KeyTDES,
+        PBEAlgorithm::SHA1And128BitRC2,
+        PBEAlgorithm::SHA1And40BitRC2,
+    ] {
+        let pbe = PBE::new(algorithm).unwrap();
+        assert_eq!(pbe.salt.len(), 8);
+        assert_eq!(pbe.iterations, 2048);
+
+        let encrypted = pbe.encrypt("password", b"The quick brown fox").unwrap();
+        assert_ne!(&encrypted[..], b"The quick brown fox");
+        assert_eq!(
+            pbe.decrypt("password", &encrypted).unwrap(),
+            b"The quick brown fox",
+            "{algorithm:?}"
+        );
+    }
+}
+
+#[test]
+fn decrypt_with_wrong_password() {
+    let pbe = PBE {
+        algorithm: PBEAlgorithm::SHA1And3KeyTDES,
+        salt: b"saltsalt".to_vec(),
+        iterations: 1,
+    };
+    let encrypted = pbe.encrypt("password", b"The quick brown fox").unwrap();
+
+    assert_eq!(
+        pbe.decrypt("hunter2", &encrypted),
+        Err(CryptorError::Decode)
+    );
+}
@@ -0,0 +1,21 @@
+use common_crypto::random;
+
+#[test]
+fn fill() {
+    let mut a = [0u8; 32];
+    let mut b = [0u8; 32];
+    random::fill(&mut a).unwrap();
+    random::fill(&mut b).unwrap();
+
+    assert_ne!(a, [0u8; 32]);
+    assert_ne!(a, b);
+    random::fill(&mut []).unwrap();
+}
+
+#[test]
+fn bytes() {
+    assert_ne!(
+        random::bytes::<16>().unwrap(),
+        random::bytes::<16>().unwrap()
+    );
+}
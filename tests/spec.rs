@@ -0,0 +1,297 @@
+use common_crypto::cryptor::{Config, Cryptor, CryptorError, Mode, Padding};
+use common_crypto::spec::{Algorithm, CipherSpec, ParseCipherError};
+
+const KEY: &[u8; 32] = b"0123456789abcdef0123456789abcdef";
+const IV: &[u8; 16] = b"use random iv :)";
+
+#[test]
+fn parse() {
+    assert_eq!(
+        "aes-256-cbc".parse::<CipherSpec>().unwrap(),
+        CipherSpec {
+            algorithm: Algorithm::AES,
+            mode: Some(Mode::CBC),
+            padding: Padding::PKCS7,
+            key_len: 32,
+        }
+    );
+
+    assert_eq!(
+        "des-ede3-cfb8".parse::<CipherSpec>().unwrap(),
+        CipherSpec {
+            algorithm: Algorithm::TDES,
+            mode: Some(Mode::CFB8),
+            padding: Padding::None,
+            key_len: 24,
+        }
+    );
+
+    assert_eq!(
+        "rc4".parse::<CipherSpec>().unwrap(),
+        CipherSpec {
+            algorithm: Algorithm::RC4,
+            mode: None,
+            padding: Padding::None,
+            key_len: 16,
+        }
+    );
+
+    assert_eq!("bf-448-ofb".parse::<CipherSpec>().unwrap().key_len, 56);
+}
+
+#[test]
+fn parse_errors() {
+    for (name, error) in [
+        ("aes", ParseCipherError::KeySize),
+        ("aes-cbc", ParseCipherError::KeySize),
+        ("aes-64-cbc", ParseCipherError::KeySize),
+        ("aes-256", ParseCipherError::Mode),
+        ("aes-256-abc", ParseCipherError::Mode),
+        ("aes-256-cbc-cbc", ParseCipherError::Mode),
+        ("des-128-cbc", ParseCipherError::KeySize),
+        ("rc4-cbc", ParseCipherError::Mode),
+        ("aesx-256-cbc", ParseCipherError::Algorithm),
+        ("serpent-256-cbc", ParseCipherError::Algorithm),
+    ] {
+        assert_eq!(name.parse::<CipherSpec>().unwrap_err(), error, "{}", name);
+    }
+}
+
+#[test]
+fn openssl_names() {
+    // The output of `openssl enc -list`.
+    for (name, canonical) in [
+        ("aes-128-cbc", "aes-128-cbc"),
+        ("aes-128-cfb", "aes-128-cfb"),
+        ("aes-128-cfb8", "aes-128-cfb8"),
+        ("aes-128-ctr", "aes-128-ctr"),
+        ("aes-128-ecb", "aes-128-ecb"),
+        ("aes-128-ofb", "aes-128-ofb"),
+        ("aes-256-cbc", "aes-256-cbc"),
+        ("aes128", "aes-128-cbc"),
+        ("aes192", "aes-192-cbc"),
+        ("aes256", "aes-256-cbc"),
+        ("bf", "bf-cbc"),
+        ("bf-cbc", "bf-cbc"),
+        ("bf-cfb", "bf-cfb"),
+        ("bf-ecb", "bf-ecb"),
+        ("bf-ofb", "bf-ofb"),
+        ("blowfish", "bf-cbc"),
+        ("cast", "cast5-cbc"),
+        ("cast-cbc", "cast5-cbc"),
+        ("cast5-cbc", "cast5-cbc"),
+        ("cast5-cfb", "cast5-cfb"),
+        ("cast5-ecb", "cast5-ecb"),
+        ("cast5-ofb", "cast5-ofb"),
+        ("des", "des-cbc"),
+        ("des-cbc", "des-cbc"),
+        ("des-cfb", "des-cfb"),
+        ("des-cfb8", "des-cfb8"),
+        ("des-ecb", "des-ecb"),
+        ("des-ede3", "des-ede3-ecb"),
+        ("des-ede3-cbc", "des-ede3-cbc"),
+        ("des-ede3-cfb", "des-ede3-cfb"),
+        ("des-ede3-cfb8", "des-ede3-cfb8"),
+        ("des-ede3-ecb", "des-ede3-ecb"),
+        ("des-ede3-ofb", "des-ede3-ofb"),
+        ("des-ofb", "des-ofb"),
+        ("des3", "des-ede3-cbc"),
+        ("rc2", "rc2-cbc"),
+        ("rc2-128", "rc2-cbc"),
+        ("rc2-40", "rc2-40-cbc"),
+        ("rc2-40-cbc", "rc2-40-cbc"),
+        ("rc2-64", "rc2-64-cbc"),
+        ("rc2-64-cbc", "rc2-64-cbc"),
+        ("rc2-cbc", "rc2-cbc"),
+        ("rc2-cfb", "rc2-cfb"),
+        ("rc2-ecb", "rc2-ecb"),
+        ("rc2-ofb", "rc2-ofb"),
+        ("rc4", "rc4"),
+        ("rc4-40", "rc4-40"),
+        ("AES-256-CBC", "aes-256-cbc"),
+    ] {
+        assert_eq!(name.parse::<CipherSpec>().unwrap().to_string(), canonical);
+    }
+
+    for name in [
+        "aes-128-cfb1",
+        "aes128-wrap",
+        "aes256-wrap-pad",
+        "id-aes128-wrap",
+        "des-cfb1",
+        "des-ede",
+        "des-ede-cbc",
+        "des-ede3-cfb1",
+        "des3-wrap",
+        "desx-cbc",
+        "aes-256-gcm",
+        "aes-256-xts",
+        "aes-128-cbc-hmac-sha256",
+        "rc4-hmac-md5",
+        "camellia-128-cbc",
+        "chacha20-poly1305",
+    ] {
+        assert_eq!(
+            name.parse::<CipherSpec>().unwrap_err(),
+            ParseCipherError::Unsupported,
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn matches_openssl_enc() {
+    // printf 'The quick brown fox' | openssl enc -<name> -K <key> -iv <iv>
+    for (name, key, iv, expected) in [
+        (
+            "bf-cbc",
+            "000102030405060708090a0b0c0d0e0f",
+            "0706050403020100",
+            "5bf922b64ddb68e70004d56b1b15d68e50df08de011cd7f4",
+        ),
+        (
+            "cast5-ofb",
+            "000102030405060708090a0b0c0d0e0f",
+            "0706050403020100",
+            "02e4aa02e5aec6242f8ea2b38409bcafaaf8d8",
+        ),
+        (
+            "rc2-40-cbc",
+            "0123456789",
+            "0706050403020100",
+            "8d6b091d072bbf4c697fa32293a25a6fe668d0230c19c06f",
+        ),
+        (
+            "des-ede3-cbc",
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "0706050403020100",
+            "8f8f4927eae06b33222f48829b861773bf0c181fcbfa58be",
+        ),
+        (
+            "aes-128-cfb8",
+            "000102030405060708090a0b0c0d0e0f",
+            "0f0e0d0c0b0a09080706050403020100",
+            "745c0f0abd994b0db7824043a0452daf9e7193",
+        ),
+    ] {
+        let spec: CipherSpec = name.parse().unwrap();
+        let key = hex::decode(key).unwrap();
+        let iv = hex::decode(iv).unwrap();
+
+        assert_eq!(
+            hex::encode(
+                spec.encrypt(&key, Some(&iv), b"The quick brown fox")
+                    .unwrap()
+            ),
+            expected,
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn display_round_trips() {
+    for name in [
+        "aes-128-ecb",
+        "aes-192-ctr",
+        "aes-256-cbc",
+        "des-cfb",
+        "des-ede3-ofb",
+        "cast5-cbc",
+        "cast5-40-cbc",
+        "rc4",
+        "rc4-40",
+        "rc2-64-cbc",
+        "bf-cfb8",
+    ] {
+        assert_eq!(name.parse::<CipherSpec>().unwrap().to_string(), name);
+    }
+}
+
+#[test]
+fn matches_config() {
+    let spec: CipherSpec = "aes-256-ctr".parse().unwrap();
+    let config = Config::aes256(KEY).ctr(IV);
+
+    assert_eq!(
+        spec.encrypt(KEY, Some(IV), b"Hello").unwrap(),
+        Cryptor::encrypt(&config, b"Hello").unwrap()
+    );
+}
+
+#[test]
+fn padding() {
+    let mut spec: CipherSpec = "aes-256-cbc".parse().unwrap();
+
+    let encrypted = spec.encrypt(KEY, Some(IV), [0u8; 32]).unwrap();
+    assert_eq!(encrypted.len(), 48);
+    assert_eq!(spec.decrypt(KEY, Some(IV), encrypted).unwrap(), [0u8; 32]);
+
+    spec.padding = Padding::None;
+    let encrypted = spec.encrypt(KEY, Some(IV), [0u8; 32]).unwrap();
+    assert_eq!(encrypted.len(), 32);
+    assert_eq!(spec.decrypt(KEY, Some(IV), encrypted).unwrap(), [0u8; 32]);
+}
+
+#[test]
+fn key_size_is_checked() {
+    let spec: CipherSpec = "aes-128-cbc".parse().unwrap();
+
+    assert_eq!(
+        spec.encrypt(KEY, Some(IV), b"Hello").unwrap_err(),
+        CryptorError::KeySize
+    );
+}
+
+#[test]
+fn xts_is_rejected() {
+    let spec = CipherSpec {
+        algorithm: Algorithm::AES,
+        mode: Some(Mode::XTS),
+        padding: Padding::None,
+        key_len: 32,
+    };
+
+    assert_eq!(
+        spec.encrypt(KEY, Some(IV), b"sixteen byte msg")
+            .unwrap_err(),
+        CryptorError::Param
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde() {
+    let mut spec: CipherSpec = serde_json::from_str(r#""aes-256-cbc""#).unwrap();
+    assert_eq!(spec, "aes-256-cbc".parse().unwrap());
+    assert_eq!(serde_json::to_string(&spec).unwrap(), r#""aes-256-cbc""#);
+
+    spec.padding = Padding::None;
+    let json = serde_json::to_string(&spec).unwrap();
+    assert_eq!(json, r#"{"cipher":"aes-256-cbc","padding":"none"}"#);
+    assert_eq!(serde_json::from_str::<CipherSpec>(&json).unwrap(), spec);
+
+    assert!(serde_json::from_str::<CipherSpec>(r#""aes-256""#).is_err());
+    assert!(serde_json::from_str::<CipherSpec>(r#"{"padding":"none"}"#).is_err());
+}
+
+#[test]
+fn iv_is_checked() {
+    let ecb: CipherSpec = "aes-256-ecb".parse().unwrap();
+    assert_eq!(
+        ecb.encrypt(KEY, Some(IV), b"Hello").unwrap_err(),
+        CryptorError::InitializationVectorPresent
+    );
+
+    let cbc: CipherSpec = "aes-256-cbc".parse().unwrap();
+    assert_eq!(
+        cbc.encrypt(KEY, None, b"Hello").unwrap_err(),
+        CryptorError::Param
+    );
+    assert_eq!(
+        cbc.encrypt(KEY, Some(&IV[..8]), b"Hello").unwrap_err(),
+        CryptorError::Param
+    );
+}
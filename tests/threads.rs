@@ -0,0 +1,112 @@
+use common_crypto::cryptor::{Config, Cryptor, Padding};
+use common_crypto::ctr::CTR;
+use common_crypto::hash::{self, Hash};
+use common_crypto::hmac::{self, HMAC};
+use std::thread;
+
+fn assert_send<T: Send>() {}
+fn assert_sync<T: Sync>() {}
+
+#[test]
+fn send_and_sync() {
+    assert_send::<Cryptor>();
+    assert_send::<CTR>();
+
+    assert_send::<hash::SHA1>();
+    assert_send::<hash::SHA256>();
+    assert_send::<hash::SHA512>();
+    assert_send::<hash::MD5>();
+    assert_sync::<hash::SHA1>();
+    assert_sync::<hash::SHA256>();
+    assert_sync::<hash::SHA512>();
+    assert_sync::<hash::MD5>();
+
+    assert_send::<hmac::SHA1>();
+    assert_send::<hmac::SHA256>();
+    assert_send::<hmac::MD5>();
+    assert_sync::<hmac::SHA1>();
+    assert_sync::<hmac::SHA256>();
+    assert_sync::<hmac::MD5>();
+}
+
+#[test]
+fn cryptor_across_threads() {
+    let config = Config::aes256(b"0123456789abcdef0123456789abcdef")
+        .cbc(b"use random iv :)", Padding::PKCS7);
+    let plaintext = [7u8; 100];
+
+    let cryptor = Cryptor::new_encryptor(&config).unwrap();
+    let mut first = Vec::new();
+    cryptor.update(&plaintext[..40], &mut first).unwrap();
+
+    let rest = thread::spawn(move || {
+        let mut output = Vec::new();
+        cryptor.update(&plaintext[40..], &mut output).unwrap();
+        let mut last = Vec::new();
+        cryptor.finish(&mut last).unwrap();
+        output.extend(last);
+        output
+    })
+    .join()
+    .unwrap();
+
+    first.extend(rest);
+    assert_eq!(first, Cryptor::encrypt(&config, plaintext).unwrap());
+}
+
+#[test]
+fn hashers_across_threads() {
+    let mut hasher = hash::SHA256::new();
+    hasher.update(b"data");
+
+    let hash = thread::spawn(move || {
+        hasher.update(b"more data");
+        hasher.finish()
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(hash, Hash::sha256(b"datamore data"));
+
+    let mut hmac = hmac::SHA512::new(b"Key");
+    hmac.update(b"data");
+
+    let auth_code = thread::spawn(move || {
+        hmac.update(b"more data");
+        hmac.finish()
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(auth_code, HMAC::sha512(b"Key", b"datamore data"));
+}
+
+#[test]
+fn many_threads() {
+    let handles: Vec<_> = (0..8u8)
+        .map(|i| {
+            thread::spawn(move || {
+                let data = vec![i; 10_000];
+                let mut hasher = hash::SHA1::new();
+                let mut hmac = hmac::SHA256::new([i; 32]);
+
+                for chunk in data.chunks(1000) {
+                    hasher.update(chunk);
+                    hmac.update(chunk);
+                }
+
+                assert_eq!(hasher.finish(), Hash::sha1(&data));
+                assert_eq!(hmac.finish(), HMAC::sha256([i; 32], &data));
+
+                let key = [i + 1; 16];
+                let config = Config::rc4(&key);
+                let encrypted = Cryptor::encrypt(&config, &data).unwrap();
+                assert_eq!(Cryptor::decrypt(&config, encrypted).unwrap(), data);
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
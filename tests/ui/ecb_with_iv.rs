@@ -0,0 +1,5 @@
+use common_crypto::cryptor::{Config, Padding};
+
+fn main() {
+    Config::aes256(b"0123456789abcdef0123456789abcdef").ecb(b"use random iv :)", Padding::None);
+}
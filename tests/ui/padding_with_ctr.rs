@@ -0,0 +1,5 @@
+use common_crypto::cryptor::{Config, Padding};
+
+fn main() {
+    Config::aes128(b"0123456789abcdef").ctr(b"use random iv :)", Padding::PKCS7);
+}
@@ -0,0 +1,5 @@
+use common_crypto::cryptor::Config;
+
+fn main() {
+    Config::rc4(b"Key").cfb(b"iv bytes");
+}
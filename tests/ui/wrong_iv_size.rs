@@ -0,0 +1,5 @@
+use common_crypto::cryptor::{Config, Padding};
+
+fn main() {
+    Config::tdes(b"0123456789abcdef01234567").cbc(b"use random iv :)", Padding::PKCS7);
+}
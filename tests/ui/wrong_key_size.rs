@@ -0,0 +1,5 @@
+use common_crypto::cryptor::Config;
+
+fn main() {
+    Config::aes256(b"0123456789abcdef").ctr(b"use random iv :)");
+}
@@ -0,0 +1,201 @@
+use common_crypto::encoding::base64_decode;
+use common_crypto::zip::{self, AESStrength, ZIPError};
+
+const FOX: &[u8] = b"The quick brown fox jumps over the lazy dog\n";
+const BOX: &[u8] = b"Pack my box with five dozen liquor jugs\n";
+
+// bsdtar -cf aes128.zip --format zip --passphrase password \
+//     --options zip:compression=store,zip:encryption=aes128 fox.txt
+const AES128: &str = "
+UEsDBBQACQBjACuRUl0AAAAAAAAAAAAAAAAHACsAZm94LnR4dHV4CwABBAAAAAAEAAAAAAGZBwAB
+AEFFAQAAVVQNAAdTC9VqUwvValML1WpngVyYreawePQPOov8AWFPN2ocW7O/lufFLnNwNdKaoHWE
+vxYSeQJQ2BUbTNqVvtIlbgm02prG5RUFvfhrCqKWUEsHCDjBk21AAAAALAAAAFBLAQIUAxQACQBj
+ACuRUl04wZNtQAAAACwAAAAHACMAAAAAAAAAAACkgQAAAABmb3gudHh0dXgLAAEEAAAAAAQAAAAA
+AZkHAAEAQUUBAABVVAUAAVML1WpQSwUGAAAAAAEAAQBYAAAAoAAAAAAA
+";
+
+// bsdtar -cf aes256.zip --format zip --passphrase password \
+//     --options zip:compression=store,zip:encryption=aes256 fox.txt box.txt
+const AES256: &str = "
+UEsDBBQACQBjACuRUl0AAAAAAAAAAAAAAAAHACsAZm94LnR4dHV4CwABBAAAAAAEAAAAAAGZBwAB
+AEFFAwAAVVQNAAdTC9VqUwvValML1Wp7s6O5ST+YpSVDJtvAKIfWL9a+5OO3Mc0Osz8QE/KY+utD
+lsHCQcD+1InDNcTupfFgpjhfnHt/3b6n5eCSh3xp+z3ABk4rfDpQSwcIOMGTbUgAAAAsAAAAUEsD
+BBQACQBjAFORUl0AAAAAAAAAAAAAAAAHACsAYm94LnR4dHV4CwABBAAAAAAEAAAAAAGZBwABAEFF
+AwAAVVQNAAeeC9VqngvVap4L1Wr8XTuVZYp4VJuxI7Evsx4zfdcbX5c7B/IVs1Wn6SZDybC0UHot
+RVgzLqOIyb/IxeerZ9gRHzfV+vjZMMNcOehX4nOCCVBLBwiN44U0RAAAACgAAABQSwECFAMUAAkA
+YwArkVJdOMGTbUgAAAAsAAAABwAjAAAAAAAAAAAApIEAAAAAZm94LnR4dHV4CwABBAAAAAAEAAAA
+AAGZBwABAEFFAwAAVVQFAAFTC9VqUEsBAhQDFAAJAGMAU5FSXY3jhTREAAAAKAAAAAcAIwAAAAAA
+AAAAAKSBqAAAAGJveC50eHR1eAsAAQQAAAAABAAAAAABmQcAAQBBRQMAAFVUBQABngvValBLBQYA
+AAAAAgACALAAAABMAQAAAAA=
+";
+
+// bsdtar -cf deflate.zip --format zip --passphrase password \
+//     --options zip:encryption=aes256 fox.txt
+const DEFLATE: &str = "
+UEsDBBQACQBjACuRUl0AAAAAAAAAAAAAAAAHACsAZm94LnR4dHV4CwABBAAAAAAEAAAAAAGZBwAB
+AEFFAwgAVVQNAAdTC9VqUwvValML1Wrp0mq0DOwPljVInc1D0ja4KaOi3TOSvEDFu6+pwZKQNSDN
+ay1NFoATZnSko/jkzcGhbvXrzGQw07xIgXsjSgdSqq0FVDRy7N5QUEsHCDjBk21JAAAALAAAAFBL
+AQIUAxQACQBjACuRUl04wZNtSQAAACwAAAAHACMAAAAAAAAAAACkgQAAAABmb3gudHh0dXgLAAEE
+AAAAAAQAAAAAAZkHAAEAQUUDCABVVAUAAVML1WpQSwUGAAAAAAEAAQBYAAAAqQAAAAAA
+";
+
+// AES128 changed to AE-2, as WinZip writes small files, with the CRCs zeroed.
+const AE2: &str = "
+UEsDBBQACQBjACuRUl0AAAAAAAAAAAAAAAAHACsAZm94LnR4dHV4CwABBAAAAAAEAAAAAAGZBwAC
+AEFFAQAAVVQNAAdTC9VqUwvValML1WpngVyYreawePQPOov8AWFPN2ocW7O/lufFLnNwNdKaoHWE
+vxYSeQJQ2BUbTNqVvtIlbgm02prG5RUFvfhrCqKWUEsHCAAAAABAAAAALAAAAFBLAQIUAxQACQBj
+ACuRUl0AAAAAQAAAACwAAAAHACMAAAAAAAAAAACkgQAAAABmb3gudHh0dXgLAAEEAAAAAAQAAAAA
+AZkHAAIAQUUBAABVVAUAAVML1WpQSwUGAAAAAAEAAQBYAAAAoAAAAAAA
+";
+
+// bsdtar -cf zipcrypt.zip --format zip --passphrase password \
+//     --options zip:compression=store,zip:encryption=zipcrypt fox.txt
+const ZIPCRYPT: &str = "
+UEsDBBQACQAAACuRUl0AAAAAAAAAAAAAAAAHACAAZm94LnR4dHV4CwABBAAAAAAEAAAAAFVUDQAH
+UwvValML1WpTC9VqiPVZzM4ViJ6i2bSwlL+sK7XGGb4UMn8cpvVjD8OJmHZ0qBBcIVGFh5MggupK
+tOAyqalhM4lL0HlQSwcIOMGTbTgAAAAsAAAAUEsBAhQDFAAJAAAAK5FSXTjBk204AAAALAAAAAcA
+GAAAAAAAAAAAAKSBAAAAAGZveC50eHR1eAsAAQQAAAAABAAAAABVVAUAAVML1WpQSwUGAAAAAAEA
+AQBNAAAAjQAAAAAA
+";
+
+// zip -0 -P password infozip.zip fox.txt
+const INFOZIP: &str = "
+UEsDBAoACQAAACyRUl04wZNtOAAAACwAAAAHABwAZm94LnR4dFVUCQADUwvValML1Wp1eAsAAQQA
+AAAABAAAAADTxuUMpB10mtLKFEeNzQ0U/xZ08Rp38+hrJ5MN48hMsPNRkz8RcgvR3HDezLh9pu9o
+9XBFIpViiFBLBwg4wZNtOAAAACwAAABQSwECHgMKAAkAAAAskVJdOMGTbTgAAAAsAAAABwAYAAAA
+AAAAAAAApIEAAAAAZm94LnR4dFVUBQADUwvVanV4CwABBAAAAAAEAAAAAFBLBQYAAAAAAQABAE0A
+AACJAAAAAAA=
+";
+
+#[test]
+fn crc32() {
+    assert_eq!(zip::crc32(b""), 0);
+    assert_eq!(zip::crc32(b"123456789"), 0xcbf43926);
+    assert_eq!(zip::crc32(FOX), 0x6d93c138);
+}
+
+#[test]
+fn decrypt() {
+    for archive in [AES128, AE2, ZIPCRYPT, INFOZIP] {
+        let archive = base64_decode(archive).unwrap();
+        let entries = zip::entries(&archive).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "fox.txt");
+        assert!(entries[0].is_encrypted());
+        assert_eq!(entries[0].compression_method(), 0);
+        assert_eq!(entries[0].decrypt("password").unwrap(), FOX);
+    }
+}
+
+#[test]
+fn decrypt_entries() {
+    let archive = base64_decode(AES256).unwrap();
+    let entries = zip::entries(&archive).unwrap();
+
+    let names: Vec<_> = entries.iter().map(|entry| entry.name.as_str()).collect();
+    assert_eq!(names, ["fox.txt", "box.txt"]);
+
+    let aes = entries[0].aes.unwrap();
+    assert_eq!(aes.version, 1);
+    assert_eq!(aes.strength, AESStrength::AES256);
+    assert_eq!(entries[0].method, 99);
+
+    assert_eq!(entries[0].decrypt("password").unwrap(), FOX);
+    assert_eq!(entries[1].decrypt("password").unwrap(), BOX);
+}
+
+#[test]
+fn decrypt_deflated() {
+    let archive = base64_decode(DEFLATE).unwrap();
+    let entries = zip::entries(&archive).unwrap();
+
+    // The CRC can only be checked once inflated.
+    assert_eq!(entries[0].compression_method(), 8);
+    let deflated = entries[0].decrypt("password").unwrap();
+    assert_eq!(deflated.len(), entries[0].data.len() - 16 - 2 - 10);
+}
+
+#[test]
+fn wrong_password() {
+    for archive in [AES128, AES256, ZIPCRYPT, INFOZIP] {
+        let archive = base64_decode(archive).unwrap();
+        let entries = zip::entries(&archive).unwrap();
+
+        assert_eq!(entries[0].decrypt("wrong"), Err(ZIPError::Password));
+    }
+}
+
+#[test]
+fn modified() {
+    let mut archive = base64_decode(AES128).unwrap();
+    let entries = zip::entries(&archive).unwrap();
+    let offset = entries[0].data.as_ptr() as usize - archive.as_ptr() as usize;
+
+    // After the salt and password verification value.
+    archive[offset + 8 + 2] ^= 1;
+    let entries = zip::entries(&archive).unwrap();
+    assert_eq!(
+        entries[0].decrypt("password"),
+        Err(ZIPError::Authentication)
+    );
+
+    // Traditional encryption relies on the CRC.
+    let mut archive = base64_decode(ZIPCRYPT).unwrap();
+    let entries = zip::entries(&archive).unwrap();
+    let offset = entries[0].data.as_ptr() as usize - archive.as_ptr() as usize;
+
+    archive[offset + 12] ^= 1;
+    let entries = zip::entries(&archive).unwrap();
+    assert_eq!(entries[0].decrypt("password"), Err(ZIPError::CRC));
+}
+
+#[test]
+fn round_trip() {
+    for strength in [
+        AESStrength::AES128,
+        AESStrength::AES192,
+        AESStrength::AES256,
+    ] {
+        let encrypted = zip::encrypt_aes(strength, "password", FOX).unwrap();
+        assert_eq!(encrypted.len(), strength.salt_len() + 2 + FOX.len() + 10);
+        assert_eq!(
+            zip::decrypt_aes(strength, "password", &encrypted).unwrap(),
+            FOX
+        );
+        assert_ne!(
+            encrypted,
+            zip::encrypt_aes(strength, "password", FOX).unwrap()
+        );
+    }
+
+    let check = (zip::crc32(FOX) >> 24) as u8;
+    let encrypted = zip::encrypt_traditional("password", FOX, check).unwrap();
+    assert_eq!(encrypted.len(), 12 + FOX.len());
+    assert_eq!(
+        zip::decrypt_traditional("password", &encrypted, check).unwrap(),
+        FOX
+    );
+}
+
+#[test]
+fn invalid() {
+    assert_eq!(zip::entries(b""), Err(ZIPError::Format));
+    assert_eq!(zip::entries(FOX), Err(ZIPError::Format));
+
+    let archive = base64_decode(AES256).unwrap();
+    assert_eq!(
+        zip::entries(&archive[..archive.len() - 1]),
+        Err(ZIPError::Format)
+    );
+    assert_eq!(zip::entries(&archive[100..]), Err(ZIPError::Format));
+
+    assert_eq!(
+        zip::decrypt_aes(AESStrength::AES256, "password", [0; 27]),
+        Err(ZIPError::Format)
+    );
+    assert_eq!(
+        zip::decrypt_traditional("password", [0; 11], 0),
+        Err(ZIPError::Format)
+    );
+}